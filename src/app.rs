@@ -1,26 +1,999 @@
 use leptos::prelude::*;
 use leptos::task::spawn_local;
 use leptos_meta::{provide_meta_context, MetaTags, Stylesheet, Title};
-use pulldown_cmark::{Parser, Options, html};
+use pulldown_cmark::{Event, Parser, Options, html};
 use serde::{Deserialize, Serialize};
+use crate::models::*;
+use crate::routes;
 
-/// Convert markdown text to HTML
-fn markdown_to_html(text: &str) -> String {
+/// Convert markdown text to HTML, wrapping matches of `highlight` (if any) in `<mark>`
+fn markdown_to_html(text: &str, highlight: &str) -> String {
+    markdown_to_html_fresh(text, highlight, None)
+}
+
+/// `markdown_to_html`, with the text from byte `fresh_from` on wrapped in
+/// `<span class="token-fresh">` so tokens that just streamed in can fade in
+fn markdown_to_html_fresh(text: &str, highlight: &str, fresh_from: Option<usize>) -> String {
     let mut options = Options::empty();
     options.insert(Options::ENABLE_STRIKETHROUGH);
     options.insert(Options::ENABLE_TABLES);
     options.insert(Options::ENABLE_TASKLISTS);
 
-    let parser = Parser::new_ext(text, options);
+    let marked = |text: &str| -> String {
+        highlight_segments(text, highlight)
+            .into_iter()
+            .map(|(part, hit)| if hit { format!("<mark>{}</mark>", escape_html(&part)) } else { escape_html(&part) })
+            .collect()
+    };
+    let parser = Parser::new_ext(text, options).into_offset_iter().map(|(event, range)| match event {
+        Event::Text(text) => {
+            // Split inside a text event only where it's a literal copy of the source
+            let fresh_at = fresh_from
+                .filter(|from| *from < range.end)
+                .map(|from| from.saturating_sub(range.start))
+                .filter(|at| *at == 0 || (text.len() == range.len() && text.is_char_boundary(*at)));
+            match fresh_at {
+                Some(at) => Event::InlineHtml(
+                    format!("{}<span class=\"token-fresh\">{}</span>", marked(&text[..at]), marked(&text[at..])).into(),
+                ),
+                None if !find_matches(&text, highlight).is_empty() => Event::InlineHtml(marked(&text).into()),
+                None => Event::Text(text),
+            }
+        }
+        event => event,
+    });
     let mut html_output = String::new();
     html::push_html(&mut html_output, parser);
     html_output
 }
 
+/// Escape text for use inside HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Byte ranges of case-insensitive, non-overlapping matches of `query` in `text`
+fn find_matches(text: &str, query: &str) -> Vec<std::ops::Range<usize>> {
+    let needle: Vec<char> = query.chars().collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i + needle.len() <= chars.len() {
+        let hit = needle.iter().zip(&chars[i..]).all(|(q, (_, c))| c.to_lowercase().eq(q.to_lowercase()));
+        if hit {
+            let end = chars.get(i + needle.len()).map_or(text.len(), |(offset, _)| *offset);
+            matches.push(chars[i].0..end);
+            i += needle.len();
+        } else {
+            i += 1;
+        }
+    }
+    matches
+}
+
+/// Split `text` into `(part, is_match)` pieces around matches of `query`
+fn highlight_segments(text: &str, query: &str) -> Vec<(String, bool)> {
+    let mut segments = Vec::new();
+    let mut last = 0;
+    for range in find_matches(text, query) {
+        if range.start > last {
+            segments.push((text[last..range.start].to_string(), false));
+        }
+        segments.push((text[range.clone()].to_string(), true));
+        last = range.end;
+    }
+    if last < text.len() || segments.is_empty() {
+        segments.push((text[last..].to_string(), false));
+    }
+    segments
+}
+
+/// Plain text with matches of `query` wrapped in `<mark>`
+fn highlighted_text(text: &str, query: &str) -> impl IntoView {
+    highlight_segments(text, query)
+        .into_iter()
+        .map(|(part, hit)| if hit { view! { <mark>{part}</mark> }.into_any() } else { part.into_any() })
+        .collect_view()
+}
+
+/// A run of words in a [`word_diff`]
+#[derive(Clone, Debug, PartialEq)]
+enum DiffPart {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Word-by-word differences from `old` to `new` (longest common subsequence).
+/// Very long texts aren't compared word by word; they show as wholly replaced.
+fn word_diff(old: &str, new: &str) -> Vec<DiffPart> {
+    let a: Vec<&str> = old.split_inclusive(char::is_whitespace).collect();
+    let b: Vec<&str> = new.split_inclusive(char::is_whitespace).collect();
+    if a.len() * b.len() > 2_000_000 {
+        return vec![DiffPart::Removed(old.to_string()), DiffPart::Added(new.to_string())];
+    }
+
+    // lcs[i * width + j]: common words between a[i..] and b[j..]
+    let width = b.len() + 1;
+    let mut lcs = vec![0u16; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = if a[i].trim_end() == b[j].trim_end() {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut parts: Vec<DiffPart> = Vec::new();
+    let mut push = |part: DiffPart| match (parts.last_mut(), part) {
+        (Some(DiffPart::Same(text)), DiffPart::Same(more))
+        | (Some(DiffPart::Removed(text)), DiffPart::Removed(more))
+        | (Some(DiffPart::Added(text)), DiffPart::Added(more)) => text.push_str(&more),
+        (_, part) => parts.push(part),
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i].trim_end() == b[j].trim_end() {
+            push(DiffPart::Same(b[j].to_string()));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]) {
+            push(DiffPart::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            push(DiffPart::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    parts
+}
+
+/// Shorten a model name for the header button without splitting multi-byte characters
+fn truncate_model_name(name: &str) -> String {
+    if name.chars().count() > 15 {
+        format!("{}...", name.chars().take(12).collect::<String>())
+    } else {
+        name.to_string()
+    }
+}
+
+/// How long a warmed-up model stays loaded (seconds)
+const WARM_UP_KEEP_ALIVE_SECS: i64 = 60 * 60;
+
+/// Small model offered for a one-click pull when nothing is installed yet
+const STARTER_MODEL: &str = "llama3.2:1b";
+
+/// Maximum number of sent prompts kept for arrow-key recall
+const PROMPT_HISTORY_LIMIT: usize = 50;
+
+/// Earlier answers kept per reply when it's regenerated; older ones are dropped
+const MAX_REPLY_VERSIONS: usize = 5;
+
+/// Characters of a dropped file kept as context unless changed in settings
+const DEFAULT_CONTEXT_MAX_CHARS: usize = 20_000;
+
+/// Largest image accepted from the clipboard; images are kept in the saved conversation
+#[cfg(target_arch = "wasm32")]
+const MAX_PASTED_IMAGE_BYTES: f64 = 5.0 * 1024.0 * 1024.0;
+
+/// The base64 payload of a `data:` URI, which is what Ollama's `images` expects
+fn data_uri_base64(image: &str) -> &str {
+    image.split_once(";base64,").map_or(image, |(_, data)| data)
+}
+
+/// Decode a dropped file as text, or `None` if it looks binary.
+/// `cut` means the bytes were sliced off the end of a longer file, so a
+/// character split at the end is dropped instead of treated as invalid.
+#[cfg(target_arch = "wasm32")]
+fn file_text(bytes: &[u8], cut: bool) -> Option<String> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text.to_string()),
+        Err(e) if cut && e.error_len().is_none() => Some(String::from_utf8_lossy(&bytes[..e.valid_up_to()]).into_owned()),
+        Err(_) => None,
+    }
+}
+
+/// Delay before the next status check, backing off while Ollama is down
+#[cfg(target_arch = "wasm32")]
+fn status_poll_delay_ms(consecutive_failures: u32) -> i32 {
+    match consecutive_failures {
+        0 => 2000,
+        1 => 5000,
+        _ => 15000,
+    }
+}
+
+/// Milliseconds until the next check on the downloads in progress, or none when
+/// there are none to follow. With polling paused for a hidden tab the checks
+/// only slow down: the server stops a pull nobody has asked about for three
+/// minutes, taking its page to be gone.
+#[cfg(any(target_arch = "wasm32", test))]
+fn download_poll_delay_ms(downloads: &[PullProgress], polling_paused: bool) -> Option<i32> {
+    if !downloads.iter().any(|d| !d.done && d.status != "Paused") {
+        return None;
+    }
+    Some(if polling_paused { 30_000 } else { 2000 })
+}
+
+/// The model that should be selected given what's installed: the current one if
+/// it's still there, `llama3` counting as `llama3:latest` (cloud models aren't in
+/// the local list, so they're kept), otherwise the first installed model, or none
+fn reconciled_model(current: Option<&str>, installed: &[String]) -> Option<String> {
+    match current {
+        Some(model) if model.starts_with("cloud:") || installed.iter().any(|m| same_model(model, m)) => Some(model.to_string()),
+        _ => installed.first().cloned(),
+    }
+}
+
+/// How long a toast stays up before dismissing itself
+#[cfg(target_arch = "wasm32")]
+const TOAST_DURATION_MS: i32 = 5000;
+
+/// How long a model marked for deletion can still be restored before it's deleted
+#[cfg(target_arch = "wasm32")]
+const DELETE_UNDO_MS: i32 = 6000;
+
+/// Whether the textarea caret sits on its first line (`up`) or last line, so arrow
+/// keys only browse prompt history when they wouldn't move the caret between lines
+fn caret_on_edge_line(ev: &web_sys::KeyboardEvent, up: bool) -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+        if let Some(textarea) = ev.target().and_then(|t| t.dyn_into::<web_sys::HtmlTextAreaElement>().ok()) {
+            // Selection offsets are in UTF-16 code units
+            let value: Vec<u16> = textarea.value().encode_utf16().collect();
+            let start = textarea.selection_start().ok().flatten().unwrap_or(0) as usize;
+            let end = textarea.selection_end().ok().flatten().unwrap_or(0) as usize;
+            let newline = '\n' as u16;
+            return if up {
+                !value[..start.min(value.len())].contains(&newline)
+            } else {
+                !value[end.min(value.len())..].contains(&newline)
+            };
+        }
+        true
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (ev, up);
+        true
+    }
+}
+
+/// Current time in milliseconds since the Unix epoch
+fn now_millis() -> i64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now() as i64
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// Short relative age like "just now", "5m ago" or "2d ago"
+fn format_relative_time(timestamp_ms: i64, now_ms: i64) -> String {
+    let secs = (now_ms - timestamp_ms).max(0) / 1000;
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+/// Local clock time for a timestamp, used as the tooltip on relative times
+fn format_clock_time(timestamp_ms: i64) -> String {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let date = js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(timestamp_ms as f64));
+        date.to_locale_string("default", &wasm_bindgen::JsValue::UNDEFINED).into()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = timestamp_ms;
+        String::new()
+    }
+}
+
+/// Decode a chunk of a UTF-8 byte stream. A multi-byte character cut off at the end
+/// of the chunk is held back in `pending` and completed by the next call.
+#[cfg(any(target_arch = "wasm32", test))]
+fn decode_utf8_chunk(pending: &mut Vec<u8>, chunk: &[u8]) -> String {
+    pending.extend_from_slice(chunk);
+    let complete = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        // Incomplete sequence at the end: keep it for the next chunk
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        // Genuinely invalid bytes: let the lossy decode replace them
+        Err(_) => pending.len(),
+    };
+    let rest = pending.split_off(complete);
+    let text = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+    text
+}
+
+/// `/api/stream` event format the client understands: every event is named
+/// (`start`, `token`, `thinking`, `tool_calls`, `context`, `status`, `error`, `done`)
+#[cfg(target_arch = "wasm32")]
+const STREAM_PROTOCOL: u32 = 2;
+
+/// A `/api/stream` request still being read: the fetch to abort, and the
+/// server's id for it once the response headers are in
+#[cfg(target_arch = "wasm32")]
+struct ActiveStream {
+    controller: web_sys::AbortController,
+    request_id: Option<String>,
+}
+
+// Streams in progress by key (a conversation, a compare column, the summary),
+// so stopping one leaves the others running
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    static ACTIVE_STREAMS: std::cell::RefCell<HashMap<String, ActiveStream>> = Default::default();
+}
+
+/// Takes a stream out of `ACTIVE_STREAMS` when `stream_chat` returns, unless
+/// it was already stopped or replaced by a newer stream under the same key
+#[cfg(target_arch = "wasm32")]
+struct ActiveStreamGuard {
+    key: String,
+    controller: web_sys::AbortController,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for ActiveStreamGuard {
+    fn drop(&mut self) {
+        ACTIVE_STREAMS.with(|streams| {
+            let mut streams = streams.borrow_mut();
+            if streams.get(&self.key).is_some_and(|s| s.controller == self.controller) {
+                streams.remove(&self.key);
+            }
+        });
+    }
+}
+
+/// Stop the stream running under `key`: abort its fetch, and ask the server to
+/// drop the Ollama request too, in case something in between keeps the connection open.
+/// Returns whether there was one.
+#[cfg(target_arch = "wasm32")]
+fn stop_stream(key: &str) -> bool {
+    use wasm_bindgen::prelude::*;
+
+    let Some(stream) = ACTIVE_STREAMS.with(|streams| streams.borrow_mut().remove(key)) else {
+        return false;
+    };
+    stream.controller.abort();
+    if let (Some(request_id), Some(window)) = (stream.request_id, web_sys::window()) {
+        let opts = web_sys::RequestInit::new();
+        opts.set_method("POST");
+        opts.set_body(&JsValue::from_str(&serde_json::json!({ "request_id": request_id }).to_string()));
+        let headers = web_sys::Headers::new().unwrap();
+        headers.set("Content-Type", "application/json").unwrap();
+        opts.set_headers(&headers);
+        if let Ok(request) = web_sys::Request::new_with_str_and_init(routes::STREAM_CANCEL, &opts) {
+            // Not found just means it had already finished
+            let _ = window.fetch_with_request(&request);
+        }
+    }
+    true
+}
+
+/// Key of the stream for a reply in conversation `id` (`None` for one not saved yet)
+#[cfg(target_arch = "wasm32")]
+fn chat_stream_key(id: Option<&str>) -> String {
+    format!("chat:{}", id.unwrap_or_default())
+}
+
+/// POST a request body to `/api/stream` and hand each SSE event's data to
+/// `on_data` along with its event name. Data is passed through untrimmed so
+/// spaces and newlines inside tokens survive. Keep-alive comments are skipped;
+/// returning `false` stops reading. The stream is registered under `key`
+/// until it ends, so `stop_stream(key)` can cut it short.
+#[cfg(target_arch = "wasm32")]
+async fn stream_chat(key: &str, body: &serde_json::Value, mut on_data: impl FnMut(&str, &str) -> bool) {
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen::JsCast;
+
+    let Some(window) = web_sys::window() else { return };
+    let Ok(controller) = web_sys::AbortController::new() else { return };
+    // A stream still running under this key is replaced, not left to write into the same place
+    let replaced = ACTIVE_STREAMS.with(|streams| {
+        streams.borrow_mut().insert(key.to_string(), ActiveStream { controller: controller.clone(), request_id: None })
+    });
+    if let Some(replaced) = replaced {
+        replaced.controller.abort();
+    }
+    let _guard = ActiveStreamGuard { key: key.to_string(), controller: controller.clone() };
+
+    let mut body = body.clone();
+    body["protocol"] = serde_json::Value::from(STREAM_PROTOCOL);
+    let opts = web_sys::RequestInit::new();
+    opts.set_method("POST");
+    opts.set_body(&JsValue::from_str(&body.to_string()));
+    opts.set_signal(Some(&controller.signal()));
+
+    let headers = web_sys::Headers::new().unwrap();
+    headers.set("Content-Type", "application/json").unwrap();
+    opts.set_headers(&headers);
+
+    let request = web_sys::Request::new_with_str_and_init(routes::STREAM, &opts).unwrap();
+    let Ok(resp) = wasm_bindgen_futures::JsFuture::from(window.fetch_with_request(&request)).await else {
+        return;
+    };
+    let resp: web_sys::Response = resp.dyn_into().unwrap();
+    // Known before any event arrives, so it's there even if the request fails
+    if let Ok(Some(request_id)) = resp.headers().get("x-request-id") {
+        ACTIVE_STREAMS.with(|streams| {
+            if let Some(stream) = streams.borrow_mut().get_mut(key).filter(|s| s.controller == controller) {
+                stream.request_id = Some(request_id.clone());
+            }
+        });
+        on_data("request_id", &request_id);
+    }
+    // A refusal before streaming starts (rate limit, expired sign-in) has no SSE events
+    if !resp.ok() {
+        let text = match resp.text() {
+            Ok(text) => wasm_bindgen_futures::JsFuture::from(text).await.ok().and_then(|t| t.as_string()),
+            Err(_) => None,
+        };
+        let retry_after = resp.headers().get("retry-after").ok().flatten();
+        on_data(
+            "error",
+            &stream_error_message(resp.status(), text.as_deref().unwrap_or_default(), retry_after.as_deref()),
+        );
+        return;
+    }
+    let Some(body) = resp.body() else { return };
+    let reader: web_sys::ReadableStreamDefaultReader = body.get_reader().unchecked_into();
+
+    // Name and data lines of the SSE event being read ("" for plain message data)
+    let mut event_name = String::new();
+    let mut event_data: Vec<String> = Vec::new();
+    // Bytes of a character split across two reads
+    let mut pending_bytes = Vec::new();
+    // Text of a line split across two reads
+    let mut pending_line = String::new();
+
+    loop {
+        let Ok(chunk) = wasm_bindgen_futures::JsFuture::from(reader.read()).await else {
+            break;
+        };
+        let done = js_sys::Reflect::get(&chunk, &JsValue::from_str("done")).unwrap();
+        if done.as_bool().unwrap_or(true) {
+            break;
+        }
+
+        let value = js_sys::Reflect::get(&chunk, &JsValue::from_str("value")).unwrap();
+        let array: js_sys::Uint8Array = value.dyn_into().unwrap();
+        pending_line.push_str(&decode_utf8_chunk(&mut pending_bytes, &array.to_vec()));
+
+        // Parse SSE format, leaving any incomplete last line for the next read
+        let Some(complete) = pending_line.rfind('\n') else { continue };
+        let rest = pending_line.split_off(complete + 1);
+        for line in pending_line.lines() {
+            if line.starts_with(':') {
+                // SSE comment (server keep-alive), nothing to show
+                continue;
+            } else if let Some(name) = line.strip_prefix("event:") {
+                event_name = name.trim().to_string();
+            } else if let Some(data) = line.strip_prefix("data:") {
+                // Only the single space after the colon is framing
+                event_data.push(data.strip_prefix(' ').unwrap_or(data).to_string());
+            } else if line.is_empty() {
+                // A blank line ends the event; multi-line data is joined with newlines
+                let keep_reading = event_data.is_empty() || on_data(&event_name, &event_data.join("\n"));
+                event_name.clear();
+                event_data.clear();
+                if !keep_reading {
+                    return;
+                }
+            }
+        }
+        pending_line = rest;
+    }
+}
+
+/// What to show for an `/api/stream` response with error status `status`.
+/// The 401 body is the sign-in page, so it's replaced by a hint to sign in again.
+#[cfg(any(target_arch = "wasm32", test))]
+fn stream_error_message(status: u16, body: &str, retry_after: Option<&str>) -> String {
+    let mut message = if status == 401 {
+        "Not signed in; reload the page to sign in again".to_string()
+    } else if body.trim().is_empty() {
+        format!("Request failed with status {}", status)
+    } else {
+        body.trim().to_string()
+    };
+    if let Some(secs) = retry_after.and_then(|v| v.trim().parse::<u64>().ok()) {
+        message.push_str(&format!(" (retry in {}s)", secs));
+    }
+    message
+}
+
+/// Quote a string for a POSIX shell
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// `curl` command that sends a `/api/stream` request body straight to Ollama.
+/// `capabilities` are those known per model: like the server, the command asks a
+/// model that can reason to `think`, except in raw mode.
+fn curl_command(base_url: &str, request_json: &str, capabilities: &HashMap<String, Vec<String>>) -> Option<String> {
+    let request: serde_json::Value = serde_json::from_str(request_json).ok()?;
+    let mut body = serde_json::json!({ "model": request["model"], "stream": true });
+    let latest = |role: &str| {
+        request["messages"]
+            .as_array()
+            .and_then(|messages| messages.iter().rev().find(|m| m["role"] == role))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let raw = request["raw"].as_bool().unwrap_or(false);
+    let path = if raw {
+        body["prompt"] = latest("user")["content"].clone();
+        body["raw"] = serde_json::Value::Bool(true);
+        routes::ollama::GENERATE
+    } else if request["endpoint"] == "generate" {
+        let user = latest("user");
+        body["prompt"] = user["content"].clone();
+        if let Some(system) = latest("system")["content"].as_str().filter(|s| !s.is_empty()) {
+            body["system"] = serde_json::Value::from(system);
+        }
+        if user["images"].as_array().is_some_and(|images| !images.is_empty()) {
+            body["images"] = user["images"].clone();
+        }
+        routes::ollama::GENERATE
+    } else {
+        body["messages"] = request["messages"].clone();
+        routes::ollama::CHAT
+    };
+    for key in ["keep_alive", "format", "options", "tools"] {
+        if let Some(value) = request.get(key) {
+            body[key] = value.clone();
+        }
+    }
+    let thinking = request["model"]
+        .as_str()
+        .and_then(|model| capabilities.get(model))
+        .is_some_and(|caps| caps.iter().any(|c| c == "thinking"));
+    if thinking && !raw {
+        body["think"] = serde_json::Value::Bool(true);
+    }
+    Some(format!(
+        "curl --no-buffer {} \\\n  -H 'Content-Type: application/json' \\\n  -d {}",
+        shell_quote(&format!("{}{}", base_url.trim_end_matches('/'), path)),
+        shell_quote(&serde_json::to_string_pretty(&body).ok()?)
+    ))
+}
+
+/// Tab icons showing whether Ollama is running: a green or a red dot
+#[cfg(target_arch = "wasm32")]
+const FAVICON_RUNNING: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 16 16'%3E%3Ccircle cx='8' cy='8' r='7' fill='%232ecc71'/%3E%3C/svg%3E";
+#[cfg(target_arch = "wasm32")]
+const FAVICON_STOPPED: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 16 16'%3E%3Ccircle cx='8' cy='8' r='7' fill='%23e74c3c'/%3E%3C/svg%3E";
+
+/// Point the tab icon at `href`, or with `None` drop ours so the browser's default comes back
+#[cfg(target_arch = "wasm32")]
+fn set_favicon(href: Option<&str>) {
+    const ID: &str = "status-favicon";
+    let Some(document) = web_sys::window().and_then(|w| w.document()) else { return };
+    let existing = document.get_element_by_id(ID);
+    let Some(href) = href else {
+        if let Some(link) = existing {
+            link.remove();
+        }
+        return;
+    };
+    let link = match existing {
+        Some(link) => link,
+        None => {
+            let Ok(link) = document.create_element("link") else { return };
+            link.set_id(ID);
+            let _ = link.set_attribute("rel", "icon");
+            let _ = link.set_attribute("type", "image/svg+xml");
+            if let Some(head) = document.head() {
+                let _ = head.append_child(&link);
+            }
+            link
+        }
+    };
+    let _ = link.set_attribute("href", href);
+}
+
+/// Visible entries of the menu with id `menu_id`, in document order
+#[cfg(target_arch = "wasm32")]
+fn menu_items(menu_id: &str) -> Vec<web_sys::HtmlElement> {
+    use wasm_bindgen::JsCast;
+    let Some(menu) = web_sys::window().and_then(|w| w.document()).and_then(|d| d.get_element_by_id(menu_id)) else {
+        return Vec::new();
+    };
+    let Ok(nodes) = menu.query_selector_all("[role=menuitem], [role=menuitemcheckbox]") else {
+        return Vec::new();
+    };
+    (0..nodes.length())
+        .filter_map(|i| nodes.get(i)?.dyn_into::<web_sys::HtmlElement>().ok())
+        // Entries in collapsed submenus have no layout boxes
+        .filter(|item| item.get_client_rects().length() > 0)
+        .collect()
+}
+
+/// Viewports where the model menu opens as a bottom sheet rather than nested hover panels
+#[cfg(target_arch = "wasm32")]
+const SHEET_MENU_QUERY: &str = "(max-width: 640px), (hover: none) and (pointer: coarse)";
+
+/// Tokens closer together than this skip the typewriter fade: fast streams
+/// would only flicker, and rebuild the animation on every frame
+#[cfg(target_arch = "wasm32")]
+const TYPEWRITER_MIN_GAP_MS: f64 = 40.0;
+
+/// Focus the first entry of a menu that was just opened, once it's rendered
+fn focus_first_menu_item(menu_id: &'static str) {
+    #[cfg(target_arch = "wasm32")]
+    request_animation_frame(move || {
+        if let Some(first) = menu_items(menu_id).first() {
+            let _ = first.focus();
+        }
+    });
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = menu_id;
+}
+
+/// Keyboard handling for a dropdown menu: arrow keys, Home and End move
+/// between its entries, Enter activates one and Escape calls `close`. Focus
+/// goes back to the `trigger_id` button whenever the menu closes.
+fn menu_keydown(ev: &web_sys::KeyboardEvent, menu_id: &'static str, trigger_id: &'static str, close: impl Fn()) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+        let Some(document) = web_sys::window().and_then(|w| w.document()) else { return };
+        let focus_trigger = move || {
+            if let Some(trigger) = document.get_element_by_id(trigger_id).and_then(|t| t.dyn_into::<web_sys::HtmlElement>().ok()) {
+                let _ = trigger.focus();
+            }
+        };
+        let items = menu_items(menu_id);
+        let active = web_sys::window().and_then(|w| w.document()).and_then(|d| d.active_element());
+        let current = active.as_ref().and_then(|a| items.iter().position(|item| item.is_same_node(Some(a))));
+        // Leave text fields and selects inside the menu their own keys
+        let in_field = current.is_none()
+            && active.as_ref().is_some_and(|a| matches!(a.tag_name().as_str(), "INPUT" | "SELECT" | "TEXTAREA"));
+
+        match ev.key().as_str() {
+            "ArrowDown" | "ArrowUp" | "Home" | "End" if !items.is_empty() && !in_field => {
+                ev.prevent_default();
+                let last = items.len() - 1;
+                let next = match (ev.key().as_str(), current) {
+                    ("Home", _) | ("ArrowDown", None) => 0,
+                    ("ArrowDown", Some(i)) => if i == last { 0 } else { i + 1 },
+                    ("ArrowUp", Some(i)) => if i == 0 { last } else { i - 1 },
+                    _ => last,
+                };
+                let _ = items[next].focus();
+            }
+            "Enter" => {
+                let Some(item) = current.map(|i| items[i].clone()) else { return };
+                // Buttons and links already activate on Enter
+                if !matches!(item.tag_name().as_str(), "BUTTON" | "A") {
+                    ev.prevent_default();
+                    item.click();
+                }
+                // If that closed the menu, don't leave focus on a hidden element
+                request_animation_frame(move || {
+                    if item.get_client_rects().length() == 0 {
+                        focus_trigger();
+                    }
+                });
+            }
+            "Escape" => {
+                ev.prevent_default();
+                close();
+                focus_trigger();
+            }
+            _ => {}
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = (ev, menu_id, trigger_id, close);
+}
+
+// Settings written while localStorage is unavailable, kept for this page load only
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    static MEMORY_STORAGE: std::cell::RefCell<HashMap<String, String>> = Default::default();
+    static LOCAL_STORAGE: std::cell::OnceCell<Option<web_sys::Storage>> = const { std::cell::OnceCell::new() };
+}
+
+/// The browser's localStorage if it can actually be written to. Private
+/// browsing and some webviews refuse it; that's logged once and settings
+/// fall back to memory.
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    LOCAL_STORAGE.with(|cell| {
+        cell.get_or_init(|| {
+            let storage = web_sys::window()
+                .and_then(|w| w.local_storage().ok().flatten())
+                .filter(|s| s.set_item("__storage_test__", "1").and_then(|_| s.remove_item("__storage_test__")).is_ok());
+            if storage.is_none() {
+                log::warn!("localStorage is unavailable; settings will only last until the page is reloaded");
+            }
+            storage
+        })
+        .clone()
+    })
+}
+
+/// Read a saved setting
+#[cfg(target_arch = "wasm32")]
+fn storage_get(key: &str) -> Option<String> {
+    MEMORY_STORAGE
+        .with(|memory| memory.borrow().get(key).cloned())
+        .or_else(|| local_storage()?.get_item(key).ok().flatten())
+}
+
+/// Save a setting, keeping it in memory if localStorage refuses it (unavailable or full)
+#[cfg(target_arch = "wasm32")]
+fn storage_set(key: &str, value: &str) {
+    let saved = local_storage().is_some_and(|storage| storage.set_item(key, value).is_ok());
+    MEMORY_STORAGE.with(|memory| {
+        let mut memory = memory.borrow_mut();
+        if saved {
+            memory.remove(key);
+        } else {
+            memory.insert(key.to_string(), value.to_string());
+        }
+    });
+}
+
+/// Approximate bytes this origin has in localStorage (browsers store UTF-16,
+/// two bytes per unit). `None` when localStorage is unavailable.
+#[cfg(target_arch = "wasm32")]
+fn storage_usage() -> Option<u64> {
+    let storage = local_storage()?;
+    let count = storage.length().ok()?;
+    let units: usize = (0..count)
+        .filter_map(|i| storage.key(i).ok().flatten())
+        .map(|key| {
+            let value = storage.get_item(&key).ok().flatten().unwrap_or_default();
+            key.encode_utf16().count() + value.encode_utf16().count()
+        })
+        .sum();
+    Some(units as u64 * 2)
+}
+
+/// Turn the `\n`, `\t` and `\\` escapes in a typed stop sequence into the characters
+/// themselves, since a literal newline can't be typed into a one-per-line list
+fn unescape_stop_sequence(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Title for a saved conversation: the start of its first user message
+#[cfg(target_arch = "wasm32")]
+fn conversation_title(messages: &[ChatMessage]) -> String {
+    let first = messages
+        .iter()
+        .find(|m| m.role == "user")
+        .and_then(|m| m.text.lines().find(|line| !line.trim().is_empty()))
+        .unwrap_or("Untitled")
+        .trim();
+    if first.chars().count() > 60 {
+        format!("{}…", first.chars().take(60).collect::<String>())
+    } else {
+        first.to_string()
+    }
+}
+
+/// Copy text to the system clipboard
+fn copy_to_clipboard(text: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        if let Some(window) = web_sys::window() {
+            let _ = window.navigator().clipboard().write_text(text);
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = text;
+}
+
+/// Save text as a file through the browser's download prompt
+fn download_file(filename: &str, mime: &str, text: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+
+        let Some(document) = web_sys::window().and_then(|w| w.document()) else { return };
+        let Ok(link) = document.create_element("a") else { return };
+        let href = format!("data:{};charset=utf-8,{}", mime, js_sys::encode_uri_component(text));
+        let _ = link.set_attribute("href", &href);
+        let _ = link.set_attribute("download", filename);
+        if let Ok(link) = link.dyn_into::<web_sys::HtmlElement>() {
+            link.click();
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = (filename, mime, text);
+}
+
+/// The conversation as an OpenAI-style `messages` array, for use with other tools.
+/// Tool calls get `call_<n>` ids, and each tool result refers to the call it answers.
+fn openai_messages_json(messages: &[ChatMessage]) -> String {
+    let mut call_count = 0;
+    // Ids of calls still waiting for their result, oldest first
+    let mut open_calls = std::collections::VecDeque::new();
+    let array: Vec<serde_json::Value> = messages
+        .iter()
+        .filter(|m| m.has_content())
+        .map(|m| {
+            let api = m.clone().to_api();
+            let mut message = serde_json::json!({ "role": api.role, "content": api.content });
+            if !api.tool_calls.is_empty() {
+                let calls: Vec<serde_json::Value> = api
+                    .tool_calls
+                    .iter()
+                    .map(|call| {
+                        call_count += 1;
+                        let id = format!("call_{}", call_count);
+                        open_calls.push_back(id.clone());
+                        serde_json::json!({
+                            "id": id,
+                            "type": "function",
+                            "function": {
+                                "name": call["function"]["name"],
+                                // OpenAI sends arguments as a JSON string
+                                "arguments": call["function"]["arguments"].to_string(),
+                            }
+                        })
+                    })
+                    .collect();
+                message["tool_calls"] = serde_json::Value::Array(calls);
+            }
+            if api.role == "tool" {
+                if let Some(id) = open_calls.pop_front() {
+                    message["tool_call_id"] = serde_json::Value::String(id);
+                }
+            }
+            message
+        })
+        .collect();
+    serde_json::to_string_pretty(&array).unwrap_or_default()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReachabilityInfo {
+    pub base_url: String,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A newer Ollama release than the one that's running
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OllamaUpdate {
+    pub current: String,
+    pub latest: String,
+    /// Release notes and downloads
+    pub url: String,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct StatusResponse {
-    pub running: bool,
-    pub models: Vec<String>,
+pub struct DiskSpace {
+    pub path: String,
+    pub free_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Memory a model could be loaded into, for the advisory "may not fit" flag
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MemoryInfo {
+    pub total_ram: u64,
+    pub available_ram: u64,
+    /// Total across NVIDIA GPUs, when `nvidia-smi` is available
+    pub vram: Option<u64>,
+    /// System RAM held by models Ollama has loaded right now, which it unloads to
+    /// make room. Their share in GPU memory is already part of `vram`.
+    pub loaded_ram_bytes: u64,
+}
+
+impl MemoryInfo {
+    /// Rough room for one more model: free RAM, plus the RAM loaded models would
+    /// give back, plus any GPU memory
+    pub fn budget(&self) -> u64 {
+        self.available_ram + self.loaded_ram_bytes + self.vram.unwrap_or(0)
+    }
+
+    /// Advisory only: a model needs about its size on disk plus ~20% for the
+    /// context cache and runtime, which varies with the context length
+    pub fn may_not_fit(&self, model_size: u64) -> bool {
+        model_size.saturating_add(model_size / 5) > self.budget()
+    }
+}
+
+/// Disk use of one installed model, read from its manifest
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelStorage {
+    pub name: String,
+    /// All layers the model needs, including ones shared with other models
+    pub total_bytes: u64,
+    /// Layers no other model uses: what deleting it would free
+    pub unique_bytes: u64,
+}
+
+/// A conversation synced to the server, stored as one JSON file per id
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedConversation {
+    pub id: String,
+    pub title: String,
+    /// Milliseconds since the Unix epoch
+    pub updated_at: i64,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// A saved conversation as listed, without its messages
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub updated_at: i64,
+    pub message_count: usize,
+}
+
+/// Where the models directory is and what takes up space in it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageInfo {
+    pub path: String,
+    /// Every referenced blob counted once
+    pub total_bytes: u64,
+    /// Blobs only one model references
+    pub unique_bytes: u64,
+    /// Blobs more than one model references, counted once
+    pub shared_bytes: u64,
+    pub models: Vec<ModelStorage>,
+    /// Manifests that couldn't be read or parsed and so aren't counted
+    pub skipped_manifests: usize,
+    /// Blobs a manifest references that aren't on disk; the manifest's size is used instead
+    pub missing_blobs: usize,
+    /// Set when the directory couldn't be read (missing, permissions, ...)
+    pub error: Option<String>,
+}
+
+/// The JSON lines Ollama streamed back for one chat request, as received
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RawResponse {
+    pub chunks: Vec<String>,
+    /// More lines arrived than are kept; the rest were dropped
+    pub truncated: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -42,10 +1015,164 @@ pub struct CloudModelsResponse {
     pub models: Vec<CloudModel>,
 }
 
+/// An earlier answer to the same prompt, kept when a reply is regenerated
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplyVersion {
+    pub text: String,
+    #[serde(default)]
+    pub thinking: String,
+    pub model: Option<String>,
+    pub created_at: i64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ChatMessage {
     pub role: String,
     pub text: String,
+    /// Reasoning streamed separately by thinking models
+    #[serde(default)]
+    pub thinking: String,
+    /// Milliseconds since the Unix epoch; for replies, when the reply finished
+    #[serde(default)]
+    pub created_at: i64,
+    /// Model that wrote the reply (`None` for user messages)
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Function calls the model asked for, in Ollama's `tool_calls` format
+    #[serde(default)]
+    pub tool_calls: Vec<serde_json::Value>,
+    /// For `tool` messages: the function whose result this is
+    #[serde(default)]
+    pub tool_name: Option<String>,
+    /// Set on a reply whose prompt overflowed the model's context window (in tokens),
+    /// so Ollama dropped the earliest part of the conversation
+    #[serde(default)]
+    pub context_window: Option<u64>,
+    /// Bookmarked by the user, listed in the pinned replies panel
+    #[serde(default)]
+    pub pinned: bool,
+    /// Answers this reply replaced when regenerated, oldest first
+    #[serde(default)]
+    pub versions: Vec<ReplyVersion>,
+    /// Images pasted into a user message, as `data:` URIs
+    #[serde(default)]
+    pub images: Vec<String>,
+}
+
+impl ChatMessage {
+    /// This message in Ollama's chat format; summary notes go out as system messages
+    pub fn to_api(self) -> ApiMessage {
+        let role = match self.role.as_str() {
+            "user" => "user",
+            "system" => "system",
+            "tool" => "tool",
+            _ => "assistant",
+        };
+        ApiMessage {
+            role: role.to_string(),
+            content: self.text,
+            tool_calls: self.tool_calls,
+            tool_name: self.tool_name,
+            images: self.images.iter().map(|image| data_uri_base64(image).to_string()).collect(),
+        }
+    }
+
+    /// Worth sending upstream: has text or images, asked for a tool, or answers one
+    pub fn has_content(&self) -> bool {
+        !self.text.is_empty() || !self.images.is_empty() || !self.tool_calls.is_empty() || self.role == "tool"
+    }
+}
+
+/// Short-lived notification shown in the corner for background events
+#[derive(Clone, Debug)]
+pub struct Toast {
+    pub id: u64,
+    pub message: String,
+    pub is_error: bool,
+    /// Model whose pending deletion the toast's Undo button cancels
+    pub undo_delete: Option<String>,
+}
+
+/// A model's own running chat, set aside while another model is selected
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ModelChat {
+    pub conversation_id: Option<String>,
+    pub messages: Vec<ChatMessage>,
+    /// Whether the chat was locked to its model
+    pub locked: bool,
+    /// When it was set aside, in ms since the epoch; the oldest go first over the cap
+    #[serde(default)]
+    pub set_aside_at: i64,
+}
+
+/// Set `current` aside as `from`'s chat and take out `to`'s (empty if it has none).
+/// Empty chats aren't kept, so the map only holds models with something to return to.
+fn swap_model_chat(chats: &mut HashMap<String, ModelChat>, from: &str, to: &str, current: ModelChat) -> ModelChat {
+    if current.messages.is_empty() {
+        chats.remove(from);
+    } else {
+        chats.insert(from.to_string(), current);
+    }
+    chats.remove(to).unwrap_or_default()
+}
+
+/// Drop the chats set aside longest ago until they and the current chat come to at
+/// most `max_conversations` (0 keeps everything). Returns how many were dropped.
+fn trim_model_chats(chats: &mut HashMap<String, ModelChat>, max_conversations: usize) -> usize {
+    if max_conversations == 0 {
+        return 0;
+    }
+    let excess = chats.len().saturating_sub(max_conversations - 1);
+    let mut oldest: Vec<(i64, String)> = chats.iter().map(|(model, chat)| (chat.set_aside_at, model.clone())).collect();
+    oldest.sort();
+    for (_, model) in oldest.into_iter().take(excess) {
+        chats.remove(&model);
+    }
+    excess
+}
+
+/// Text file dropped onto the chat, sent ahead of the next message
+#[derive(Clone, Debug)]
+pub struct AttachedFile {
+    pub name: String,
+    pub content: String,
+    pub truncated: bool,
+}
+
+/// One side of the model comparison view, filled in as its stream arrives
+#[derive(Clone, Debug, Default)]
+pub struct CompareColumn {
+    pub model: String,
+    pub text: String,
+    pub streaming: bool,
+    /// Streamed chunks, roughly one per token
+    pub chunks: u32,
+    pub first_chunk_at: Option<i64>,
+    pub finished_at: Option<i64>,
+}
+
+impl CompareColumn {
+    /// Generation speed measured from the first chunk to the last
+    pub fn tokens_per_sec(&self) -> Option<f64> {
+        let elapsed_ms = self.finished_at? - self.first_chunk_at?;
+        (elapsed_ms > 0 && self.chunks > 1).then(|| f64::from(self.chunks - 1) * 1000.0 / elapsed_ms as f64)
+    }
+}
+
+/// One entry of the `messages` array sent to `/api/stream` and on to Ollama's `/api/chat`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ApiMessage {
+    pub role: String,
+    pub content: String,
+    /// Tool calls an assistant turn made, sent back so the model sees what it asked for
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<serde_json::Value>,
+    /// For `tool` messages: the function whose result `content` is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    /// Base64-encoded images for vision models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -144,25 +1271,47 @@ pub async fn test_brave_api(api_token: String) -> Result<BraveSearchResponse, Se
     brave_search("test query".to_string(), api_token).await
 }
 
+/// Ollama address the server talks to (`OLLAMA_HOST`), for building `curl` commands
+#[server]
+pub async fn get_ollama_base_url() -> Result<String, ServerFnError> {
+    Ok(ollama_base_url())
+}
+
+/// How long the `hostname` command gets before the default name is used
+#[cfg(feature = "ssr")]
+const HOSTNAME_COMMAND_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
 #[server]
 pub async fn get_hostname() -> Result<String, ServerFnError> {
-    // Try to get hostname from system
-    if let Ok(hostname) = std::fs::read_to_string("/etc/hostname") {
-        let hostname = hostname.trim().to_string();
-        if !hostname.is_empty() {
-            return Ok(hostname);
+    // An explicit display name wins over whatever the system is called
+    if let Some(name) = display_name_override() {
+        return Ok(name);
+    }
+
+    // Files and the environment first, so the subprocess is rarely needed
+    for path in ["/etc/hostname", "/proc/sys/kernel/hostname"] {
+        if let Ok(hostname) = std::fs::read_to_string(path) {
+            let hostname = hostname.trim().to_string();
+            if !hostname.is_empty() {
+                return Ok(hostname);
+            }
         }
     }
 
     // Fallback: try HOSTNAME env var
     if let Ok(hostname) = std::env::var("HOSTNAME") {
+        let hostname = hostname.trim().to_string();
         if !hostname.is_empty() {
             return Ok(hostname);
         }
     }
 
-    // Fallback: try running hostname command
-    if let Ok(output) = std::process::Command::new("hostname").output() {
+    // Last resort: the hostname command, given up on if it doesn't answer promptly
+    let command = tokio::process::Command::new("hostname")
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    if let Ok(Ok(output)) = tokio::time::timeout(HOSTNAME_COMMAND_TIMEOUT, command).await {
         if output.status.success() {
             let hostname = String::from_utf8_lossy(&output.stdout).trim().to_string();
             if !hostname.is_empty() {
@@ -174,295 +1323,644 @@ pub async fn get_hostname() -> Result<String, ServerFnError> {
     Ok("ollama".to_string())
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct PullProgress {
-    pub model: String,
-    pub status: String,
-    pub percent: f32,
-    pub done: bool,
-    pub error: Option<String>,
-    pub bytes_downloaded: u64,
-    pub speed: String,
-    pub last_update: i64, // timestamp for speed calculation
+/// Name to show instead of the hostname, from `UI_DISPLAY_NAME`; blank values,
+/// control characters and names over 64 characters are ignored
+#[cfg(feature = "ssr")]
+fn display_name_override() -> Option<String> {
+    let name = std::env::var("UI_DISPLAY_NAME").ok()?.trim().to_string();
+    if name.is_empty() || name.chars().count() > 64 || name.chars().any(char::is_control) {
+        return None;
+    }
+    Some(name)
 }
 
-// Global state for tracking pull progress (simple approach using lazy_static would be better but this works)
 use std::sync::OnceLock;
 use std::collections::HashMap;
 use std::sync::Mutex;
 
-static PULL_PROGRESS: OnceLock<Mutex<HashMap<String, PullProgress>>> = OnceLock::new();
+/// Progress readings averaged for a pull's download rate (the last several polls)
+#[cfg(target_arch = "wasm32")]
+const PULL_RATE_SAMPLES: usize = 6;
 
-fn get_progress_store() -> &'static Mutex<HashMap<String, PullProgress>> {
-    PULL_PROGRESS.get_or_init(|| Mutex::new(HashMap::new()))
+/// Readings further apart than this, polling every `poll_delay_ms`, don't belong to
+/// the same stretch of downloading. A few polls' worth, as timers in a hidden tab
+/// can fire up to a minute apart.
+#[cfg(any(target_arch = "wasm32", test))]
+fn pull_rate_max_gap_ms(poll_delay_ms: i32) -> f64 {
+    poll_delay_ms as f64 * 3.0
 }
 
-#[server]
-pub async fn start_model_pull(model_name: String) -> Result<PullProgress, ServerFnError> {
-    use std::process::Command;
+/// Bytes per second over a window of (time in ms, bytes done) readings, or `None`
+/// while there are too few readings or nothing has moved yet
+#[cfg(target_arch = "wasm32")]
+fn pull_rate(window: &std::collections::VecDeque<(f64, u64)>) -> Option<f64> {
+    let (&(first_at, first_bytes), &(last_at, last_bytes)) = (window.front()?, window.back()?);
+    if window.len() < 3 || last_at <= first_at || last_bytes <= first_bytes {
+        return None;
+    }
+    Some((last_bytes - first_bytes) as f64 / ((last_at - first_at) / 1000.0))
+}
 
-    if model_name.trim().is_empty() {
-        return Ok(PullProgress {
-            model: model_name,
-            status: "Error".to_string(),
-            percent: 0.0,
-            done: true,
-            error: Some("Model name cannot be empty".to_string()),
-            bytes_downloaded: 0,
-            speed: "".to_string(),
-            last_update: 0,
-        });
+/// Time left as "45s", "4m 10s" or "1h 5m"
+#[cfg(any(target_arch = "wasm32", test))]
+fn format_eta(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
     }
+}
 
-    // First ensure Ollama is running
-    let status = get_ollama_status().await?;
-    if !status.running {
-        let _ = Command::new("ollama").arg("serve").spawn();
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+/// Download rate and time left, like "12.0 MB/s · 4m 10s left". Time left needs
+/// `download_size` from the registry manifest: Ollama only reports a layer's size
+/// once it gets to it, so the pull's own total falls short until the last layer.
+/// Without it just the rate is shown.
+#[cfg(any(target_arch = "wasm32", test))]
+fn pull_speed_text(rate: Option<f64>, bytes_downloaded: u64, download_size: Option<u64>) -> String {
+    let Some(rate) = rate else {
+        return "estimating…".to_string();
+    };
+    match download_size {
+        Some(size) => format!(
+            "{}/s · {} left",
+            format_bytes(rate as u64),
+            format_eta((size.saturating_sub(bytes_downloaded) as f64 / rate).ceil() as u64)
+        ),
+        None => format!("{}/s", format_bytes(rate as u64)),
     }
+}
 
-    let model = model_name.trim().to_string();
-    let model_clone = model.clone();
+/// Each streamed line pretty-printed on its own; lines that aren't JSON are shown as sent
+fn pretty_raw_chunks(raw: &RawResponse) -> String {
+    let mut text = raw
+        .chunks
+        .iter()
+        .map(|line| {
+            serde_json::from_str::<serde_json::Value>(line)
+                .ok()
+                .and_then(|json| serde_json::to_string_pretty(&json).ok())
+                .unwrap_or_else(|| line.clone())
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if raw.truncated {
+        text.push_str("\n… later chunks were not kept");
+    }
+    text
+}
 
-    // Initialize progress
-    {
-        let store = get_progress_store();
-        let mut map = store.lock().unwrap();
-        map.insert(model.clone(), PullProgress {
-            model: model.clone(),
-            status: "Starting...".to_string(),
-            percent: 0.0,
-            done: false,
-            error: None,
-            bytes_downloaded: 0,
-            speed: "".to_string(),
-            last_update: 0,
-        });
+/// Free space on the disk holding the Ollama models directory
+#[server]
+pub async fn disk_free() -> Result<DiskSpace, ServerFnError> {
+    let dir = ollama_models_dir();
+    // The models directory may not exist yet; measure its nearest existing ancestor
+    let existing = dir.ancestors().find(|p| p.exists()).unwrap_or(std::path::Path::new("/"));
+    let existing = existing.canonicalize().unwrap_or_else(|_| existing.to_path_buf());
+
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let disk = disks
+        .list()
+        .iter()
+        .filter(|d| existing.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .ok_or_else(|| ServerFnError::new("Could not find the disk for the models directory"))?;
+
+    Ok(DiskSpace {
+        path: dir.display().to_string(),
+        free_bytes: disk.available_space(),
+        total_bytes: disk.total_space(),
+    })
+}
+
+/// RAM held by the models in an `/api/ps` reply: each one's size less the part
+/// Ollama put in GPU memory (`size_vram`)
+#[cfg(any(feature = "ssr", test))]
+fn loaded_ram_bytes(ps: &serde_json::Value) -> u64 {
+    ps["models"]
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|m| Some(m["size"].as_u64()?.saturating_sub(m["size_vram"].as_u64().unwrap_or(0))))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// How long `nvidia-smi` gets before the GPU is taken to be absent; a wedged
+/// driver can leave it hanging
+#[cfg(feature = "ssr")]
+const NVIDIA_SMI_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+// Total VRAM, looked up once: it doesn't change while the server runs
+#[cfg(feature = "ssr")]
+static TOTAL_VRAM: OnceLock<Option<u64>> = OnceLock::new();
+
+/// Total memory across NVIDIA GPUs from `nvidia-smi`, `None` without one
+#[cfg(feature = "ssr")]
+async fn total_vram() -> Option<u64> {
+    if let Some(vram) = TOTAL_VRAM.get() {
+        return *vram;
     }
+    // One line per GPU, in MiB
+    let command = tokio::process::Command::new("nvidia-smi")
+        .args(["--query-gpu=memory.total", "--format=csv,noheader,nounits"])
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let vram = tokio::time::timeout(NVIDIA_SMI_TIMEOUT, command)
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| line.trim().parse::<u64>().ok())
+                .sum::<u64>()
+                * 1024
+                * 1024
+        })
+        .filter(|total| *total > 0);
+    *TOTAL_VRAM.get_or_init(|| vram)
+}
 
-    // Start the pull using Ollama API (streams JSON progress)
-    tokio::spawn(async move {
-        let client = reqwest::Client::new();
-        let res = client.post("http://localhost:11434/api/pull")
-            .json(&serde_json::json!({ "name": model_clone }))
-            .send()
-            .await;
-
-        match res {
-            Ok(response) => {
-                use futures::StreamExt;
-                let mut stream = response.bytes_stream();
-
-                while let Some(chunk) = stream.next().await {
-                    if let Ok(bytes) = chunk {
-                        let text = String::from_utf8_lossy(&bytes);
-                        // Parse each line as JSON
-                        for line in text.lines() {
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-                                let store = get_progress_store();
-                                let mut map = store.lock().unwrap();
-
-                                let status_text = json["status"].as_str().unwrap_or("").to_string();
-                                let total = json["total"].as_u64().unwrap_or(0);
-                                let completed = json["completed"].as_u64().unwrap_or(0);
-
-                                // Get previous values to preserve if needed
-                                let prev = map.get(&model_clone).cloned();
-                                let prev_speed = prev.as_ref().map(|p| p.speed.clone()).unwrap_or_default();
-                                let prev_percent = prev.as_ref().map(|p| p.percent).unwrap_or(0.0);
-
-                                let percent = if total > 0 {
-                                    (completed as f32 / total as f32) * 100.0
-                                } else {
-                                    prev_percent // Keep previous percent if no new data
-                                };
+/// RAM from the OS, VRAM from `nvidia-smi` when present, and the RAM held by
+/// models already loaded (`/api/ps`)
+#[server]
+pub async fn system_memory() -> Result<MemoryInfo, ServerFnError> {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+
+    let vram = total_vram().await;
+
+    let loaded_ram_bytes = match ollama_client().get(ollama_url(routes::ollama::PS)).send().await {
+        Ok(response) => response
+            .json::<serde_json::Value>()
+            .await
+            .map(|json| loaded_ram_bytes(&json))
+            .unwrap_or(0),
+        Err(_) => 0,
+    };
 
-                                // Calculate speed from completed bytes, keep previous if no new data
-                                let speed = if total > 0 && completed > 0 {
-                                    format_bytes(completed) + " / " + &format_bytes(total)
-                                } else if !prev_speed.is_empty() {
-                                    prev_speed // Keep previous speed
-                                } else {
-                                    "".to_string()
-                                };
+    Ok(MemoryInfo {
+        total_ram: system.total_memory(),
+        available_ram: system.available_memory(),
+        vram,
+        loaded_ram_bytes,
+    })
+}
 
-                                let is_done = status_text == "success" || json.get("error").is_some();
-                                let error = json["error"].as_str().map(|s| s.to_string());
-
-                                map.insert(model_clone.clone(), PullProgress {
-                                    model: model_clone.clone(),
-                                    status: if is_done && error.is_none() { "Complete".to_string() } else { status_text },
-                                    percent: if is_done && error.is_none() { 100.0 } else { percent },
-                                    done: is_done,
-                                    error,
-                                    bytes_downloaded: completed,
-                                    speed,
-                                    last_update: std::time::SystemTime::now()
-                                        .duration_since(std::time::UNIX_EPOCH)
-                                        .unwrap_or_default()
-                                        .as_secs() as i64,
-                                });
-                            }
-                        }
-                    }
+/// Models directory and a per-model size breakdown. Layers shared between
+/// models are counted once in the total, unlike summing `/api/tags` sizes,
+/// and the total is split into unique and shared bytes.
+#[server]
+pub async fn models_storage_info() -> Result<StorageInfo, ServerFnError> {
+    let dir = ollama_models_dir();
+    let mut info = StorageInfo {
+        path: dir.display().to_string(),
+        total_bytes: 0,
+        unique_bytes: 0,
+        shared_bytes: 0,
+        models: Vec::new(),
+        skipped_manifests: 0,
+        missing_blobs: 0,
+        error: None,
+    };
+
+    let (manifests, skipped) = match read_model_manifests(&dir) {
+        Ok(manifests) => manifests,
+        Err(e) => {
+            info.error = Some(match e.kind() {
+                std::io::ErrorKind::NotFound => "No models directory yet".to_string(),
+                std::io::ErrorKind::PermissionDenied => "No permission to read the models directory".to_string(),
+                _ => format!("Could not read the models directory: {}", e),
+            });
+            return Ok(info);
+        }
+    };
+
+    info.skipped_manifests = skipped;
+
+    // Size on disk of each blob (falling back to the manifest's size) and how many models use it
+    let mut blobs: HashMap<String, (u64, usize)> = HashMap::new();
+    for (_, layers) in &manifests {
+        for (digest, size) in layers {
+            let entry = blobs.entry(digest.clone()).or_insert_with(|| {
+                let blob_path = dir.join("blobs").join(digest.replace(':', "-"));
+                let on_disk = std::fs::metadata(&blob_path).ok().filter(|m| m.is_file()).map(|m| m.len());
+                if on_disk.is_none() {
+                    info.missing_blobs += 1;
                 }
-            }
-            Err(e) => {
-                let store = get_progress_store();
-                let mut map = store.lock().unwrap();
-                map.insert(model_clone.clone(), PullProgress {
-                    model: model_clone,
-                    status: "Error".to_string(),
-                    percent: 0.0,
-                    done: true,
-                    error: Some(e.to_string()),
-                    bytes_downloaded: 0,
-                    speed: "".to_string(),
-                    last_update: 0,
-                });
-            }
+                (on_disk.unwrap_or(*size), 0)
+            });
+            entry.1 += 1;
         }
-    });
+    }
+    for (size, users) in blobs.values() {
+        if *users > 1 {
+            info.shared_bytes += size;
+        } else {
+            info.unique_bytes += size;
+        }
+    }
+    info.total_bytes = info.unique_bytes + info.shared_bytes;
+
+    info.models = manifests
+        .into_iter()
+        .map(|(name, layers)| {
+            let mut model = ModelStorage { name, total_bytes: 0, unique_bytes: 0 };
+            for (digest, _) in &layers {
+                let (size, users) = blobs[digest];
+                model.total_bytes += size;
+                if users == 1 {
+                    model.unique_bytes += size;
+                }
+            }
+            model
+        })
+        .collect();
+    info.models.sort_by(|a, b| b.unique_bytes.cmp(&a.unique_bytes).then_with(|| a.name.cmp(&b.name)));
+    Ok(info)
+}
 
-    Ok(PullProgress {
-        model: model_name.trim().to_string(),
-        status: "Starting...".to_string(),
-        percent: 0.0,
-        done: false,
-        error: None,
-        bytes_downloaded: 0,
-        speed: "".to_string(),
-        last_update: 0,
-    })
+/// Directory for data the server keeps: `UI_DATA_DIR`, else `$XDG_DATA_HOME/ollama-rust`,
+/// else `~/.local/share/ollama-rust`
+#[cfg(feature = "ssr")]
+pub fn data_dir() -> std::path::PathBuf {
+    let from_env = |var: &str| std::env::var(var).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    if let Some(dir) = from_env("UI_DATA_DIR") {
+        return std::path::PathBuf::from(dir);
+    }
+    if let Some(dir) = from_env("XDG_DATA_HOME") {
+        return std::path::Path::new(&dir).join("ollama-rust");
+    }
+    std::path::Path::new(&std::env::var("HOME").unwrap_or_default()).join(".local/share/ollama-rust")
 }
 
-fn format_bytes(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
+/// File a conversation is saved to; ids are limited to letters, digits and `-`
+/// so they can't point outside the conversations directory
+#[cfg(feature = "ssr")]
+fn conversation_path(id: &str) -> Result<std::path::PathBuf, ServerFnError> {
+    if id.is_empty() || id.len() > 64 || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(ServerFnError::new("Invalid conversation id"));
+    }
+    Ok(data_dir().join("conversations").join(format!("{}.json", id)))
+}
 
-    if bytes >= GB {
-        format!("{:.1} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.1} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.1} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} B", bytes)
+/// Save a conversation, replacing any earlier copy. The file is written under a
+/// temporary name and renamed into place, so readers never see a partial write
+/// and concurrent saves of the same id leave one complete version. Sent as
+/// JSON since the messages don't fit in a form body.
+#[server(input = server_fn::codec::Json)]
+pub async fn save_conversation(conversation: SavedConversation) -> Result<(), ServerFnError> {
+    let path = conversation_path(&conversation.id)?;
+    let dir = path.parent().unwrap_or(std::path::Path::new("."));
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Could not create {}: {}", dir.display(), e)))?;
+
+    static NEXT_TMP: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    let tmp = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        conversation.id,
+        std::process::id(),
+        NEXT_TMP.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    ));
+    let json = serde_json::to_vec(&conversation).map_err(|e| ServerFnError::new(e.to_string()))?;
+    if let Err(e) = tokio::fs::write(&tmp, json).await {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(ServerFnError::new(format!("Could not save conversation: {}", e)));
     }
+    tokio::fs::rename(&tmp, &path)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Could not save conversation: {}", e)))
 }
 
+/// A saved conversation, or `None` if there's none with that id
 #[server]
-pub async fn cancel_model_pull(model_name: String) -> Result<bool, ServerFnError> {
-    use std::process::Command;
+pub async fn load_conversation(id: String) -> Result<Option<SavedConversation>, ServerFnError> {
+    let path = conversation_path(&id)?;
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| ServerFnError::new(format!("Saved conversation is damaged: {}", e))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ServerFnError::new(format!("Could not read conversation: {}", e))),
+    }
+}
 
-    let model = model_name.trim().to_string();
+/// Saved conversations, most recently updated first. Unreadable files are skipped.
+#[server]
+pub async fn list_conversations() -> Result<Vec<ConversationSummary>, ServerFnError> {
+    let dir = data_dir().join("conversations");
+    let mut entries = match tokio::fs::read_dir(&dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(ServerFnError::new(format!("Could not read {}: {}", dir.display(), e))),
+    };
 
-    // Mark as cancelled in progress store
-    {
-        let store = get_progress_store();
-        let mut map = store.lock().unwrap();
-        if let Some(progress) = map.get_mut(&model) {
-            progress.done = true;
-            progress.status = "Cancelled".to_string();
-            progress.error = Some("Download cancelled by user".to_string());
+    let mut conversations = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
         }
+        let Ok(bytes) = tokio::fs::read(&path).await else { continue };
+        let Ok(conversation) = serde_json::from_slice::<SavedConversation>(&bytes) else { continue };
+        conversations.push(ConversationSummary {
+            id: conversation.id,
+            title: conversation.title,
+            updated_at: conversation.updated_at,
+            message_count: conversation.messages.len(),
+        });
     }
+    conversations.sort_by_key(|c| std::cmp::Reverse(c.updated_at));
+    Ok(conversations)
+}
 
-    // Kill any running ollama pull process for this model
-    let _ = Command::new("pkill")
-        .args(["-f", &format!("ollama pull {}", model)])
-        .output();
+/// Remove a saved conversation; deleting one that's already gone is fine
+#[server]
+pub async fn delete_conversation(id: String) -> Result<(), ServerFnError> {
+    let path = conversation_path(&id)?;
+    match tokio::fs::remove_file(&path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(ServerFnError::new(format!("Could not delete conversation: {}", e))),
+    }
+}
 
-    Ok(true)
+// Latest Ollama release from GitHub (`(version, release page)`) and when it was fetched
+#[cfg(feature = "ssr")]
+static LATEST_OLLAMA_RELEASE: OnceLock<Mutex<Option<(std::time::Instant, String, String)>>> = OnceLock::new();
+
+/// `major.minor.patch` of a version like `v0.5.7` or `0.6.0-rc1`, for comparing releases
+#[cfg(feature = "ssr")]
+fn version_triple(version: &str) -> [u64; 3] {
+    let mut parts = version
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse::<u64>().unwrap_or(0));
+    [(); 3].map(|_| parts.next().unwrap_or(0))
+}
+
+/// A newer Ollama release than the one running, if there is one. The GitHub
+/// lookup is cached for a day to stay clear of its rate limit; any failure just
+/// means no update is reported.
+#[server]
+pub async fn ollama_update_check() -> Result<Option<OllamaUpdate>, ServerFnError> {
+    let current = ollama_client()
+        .get(ollama_url(routes::ollama::VERSION))
+        .send()
+        .await
+        .ok()
+        .map(|response| response.json::<serde_json::Value>());
+    let current = match current {
+        Some(json) => json.await.ok().and_then(|json| json["version"].as_str().map(|s| s.to_string())),
+        None => None,
+    };
+    let Some(current) = current else { return Ok(None) };
+
+    let store = LATEST_OLLAMA_RELEASE.get_or_init(|| Mutex::new(None));
+    let cached = store
+        .lock()
+        .unwrap()
+        .clone()
+        .filter(|(fetched, _, _)| fetched.elapsed() < std::time::Duration::from_secs(24 * 60 * 60));
+    let (latest, url) = match cached {
+        Some((_, latest, url)) => (latest, url),
+        None => {
+            // Not the Ollama client: Ollama auth headers must not go to GitHub
+            let client = reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(5))
+                .user_agent(concat!("ollama-rust/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .unwrap_or_default();
+            let release = client
+                .get("https://api.github.com/repos/ollama/ollama/releases/latest")
+                .header("Accept", "application/vnd.github+json")
+                .send()
+                .await
+                .ok()
+                .filter(|response| response.status().is_success());
+            let Some(release) = release else { return Ok(None) };
+            let Ok(json) = release.json::<serde_json::Value>().await else { return Ok(None) };
+            let Some(tag) = json["tag_name"].as_str() else { return Ok(None) };
+            let latest = tag.trim_start_matches('v').to_string();
+            let url = json["html_url"]
+                .as_str()
+                .unwrap_or("https://github.com/ollama/ollama/releases/latest")
+                .to_string();
+            *store.lock().unwrap() = Some((std::time::Instant::now(), latest.clone(), url.clone()));
+            (latest, url)
+        }
+    };
+
+    Ok((version_triple(&latest) > version_triple(&current)).then_some(OllamaUpdate { current, latest, url }))
 }
 
+/// Download size of a model from the public Ollama registry manifest, if it can be determined
 #[server]
-pub async fn check_pull_progress(model_name: String) -> Result<PullProgress, ServerFnError> {
-    let model = model_name.trim().to_string();
+pub async fn remote_model_size(model_name: String) -> Result<Option<u64>, ServerFnError> {
+    let name = model_name.trim();
+    let (path, tag) = match name.rsplit_once(':') {
+        Some((path, tag)) if !tag.contains('/') => (path, tag),
+        _ => (name, "latest"),
+    };
+    // Only the default registry is supported; "host.tld/..." names use their own
+    if path.is_empty() || path.split('/').next().is_some_and(|first| first.contains('.')) {
+        return Ok(None);
+    }
+    let path = if path.contains('/') { path.to_string() } else { format!("library/{}", path) };
+
+    // Not the Ollama client: Ollama auth headers must not go to the registry
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .unwrap_or_default();
+    let res = client
+        .get(format!("https://registry.ollama.ai/v2/{}/manifests/{}", path, tag))
+        .header("Accept", "application/vnd.docker.distribution.manifest.v2+json")
+        .send()
+        .await;
+
+    let json = match res {
+        Ok(response) if response.status().is_success() => response.json::<serde_json::Value>().await.ok(),
+        _ => None,
+    };
+    Ok(json.map(|json| {
+        let layers: u64 = json["layers"]
+            .as_array()
+            .map(|arr| arr.iter().filter_map(|l| l["size"].as_u64()).sum())
+            .unwrap_or(0);
+        layers + json["config"]["size"].as_u64().unwrap_or(0)
+    }).filter(|size| *size > 0))
+}
 
-    // Check progress store first
-    {
-        let store = get_progress_store();
-        let map = store.lock().unwrap();
-        if let Some(progress) = map.get(&model) {
-            return Ok(progress.clone());
+// Raw chunks of the most recent chat requests by request id, oldest first
+#[cfg(feature = "ssr")]
+static RAW_RESPONSES: OnceLock<Mutex<std::collections::VecDeque<(String, RawResponse)>>> = OnceLock::new();
+
+/// How many requests' raw chunks are kept for the debug view
+#[cfg(feature = "ssr")]
+const RAW_RESPONSES_KEPT: usize = 16;
+
+/// Lines kept per request; a long reply streams one line per token
+#[cfg(feature = "ssr")]
+const RAW_RESPONSE_MAX_CHUNKS: usize = 4000;
+
+/// Keep one line Ollama streamed for `request_id`, dropping the oldest request
+/// once more than [`RAW_RESPONSES_KEPT`] are held
+#[cfg(feature = "ssr")]
+pub fn record_raw_chunk(request_id: &str, line: &str) {
+    let store = RAW_RESPONSES.get_or_init(|| Mutex::new(std::collections::VecDeque::new()));
+    let mut store = store.lock().unwrap();
+    if store.back().is_none_or(|(id, _)| id != request_id) {
+        match store.iter().position(|(id, _)| id == request_id) {
+            Some(index) => {
+                let entry = store.remove(index).unwrap();
+                store.push_back(entry);
+            }
+            None => {
+                store.push_back((request_id.to_string(), RawResponse { chunks: Vec::new(), truncated: false }));
+                while store.len() > RAW_RESPONSES_KEPT {
+                    store.pop_front();
+                }
+            }
         }
     }
-
-    // Fallback: check if model exists (might have been pulled before tracking)
-    let status = get_ollama_status().await?;
-    let model_exists = status.models.iter().any(|m| {
-        m.starts_with(&model) || m.contains(&model)
-    });
-
-    if model_exists {
-        Ok(PullProgress {
-            model,
-            status: "Complete".to_string(),
-            percent: 100.0,
-            done: true,
-            error: None,
-            bytes_downloaded: 0,
-            speed: "".to_string(),
-            last_update: 0,
-        })
+    let Some((_, response)) = store.back_mut() else { return };
+    if response.chunks.len() < RAW_RESPONSE_MAX_CHUNKS {
+        response.chunks.push(line.to_string());
     } else {
-        Ok(PullProgress {
-            model,
-            status: "Waiting...".to_string(),
-            percent: 0.0,
-            done: false,
-            error: None,
-            bytes_downloaded: 0,
-            speed: "".to_string(),
-            last_update: 0,
-        })
+        response.truncated = true;
     }
 }
 
+/// Raw JSON lines Ollama streamed for a recent chat request, for debugging
+/// replies whose format is surprising
 #[server]
-pub async fn delete_model(model_name: String) -> Result<bool, ServerFnError> {
-    use std::process::Command;
+pub async fn get_raw_response(request_id: String) -> Result<RawResponse, ServerFnError> {
+    let store = RAW_RESPONSES.get_or_init(|| Mutex::new(std::collections::VecDeque::new()));
+    store
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(id, _)| *id == request_id)
+        .map(|(_, response)| response.clone())
+        .ok_or_else(|| ServerFnError::new("No raw response kept for this request"))
+}
 
-    if model_name.trim().is_empty() {
-        return Ok(false);
-    }
+// Chat requests sent on to Ollama that haven't finished yet, from every client
+#[cfg(feature = "ssr")]
+static CHATS_IN_FLIGHT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
 
-    let output = Command::new("ollama")
-        .args(["rm", model_name.trim()])
-        .output();
+/// Counts a chat request as outstanding from when it's sent until it's dropped,
+/// however it ends (done, error, or the client going away)
+#[cfg(feature = "ssr")]
+pub struct ChatInFlight(());
+
+#[cfg(feature = "ssr")]
+impl ChatInFlight {
+    pub fn start() -> Self {
+        CHATS_IN_FLIGHT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Self(())
+    }
+}
 
-    match output {
-        Ok(out) => Ok(out.status.success()),
-        Err(_) => Ok(false),
+#[cfg(feature = "ssr")]
+impl Drop for ChatInFlight {
+    fn drop(&mut self) {
+        CHATS_IN_FLIGHT.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
     }
 }
 
+/// Chat requests currently outstanding on this server, including the caller's own.
+/// Ollama works through them one at a time per model unless `OLLAMA_NUM_PARALLEL` is raised.
 #[server]
-pub async fn get_ollama_status() -> Result<StatusResponse, ServerFnError> {
-    let client = reqwest::Client::new();
+pub async fn chat_queue_depth() -> Result<usize, ServerFnError> {
+    Ok(CHATS_IN_FLIGHT.load(std::sync::atomic::Ordering::Relaxed))
+}
 
-    // Check if Ollama is running by hitting the tags endpoint
-    let res = client.get("http://localhost:11434/api/tags").send().await;
+#[server]
+pub async fn ping_ollama() -> Result<ReachabilityInfo, ServerFnError> {
+    let base_url = ollama_base_url();
+    let started = std::time::Instant::now();
+    let res = ollama_client().get(ollama_url(routes::ollama::VERSION)).send().await;
+    let latency_ms = started.elapsed().as_millis() as u64;
 
     match res {
-        Ok(response) => {
-            if let Ok(json) = response.json::<serde_json::Value>().await {
-                let models: Vec<String> = json["models"]
-                    .as_array()
-                    .map(|arr| {
-                        arr.iter()
-                            .filter_map(|m| m["name"].as_str().map(|s| s.to_string()))
-                            .collect()
-                    })
-                    .unwrap_or_default();
-                Ok(StatusResponse { running: true, models })
-            } else {
-                Ok(StatusResponse { running: true, models: vec![] })
-            }
+        Ok(response) if response.status().is_success() => {
+            let version = response
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|json| json["version"].as_str().map(|s| s.to_string()));
+            Ok(ReachabilityInfo { base_url, reachable: true, latency_ms: Some(latency_ms), version, error: None })
+        }
+        Ok(response) => Ok(ReachabilityInfo {
+            base_url,
+            reachable: false,
+            latency_ms: Some(latency_ms),
+            version: None,
+            error: Some(format!("HTTP {}", response.status())),
+        }),
+        Err(e) => Ok(ReachabilityInfo {
+            base_url,
+            reachable: false,
+            latency_ms: None,
+            version: None,
+            error: Some(e.to_string()),
+        }),
+    }
+}
+
+/// Point `OLLAMA_MODELS` at `path` for the `ollama serve` processes this server
+/// starts, restarting Ollama if it's running so the change takes effect. An empty
+/// path goes back to the directory this server was started with. Models already
+/// downloaded are not moved.
+#[server]
+pub async fn set_models_dir(path: String) -> Result<String, ServerFnError> {
+    let path = path.trim();
+    if path.is_empty() {
+        set_models_dir_override(None);
+    } else {
+        let dir = std::path::Path::new(path);
+        if !dir.is_absolute() {
+            return Err(ServerFnError::new("Use an absolute path"));
+        }
+        if !dir.is_dir() {
+            return Err(ServerFnError::new(format!("{} is not an existing directory", path)));
         }
-        Err(_) => Ok(StatusResponse { running: false, models: vec![] }),
+        // Ollama needs to create blobs and manifests in it
+        let probe = dir.join(".ollama-ui-write-check");
+        std::fs::write(&probe, b"")
+            .and_then(|_| std::fs::remove_file(&probe))
+            .map_err(|e| ServerFnError::new(format!("Cannot write to {}: {}", path, e)))?;
+        set_models_dir_override(Some(dir.to_path_buf()));
+    }
+    let dir = ollama_models_dir().display().to_string();
+    leptos::logging::log!("Models directory set to {}", dir);
+
+    if !get_ollama_status().await?.running {
+        return Ok(format!("Models directory set to {}; it applies when Ollama starts", dir));
+    }
+    let _ = tokio::process::Command::new("pkill").args(["-f", "ollama serve"]).output().await;
+    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+    if get_ollama_status().await?.running {
+        // Not ours to stop, e.g. the system service
+        return Ok(format!(
+            "Models directory set to {}, but Ollama could not be restarted; restart it with OLLAMA_MODELS set to apply",
+            dir
+        ));
+    }
+    match start_ollama_and_wait().await {
+        Ok(()) => Ok(format!("Models directory set to {}; Ollama restarted", dir)),
+        Err(reason) => Err(ServerFnError::new(format!("Models directory set to {}, but {}", dir, reason))),
     }
 }
 
@@ -482,13 +1980,8 @@ pub async fn toggle_ollama_service() -> Result<StatusResponse, ServerFnError> {
         // Give it a moment to stop
         tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
     } else {
-        // Start Ollama serve in background
-        let _ = Command::new("ollama")
-            .arg("serve")
-            .spawn();
-
-        // Give it a moment to start
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        // Start Ollama serve in background and wait for it to come up
+        start_ollama_and_wait().await.map_err(ServerFnError::new)?;
     }
 
     // Return new status
@@ -656,6 +2149,8 @@ pub fn shell(options: LeptosOptions) -> impl IntoView {
                 <AutoReload options=options.clone() />
                 <HydrationScripts options/>
                 <MetaTags/>
+                // Apply the saved density before first paint so the layout doesn't jump
+                <script inner_html="try{var d=localStorage.getItem('density');if(d)document.documentElement.setAttribute('data-density',d)}catch(e){}"></script>
             </head>
             <body>
                 <App/>
@@ -664,25 +2159,303 @@ pub fn shell(options: LeptosOptions) -> impl IntoView {
     }
 }
 
+/// Small badges for the capabilities worth calling out in the model list
+fn capability_badges(capabilities: &[String]) -> impl IntoView {
+    const BADGES: [(&str, &str, &str); 3] = [
+        ("vision", "👁", "Accepts images"),
+        ("tools", "🔧", "Supports tool calling"),
+        ("embedding", "⧉", "Embedding model"),
+    ];
+    BADGES
+        .iter()
+        .filter(|(name, _, _)| capabilities.iter().any(|c| c == name))
+        .map(|(name, icon, title)| view! {
+            <span class=format!("model-badge model-badge-{name}") title=*title>{*icon}</span>
+        })
+        .collect_view()
+}
+
+/// Placeholder rows shaped like model rows, shown while a list loads
+#[component]
+fn SkeletonRows(#[prop(default = 3)] rows: usize) -> impl IntoView {
+    view! {
+        <div class="skeleton-list" aria-busy="true" aria-label="Loading">
+            {(0..rows).map(|i| view! {
+                <div class="skeleton-row" style=format!("width: {}%", 85 - (i % 3) * 20)></div>
+            }).collect_view()}
+        </div>
+    }
+}
+
 #[component]
 pub fn App() -> impl IntoView {
     provide_meta_context();
 
+    view! {
+        <ErrorBoundary fallback=|errors| view! { <AppError errors/> }>
+            <ChatApp/>
+        </ErrorBoundary>
+    }
+}
+
+/// Shown instead of the app when a view inside it fails to render
+#[component]
+fn AppError(errors: ArcRwSignal<Errors>) -> impl IntoView {
+    view! {
+        <div class="app-error" role="alert">
+            <div class="app-error-title">"Something went wrong"</div>
+            <p class="app-error-text">"The page hit an error it couldn't recover from. Reloading usually fixes it; your settings and conversation are kept."</p>
+            <button class="add-model-btn" type="button" on:click=move |_| reload_page()>"Reload"</button>
+            <details class="app-error-details">
+                <summary>"Error details"</summary>
+                <pre>
+                    {move || errors.get().into_iter().map(|(_, e)| e.to_string()).collect::<Vec<_>>().join("\n")}
+                </pre>
+            </details>
+        </div>
+    }
+}
+
+fn reload_page() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        if let Some(window) = web_sys::window() {
+            let _ = window.location().reload();
+        }
+    }
+}
+
+/// Last resort for a panic: the reactive runtime can't be trusted any more, so
+/// the same fallback as `AppError` is written straight into the page instead of
+/// leaving it blank or frozen
+pub fn show_crash_screen(detail: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Some(body) = web_sys::window().and_then(|w| w.document()).and_then(|d| d.body()) else {
+            return;
+        };
+        body.set_inner_html(&crash_screen_html(detail));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = detail;
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+fn crash_screen_html(detail: &str) -> String {
+    format!(
+        "<div class=\"app-error\" role=\"alert\">\
+            <div class=\"app-error-title\">Something went wrong</div>\
+            <p class=\"app-error-text\">The page hit an error it couldn't recover from. Reloading usually fixes it; your settings and conversation are kept.</p>\
+            <button class=\"add-model-btn\" type=\"button\" onclick=\"location.reload()\">Reload</button>\
+            <details class=\"app-error-details\"><summary>Error details</summary><pre>{}</pre></details>\
+        </div>",
+        escape_html(detail)
+    )
+}
+
+#[component]
+fn ChatApp() -> impl IntoView {
     // State
     let (input, set_input) = signal(String::new());
+    let (prompt_history, set_prompt_history) = signal(Vec::<String>::new());
+    let (history_index, set_history_index) = signal::<Option<usize>>(None);
     let (messages, set_messages) = signal(Vec::<ChatMessage>::new());
     let (selected_model, set_selected_model) = signal::<Option<String>>(None);
     let (is_streaming, set_is_streaming) = signal(false);
+    let (render_markdown, set_render_markdown) = signal(true);
+    // Pinned replies panel
+    let (pins_open, set_pins_open) = signal(false);
+    // Focus mode hides the header, toolbar and input so replies get the whole screen
+    let (focus_mode, set_focus_mode) = signal(false);
+    // Whether the chat window is scrolled (nearly) to the end; auto-scroll pauses otherwise
+    let (at_bottom, set_at_bottom) = signal(true);
+    // Model the current conversation is pinned to, if locked
+    let (locked_model, set_locked_model) = signal::<Option<String>>(None);
+    // Give each model its own chat, swapped in when it's selected; on unless turned off,
+    // in which case switching models carries the current chat over
+    let (per_model_chats, set_per_model_chats) = signal(true);
+    // Set for one model switch that should keep the current chat rather than swap it
+    let carry_chat_over = StoredValue::new(false);
+    // Chats of the models not currently selected, by model
+    let model_chats = StoredValue::new(HashMap::<String, ModelChat>::new());
+    // Id the current conversation is saved under, assigned on its first save
+    let (conversation_id, set_conversation_id) = signal::<Option<String>>(None);
+    // Also save conversations on the server, so other browsers can open them
+    let (server_sync, set_server_sync) = signal(false);
+    // Saved conversations panel
+    let (conversations_open, set_conversations_open) = signal(false);
+    let (conversation_list, set_conversation_list) = signal::<Option<Result<Vec<ConversationSummary>, String>>>(None);
+    // Conversation summary, shown in a panel until the user applies or closes it
+    let (summary, set_summary) = signal::<Option<String>>(None);
+    let (summarizing, set_summarizing) = signal(false);
+    // Search within the conversation
+    let (search_open, set_search_open) = signal(false);
+    let (search_query, set_search_query) = signal(String::new());
+    let (search_index, set_search_index) = signal(0usize);
+    let (settings_submenu_open, set_settings_submenu_open) = signal(false);
+    // Chat settings ("" means leave it to Ollama's default)
+    let (keep_alive, set_keep_alive) = signal(String::new());
+    let (json_mode, set_json_mode) = signal(false);
+    let (json_schema, set_json_schema) = signal(String::new());
+    // Function definitions offered to tool-capable models, as a JSON array
+    let (tools_enabled, set_tools_enabled) = signal(false);
+    let (tool_definitions, set_tool_definitions) = signal(String::new());
+    // Results being typed in for the last reply's tool calls, by call index
+    let (tool_results, set_tool_results) = signal(Vec::<String>::new());
+    // "enter" sends on Enter (Shift+Enter for a newline); "ctrl_enter" sends on Ctrl/Cmd+Enter
+    let (send_key, set_send_key) = signal(String::from("enter"));
+    let (show_timestamps, set_show_timestamps) = signal(false);
+    // `[hostname]: ` ahead of each reply; on unless turned off
+    let (show_host_prefix, set_show_host_prefix) = signal(true);
+    // Fade in text as it streams; off unless turned on
+    let (typewriter, set_typewriter) = signal(false);
+    // Stop polling status and slow down download checks while the tab is hidden; on unless turned off
+    let (pause_hidden_polling, set_pause_hidden_polling) = signal(true);
+    // Whether the tab is in the background, from the Page Visibility API
+    let (page_hidden, set_page_hidden) = signal(false);
+    let polling_paused = move || pause_hidden_polling.get() && page_hidden.get();
+    // Where the text added by the latest token starts in the streaming reply, while it should fade in
+    let fresh_from = StoredValue::new(None::<usize>);
+    // Ticks periodically so relative message times stay current
+    let (clock, set_clock) = signal(now_millis());
+    // Reply length cap in tokens ("" leaves Ollama's default)
+    let (num_predict, set_num_predict) = signal(String::new());
+    // Stop sequences, one per line, with `\n` and `\t` escapes
+    let (stop_sequences, set_stop_sequences) = signal(String::new());
+    // GPU placement: layers offloaded and which GPU leads ("" leaves Ollama's choice)
+    let (num_gpu, set_num_gpu) = signal(String::new());
+    let (main_gpu, set_main_gpu) = signal(String::new());
+    let (low_vram, set_low_vram) = signal(false);
+    // Dropped file waiting to go out with the next message
+    let (attached_file, set_attached_file) = signal::<Option<AttachedFile>>(None);
+    // Images pasted into the input for the next message, as `data:` URIs
+    let (pasted_images, set_pasted_images) = signal(Vec::<String>::new());
+    let (context_max_chars, set_context_max_chars) = signal(DEFAULT_CONTEXT_MAX_CHARS);
+    let (drag_over, set_drag_over) = signal(false);
+    // Chat requests outstanding on the server while we stream, our own included
+    let (queue_depth, set_queue_depth) = signal(0usize);
+    // Oldest messages beyond this many are dropped once a reply finishes (0 keeps everything)
+    let (max_messages, set_max_messages) = signal(0usize);
+    // Conversations kept in this browser, the current one and each model's own (0 = no limit)
+    let (max_conversations, set_max_conversations) = signal(0usize);
+    // Shared/kiosk use: forget the conversation after this many idle minutes (0 = never),
+    // and optionally whenever the page is closed or reloaded
+    let (idle_clear_minutes, set_idle_clear_minutes) = signal(0u32);
+    let (clear_on_unload, set_clear_on_unload) = signal(false);
+    // localStorage bytes in use, refreshed when the settings open (`None` if unavailable)
+    let (storage_used, set_storage_used) = signal::<Option<u64>>(None);
+    // Let the server start Ollama if it's off when a message is sent
+    let (auto_start, set_auto_start) = signal(false);
+    // Progress note from the server before the reply starts (e.g. "Starting Ollama...")
+    let (stream_status, set_stream_status) = signal::<Option<String>>(None);
+    // Raw mode sends the prompt verbatim to /api/generate, skipping the chat template
+    let (raw_mode, set_raw_mode) = signal(false);
+    // Ollama endpoint replies come from: "chat" (the conversation) or "generate" (the latest prompt)
+    let (endpoint, set_endpoint) = signal(String::from("chat"));
+    // Debug: pretty-printed body of the most recent chat request
+    let (show_raw_request, set_show_raw_request) = signal(false);
+    let (last_request, set_last_request) = signal::<Option<String>>(None);
+    // Earlier version being compared with the reply it was replaced by: (reply created_at, version index)
+    let (compare_version, set_compare_version) = signal::<Option<(i64, usize)>>(None);
+    // Server-assigned id of that request, to find it in the server logs
+    let (last_request_id, set_last_request_id) = signal::<Option<String>>(None);
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = set_last_request_id;
+    // What Ollama streamed back for that request, fetched once the reply finishes
+    let (last_response, set_last_response) = signal::<Option<Result<RawResponse, String>>>(None);
+    Effect::new(move |_| {
+        if is_streaming.get() || !show_raw_request.get() {
+            return;
+        }
+        let Some(id) = last_request_id.get() else { return };
+        spawn_local(async move {
+            set_last_response.set(Some(get_raw_response(id).await.map_err(|e| e.to_string())));
+        });
+    });
+    // `stream_chat` key of the reply being streamed, which the stop button cancels
+    let reply_stream_key = StoredValue::new(None::<String>);
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = reply_stream_key;
     let (menu_open, set_menu_open) = signal(false);
     let (models_panel_open, set_models_panel_open) = signal(false);
+    // Narrow/touch viewport, or the menu was last opened with a finger: show it as a bottom sheet
+    let (sheet_viewport, set_sheet_viewport) = signal(false);
+    let (touch_opened, set_touch_opened) = signal(false);
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = (set_sheet_viewport, set_touch_opened);
+    let sheet_menu = move || sheet_viewport.get() || touch_opened.get();
     let (ollama_running, set_ollama_running) = signal(false);
     let (toggle_pending, set_toggle_pending) = signal(false);
     let (show_add_model, set_show_add_model) = signal(false);
     let (new_model_name, set_new_model_name) = signal(String::new());
     let (active_downloads, set_active_downloads) = signal::<Vec<PullProgress>>(vec![]);
+    // "Update all": models waiting to be re-pulled one at a time, and the whole batch for the report
+    let (update_queue, set_update_queue) = signal(Vec::<String>::new());
+    let (update_batch, set_update_batch) = signal(Vec::<String>::new());
+    let (disk_space, set_disk_space) = signal::<Option<DiskSpace>>(None);
+    let (pull_warning, set_pull_warning) = signal::<Option<String>>(None);
+    // Model the user chose to pull despite the disk space warning
+    let (pull_confirmed, set_pull_confirmed) = signal::<Option<String>>(None);
+    // Download size of each model pulled, from the registry manifest, for the time left
+    let download_sizes = StoredValue::new(HashMap::<String, u64>::new());
     let (deleting_model, set_deleting_model) = signal::<Option<String>>(None);
+    // Models marked for deletion, hidden until their undo window runs out, with their toast's id
+    let (pending_deletes, set_pending_deletes) = signal(HashMap::<String, u64>::new());
+    // Bulk delete: selection mode in the models panel
+    let (select_mode, set_select_mode) = signal(false);
+    let (selected_for_delete, set_selected_for_delete) = signal(Vec::<String>::new());
+    let (bulk_deleting, set_bulk_deleting) = signal(false);
+    let (copied_model, set_copied_model) = signal::<Option<String>>(None);
+    // Starred models, listed first in the model menu
+    let (favorite_models, set_favorite_models) = signal(Vec::<String>::new());
+    let (warming_models, set_warming_models) = signal(Vec::<String>::new());
+    // Capabilities per installed model; an empty list means no data, so no badges
+    let (model_caps, set_model_caps) = signal(HashMap::<String, Vec<String>>::new());
+    // RAM/VRAM for the "may not fit" flag, and whether flagged models are hidden
+    let (memory, set_memory) = signal::<Option<MemoryInfo>>(None);
+    let (hide_unfit, set_hide_unfit) = signal(false);
+    // Delete what a cancelled pull of a new model left on disk; off by default since pulling again resumes from it
+    let (clean_cancelled_pulls, set_clean_cancelled_pulls) = signal(false);
+
+    // Benchmark panel state
+    let (benchmark_open, set_benchmark_open) = signal(false);
+    let (benchmark_model_name, set_benchmark_model_name) = signal(String::new());
+    let (benchmark_prompt, set_benchmark_prompt) = signal(String::from("Write a short paragraph about the ocean."));
+    let (benchmark_runs, set_benchmark_runs) = signal(3u32);
+    let (benchmark_pending, set_benchmark_pending) = signal(false);
+    let (benchmark_results, set_benchmark_results) = signal(Vec::<BenchmarkResult>::new());
+    // Storage panel state
+    let (storage_open, set_storage_open) = signal(false);
+    let (storage_info, set_storage_info) = signal::<Option<Result<StorageInfo, String>>>(None);
+    let (storage_loading, set_storage_loading) = signal(false);
+    let (models_dir_input, set_models_dir_input) = signal(String::new());
+    let (models_dir_pending, set_models_dir_pending) = signal(false);
+    // Modelfile editor state
+    let (modelfile_open, set_modelfile_open) = signal(false);
+    let (modelfile_source, set_modelfile_source) = signal(String::new());
+    let (modelfile_text, set_modelfile_text) = signal(String::new());
+    let (modelfile_name, set_modelfile_name) = signal(String::new());
+    let (modelfile_loading, set_modelfile_loading) = signal(false);
+    // Models being built from a Modelfile, with the Modelfile, so a failed one is retried by creating again
+    let created_models = StoredValue::new(HashMap::<String, String>::new());
+    // Model comparison panel state
+    let (compare_open, set_compare_open) = signal(false);
+    let (compare_models, set_compare_models) = signal((String::new(), String::new()));
+    let (compare_prompt, set_compare_prompt) = signal(String::new());
+    let (compare_columns, set_compare_columns) = signal([CompareColumn::default(), CompareColumn::default()]);
     let (status_dropdown_open, set_status_dropdown_open) = signal(false);
+    let (diagnostics_open, set_diagnostics_open) = signal(false);
+    let (reachability, set_reachability) = signal::<Option<ReachabilityInfo>>(None);
+    // Opt-in: ask GitHub (through the server) whether a newer Ollama is out
+    let (update_check, set_update_check) = signal(false);
+    let (ollama_update, set_ollama_update) = signal::<Option<OllamaUpdate>>(None);
+    let (last_error, set_last_error) = signal::<Option<String>>(None);
+    // Status checks in a row that found Ollama down or unreachable
+    let (status_failures, set_status_failures) = signal(0u32);
+    let (toasts, set_toasts) = signal(Vec::<Toast>::new());
+    let (next_toast_id, set_next_toast_id) = signal(0u64);
     let (current_theme, set_current_theme) = signal(String::from("light"));
+    let (density, set_density) = signal(String::from("comfortable"));
 
     // Brave Search state
     let (brave_search_enabled, set_brave_search_enabled) = signal(false);
@@ -708,111 +2481,599 @@ pub fn App() -> impl IntoView {
     {
         use wasm_bindgen::JsCast;
         Effect::new(move |_| {
+            // Load theme
+            if let Some(saved_theme) = storage_get("theme") {
+                set_current_theme.set(saved_theme.clone());
+                if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+                    if let Some(body) = document.body() {
+                        let _ = body.set_attribute("data-theme", &saved_theme);
+                    }
+                }
+            }
+            // Load density (already applied to <html> by the inline script in the shell)
+            if let Some(saved_density) = storage_get("density") {
+                set_density.set(saved_density);
+            }
+            // Load Brave Search settings
+            if let Some(enabled) = storage_get("brave_search_enabled") {
+                set_brave_search_enabled.set(enabled == "true");
+            }
+            if let Some(token) = storage_get("brave_api_token") {
+                set_brave_api_token.set(token);
+            }
+            // Load chat settings
+            if let Some(saved_keep_alive) = storage_get("keep_alive") {
+                set_keep_alive.set(saved_keep_alive);
+            }
+            if let Some(enabled) = storage_get("json_mode") {
+                set_json_mode.set(enabled == "true");
+            }
+            if let Some(schema) = storage_get("json_schema") {
+                set_json_schema.set(schema);
+            }
+            if let Some(enabled) = storage_get("tools_enabled") {
+                set_tools_enabled.set(enabled == "true");
+            }
+            if let Some(tools) = storage_get("tools") {
+                set_tool_definitions.set(tools);
+            }
+            if let Some(enabled) = storage_get("show_timestamps") {
+                set_show_timestamps.set(enabled == "true");
+            }
+            if let Some(enabled) = storage_get("show_host_prefix") {
+                set_show_host_prefix.set(enabled == "true");
+            }
+            if let Some(enabled) = storage_get("typewriter") {
+                set_typewriter.set(enabled == "true");
+            }
+            if let Some(enabled) = storage_get("pause_hidden_polling") {
+                set_pause_hidden_polling.set(enabled == "true");
+            }
+            if let Some(saved_send_key) = storage_get("send_key") {
+                set_send_key.set(saved_send_key);
+            }
+            if let Some(saved_num_predict) = storage_get("num_predict") {
+                set_num_predict.set(saved_num_predict);
+            }
+            if let Some(saved_stop) = storage_get("stop_sequences") {
+                set_stop_sequences.set(saved_stop);
+            }
+            if let Some(saved_num_gpu) = storage_get("num_gpu") {
+                set_num_gpu.set(saved_num_gpu);
+            }
+            if let Some(saved_main_gpu) = storage_get("main_gpu") {
+                set_main_gpu.set(saved_main_gpu);
+            }
+            if let Some(enabled) = storage_get("low_vram") {
+                set_low_vram.set(enabled == "true");
+            }
+            if let Some(max_chars) = storage_get("context_max_chars").and_then(|v| v.parse().ok()) {
+                set_context_max_chars.set(max_chars);
+            }
+            if let Some(limit) = storage_get("max_messages").and_then(|v| v.parse().ok()) {
+                set_max_messages.set(limit);
+            }
+            if let Some(limit) = storage_get("max_conversations").and_then(|v| v.parse().ok()) {
+                set_max_conversations.set(limit);
+            }
+            if let Some(minutes) = storage_get("idle_clear_minutes").and_then(|v| v.parse().ok()) {
+                set_idle_clear_minutes.set(minutes);
+            }
+            if let Some(enabled) = storage_get("clear_on_unload") {
+                set_clear_on_unload.set(enabled == "true");
+            }
+            if let Some(enabled) = storage_get("server_sync") {
+                set_server_sync.set(enabled == "true");
+            }
+            if let Some(enabled) = storage_get("update_check") {
+                set_update_check.set(enabled == "true");
+            }
+            if let Some(enabled) = storage_get("hide_unfit_models") {
+                set_hide_unfit.set(enabled == "true");
+            }
+            if let Some(enabled) = storage_get("clean_cancelled_pulls") {
+                set_clean_cancelled_pulls.set(enabled == "true");
+            }
+            if let Some(enabled) = storage_get("per_model_chats") {
+                set_per_model_chats.set(enabled == "true");
+            }
+            if let Some(chats) = storage_get("model_chats")
+                .and_then(|json| serde_json::from_str::<HashMap<String, ModelChat>>(&json).ok())
+            {
+                model_chats.set_value(chats);
+            }
+            // Pick up the conversation where the last page load left it
+            if let Some(saved) = storage_get("conversation")
+                .and_then(|json| serde_json::from_str::<SavedConversation>(&json).ok())
+            {
+                set_conversation_id.set(Some(saved.id));
+                set_messages.set(saved.messages);
+            }
+            if let Some(enabled) = storage_get("auto_start") {
+                set_auto_start.set(enabled == "true");
+            }
+            if let Some(enabled) = storage_get("raw_mode") {
+                set_raw_mode.set(enabled == "true");
+            }
+            if let Some(saved_endpoint) = storage_get("endpoint").filter(|e| e == "chat" || e == "generate") {
+                set_endpoint.set(saved_endpoint);
+            }
+            if let Some(enabled) = storage_get("show_raw_request") {
+                set_show_raw_request.set(enabled == "true");
+            }
+            // Load prompt history
+            if let Some(history) = storage_get("prompt_history") {
+                if let Ok(history) = serde_json::from_str::<Vec<String>>(&history) {
+                    set_prompt_history.set(history);
+                }
+            }
+            // Load favorite models
+            if let Some(favorites) = storage_get("favorite_models") {
+                if let Ok(favorites) = serde_json::from_str::<Vec<String>>(&favorites) {
+                    set_favorite_models.set(favorites);
+                }
+            }
+            // Load last selected model
+            if let Some(saved_model) = storage_get("selected_model") {
+                if !saved_model.is_empty() {
+                    set_selected_model.set(Some(saved_model));
+                }
+            }
+        });
+    }
+
+    // Toasts
+    let dismiss_toast = move |id: u64| {
+        set_toasts.update(|toasts| toasts.retain(|t| t.id != id));
+    };
+
+    let add_toast = move |message: String, is_error: bool, undo_delete: Option<String>| {
+        let id = next_toast_id.get_untracked();
+        set_next_toast_id.set(id + 1);
+        set_toasts.update(|toasts| toasts.push(Toast { id, message, is_error, undo_delete }));
+        id
+    };
+
+    let push_toast = move |message: String, is_error: bool| {
+        let id = add_toast(message, is_error, None);
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            use wasm_bindgen::JsCast;
             if let Some(window) = web_sys::window() {
-                if let Ok(Some(storage)) = window.local_storage() {
-                    // Load theme
-                    if let Ok(Some(saved_theme)) = storage.get_item("theme") {
-                        set_current_theme.set(saved_theme.clone());
-                        if let Some(document) = window.document() {
-                            if let Some(body) = document.body() {
-                                let _ = body.set_attribute("data-theme", &saved_theme);
-                            }
+                let cb = wasm_bindgen::closure::Closure::once_into_js(move || dismiss_toast(id));
+                let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                    cb.unchecked_ref(),
+                    TOAST_DURATION_MS,
+                );
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = id;
+    };
+
+    // Apply density change
+    let apply_density = move |value: String| {
+        set_density.set(value.clone());
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Some(window) = web_sys::window() {
+                storage_set("density", &value);
+                if let Some(document) = window.document() {
+                    if let Some(root) = document.document_element() {
+                        let _ = root.set_attribute("data-density", &value);
+                    }
+                }
+            }
+        }
+    };
+
+    // Refresh relative message times every 30 seconds
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+        if let Some(window) = web_sys::window() {
+            let cb = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
+                set_clock.set(now_millis());
+            }) as Box<dyn Fn()>);
+            let _ = window.set_interval_with_callback_and_timeout_and_arguments_0(
+                cb.as_ref().unchecked_ref(),
+                30_000,
+            );
+            cb.forget();
+        }
+    }
+
+    // In focus mode, Escape restores the layout and typing jumps straight back to the input.
+    // Space, arrows and paging keys are left alone for reading.
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            let cb = wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web_sys::KeyboardEvent| {
+                if !focus_mode.get_untracked() || ev.ctrl_key() || ev.meta_key() || ev.alt_key() {
+                    return;
+                }
+                let key = ev.key();
+                let typing = key.chars().count() == 1 && key != " ";
+                if key != "Escape" && !typing {
+                    return;
+                }
+                set_focus_mode.set(false);
+                if typing {
+                    // Focused before the key lands, so the character goes into the input
+                    let input = web_sys::window()
+                        .and_then(|w| w.document())
+                        .and_then(|d| d.get_element_by_id("prompt-input"))
+                        .and_then(|el| el.dyn_into::<web_sys::HtmlElement>().ok());
+                    if let Some(input) = input {
+                        let _ = input.focus();
+                    }
+                }
+            }) as Box<dyn Fn(web_sys::KeyboardEvent)>);
+            let _ = document.add_event_listener_with_callback("keydown", cb.as_ref().unchecked_ref());
+            cb.forget();
+        }
+    }
+
+    // While a reply streams, check every couple of seconds how many chats the server is handling
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+        if let Some(window) = web_sys::window() {
+            let cb = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
+                if is_streaming.get_untracked() {
+                    spawn_local(async move {
+                        if let Ok(depth) = chat_queue_depth().await {
+                            set_queue_depth.set(depth);
                         }
+                    });
+                } else if queue_depth.get_untracked() != 0 {
+                    set_queue_depth.set(0);
+                }
+            }) as Box<dyn Fn()>);
+            let _ = window.set_interval_with_callback_and_timeout_and_arguments_0(
+                cb.as_ref().unchecked_ref(),
+                2_000,
+            );
+            cb.forget();
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = (set_queue_depth, set_page_hidden, polling_paused);
+
+    // Apply theme change
+    let apply_theme = move |theme: String| {
+        set_current_theme.set(theme.clone());
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Some(window) = web_sys::window() {
+                storage_set("theme", &theme);
+                if let Some(document) = window.document() {
+                    if let Some(body) = document.body() {
+                        let _ = body.set_attribute("data-theme", &theme);
                     }
-                    // Load Brave Search settings
-                    if let Ok(Some(enabled)) = storage.get_item("brave_search_enabled") {
-                        set_brave_search_enabled.set(enabled == "true");
+                }
+            }
+        }
+    };
+
+    // Resources
+    let status_resource = Resource::new(|| (), |_| get_ollama_status());
+    let hostname_resource = Resource::new(|| (), |_| get_hostname());
+    let ollama_base_url_resource = Resource::new(|| (), |_| get_ollama_base_url());
+    let cloud_login_resource = Resource::new(|| (), |_| check_cloud_login());
+    let cloud_models_resource = Resource::new(
+        move || cloud_logged_in.get(),
+        |logged_in| async move {
+            if logged_in {
+                get_cloud_models().await
+            } else {
+                Ok(CloudModelsResponse { models: vec![] })
+            }
+        }
+    );
+
+    // Toggle action
+    let toggle_action = Action::new(move |_: &()| async move {
+        toggle_ollama_service().await
+    });
+
+    // Delete model action
+    let do_delete_model = move |model_name: String| {
+        if model_name.trim().is_empty() {
+            return;
+        }
+
+        set_deleting_model.set(Some(model_name.clone()));
+        // Size before it's gone; layers shared with other models stay on disk, so it's an upper bound
+        let size = status_resource
+            .get_untracked()
+            .and_then(|r| r.ok())
+            .and_then(|status| status.sizes.get(&model_name).copied());
+
+        let model = model_name.clone();
+        spawn_local(async move {
+            match delete_model(model.clone()).await {
+                Ok(true) => {
+                    // Clear selected model if it was deleted
+                    if selected_model.get().as_ref() == Some(&model) {
+                        set_selected_model.set(None);
                     }
-                    if let Ok(Some(token)) = storage.get_item("brave_api_token") {
-                        set_brave_api_token.set(token);
+                    // Refresh models list
+                    status_resource.refetch();
+                    match size {
+                        Some(size) => push_toast(format!("{} deleted, up to {} freed", model, format_bytes(size)), false),
+                        None => push_toast(format!("{} deleted", model), false),
                     }
-                    // Load last selected model
-                    if let Ok(Some(saved_model)) = storage.get_item("selected_model") {
-                        if !saved_model.is_empty() {
-                            set_selected_model.set(Some(saved_model));
+                }
+                Ok(false) => {
+                    set_last_error.set(Some(format!("Failed to delete {}", model)));
+                    push_toast(format!("Delete failed: {}", model), true);
+                }
+                Err(e) => {
+                    set_last_error.set(Some(format!("Failed to delete {}: {}", model, e)));
+                    push_toast(format!("Delete failed: {}: {}", model, e), true);
+                }
+            }
+            set_deleting_model.set(None);
+        });
+    };
+
+    // Hide a model and offer an undo; it's only deleted once the window runs out
+    let mark_for_delete = move |model_name: String| {
+        if model_name.trim().is_empty() || pending_deletes.with_untracked(|p| p.contains_key(&model_name)) {
+            return;
+        }
+        let id = add_toast(format!("Deleting {}…", model_name), false, Some(model_name.clone()));
+        set_pending_deletes.update(|p| {
+            p.insert(model_name.clone(), id);
+        });
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            use wasm_bindgen::JsCast;
+            if let Some(window) = web_sys::window() {
+                let cb = wasm_bindgen::closure::Closure::once_into_js(move || {
+                    // Undone, or marked again since, if the entry isn't ours any more
+                    let mut still_pending = false;
+                    set_pending_deletes.update(|p| {
+                        if p.get(&model_name) == Some(&id) {
+                            p.remove(&model_name);
+                            still_pending = true;
                         }
+                    });
+                    if still_pending {
+                        dismiss_toast(id);
+                        do_delete_model(model_name);
                     }
-                }
+                });
+                let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                    cb.unchecked_ref(),
+                    DELETE_UNDO_MS,
+                );
             }
-        });
-    }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = (id, do_delete_model);
+    };
+
+    // Cancel a pending deletion; the row comes back without touching Ollama
+    let undo_delete = move |model_name: String| {
+        let mut id = None;
+        set_pending_deletes.update(|p| id = p.remove(&model_name));
+        if let Some(id) = id {
+            dismiss_toast(id);
+        }
+    };
+
+    // Delete every selected model, then refresh the list once
+    let do_bulk_delete = move || {
+        let models = selected_for_delete.get();
+        if models.is_empty() || bulk_deleting.get() {
+            return;
+        }
 
-    // Apply theme change
-    let apply_theme = move |theme: String| {
-        set_current_theme.set(theme.clone());
         #[cfg(target_arch = "wasm32")]
         {
+            let sizes = status_resource
+                .get()
+                .and_then(|r| r.ok())
+                .map(|status| status.sizes)
+                .unwrap_or_default();
+            let total: u64 = models.iter().filter_map(|m| sizes.get(m)).sum();
+            let message = format!(
+                "Delete {} model{} ({})?\n\n{}",
+                models.len(),
+                if models.len() == 1 { "" } else { "s" },
+                format_bytes(total),
+                models.join("\n")
+            );
             if let Some(window) = web_sys::window() {
-                if let Ok(Some(storage)) = window.local_storage() {
-                    let _ = storage.set_item("theme", &theme);
+                if !window.confirm_with_message(&message).unwrap_or(false) {
+                    return;
                 }
-                if let Some(document) = window.document() {
-                    if let Some(body) = document.body() {
-                        let _ = body.set_attribute("data-theme", &theme);
+            }
+        }
+
+        set_bulk_deleting.set(true);
+        spawn_local(async move {
+            let mut deleted = 0;
+            for model in &models {
+                set_deleting_model.set(Some(model.clone()));
+                match delete_model(model.clone()).await {
+                    Ok(true) => {
+                        deleted += 1;
+                        if selected_model.get_untracked().as_ref() == Some(model) {
+                            set_selected_model.set(None);
+                        }
                     }
+                    Ok(false) => push_toast(format!("Delete failed: {}", model), true),
+                    Err(e) => push_toast(format!("Delete failed: {}: {}", model, e), true),
                 }
             }
-        }
+            if deleted > 0 {
+                push_toast(format!("Deleted {} of {} models", deleted, models.len()), false);
+            }
+            set_deleting_model.set(None);
+            set_selected_for_delete.set(Vec::new());
+            set_select_mode.set(false);
+            set_bulk_deleting.set(false);
+            status_resource.refetch();
+        });
     };
 
-    // Resources
-    let status_resource = Resource::new(|| (), |_| get_ollama_status());
-    let hostname_resource = Resource::new(|| (), |_| get_hostname());
-    let cloud_login_resource = Resource::new(|| (), |_| check_cloud_login());
-    let cloud_models_resource = Resource::new(
-        move || cloud_logged_in.get(),
-        |logged_in| async move {
-            if logged_in {
-                get_cloud_models().await
+    // Star or unstar a model
+    let toggle_favorite = move |model_name: String| {
+        set_favorite_models.update(|favorites| {
+            if let Some(pos) = favorites.iter().position(|m| m == &model_name) {
+                favorites.remove(pos);
             } else {
-                Ok(CloudModelsResponse { models: vec![] })
+                favorites.push(model_name);
+            }
+        });
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Ok(json) = serde_json::to_string(&favorite_models.get_untracked()) {
+                storage_set("favorite_models", &json);
             }
         }
-    );
+    };
 
-    // Toggle action
-    let toggle_action = Action::new(move |_: &()| async move {
-        toggle_ollama_service().await
-    });
+    // Warm up model action
+    let do_warm_up = move |model_name: String| {
+        if warming_models.get().contains(&model_name) {
+            return;
+        }
+        set_warming_models.update(|models| models.push(model_name.clone()));
 
-    // Delete model action
-    let do_delete_model = move |model_name: String| {
-        if model_name.trim().is_empty() {
+        spawn_local(async move {
+            match warm_up_model(model_name.clone(), WARM_UP_KEEP_ALIVE_SECS).await {
+                Ok(true) => {}
+                Ok(false) => set_last_error.set(Some(format!("{} didn't finish loading within 30 seconds", model_name))),
+                Err(e) => set_last_error.set(Some(format!("Couldn't load {}: {}", model_name, e))),
+            }
+            set_warming_models.update(|models| models.retain(|m| m != &model_name));
+        });
+    };
+
+    // Run a benchmark and keep its result for comparison
+    let do_benchmark = move || {
+        let model = benchmark_model_name.get();
+        let model = if model.is_empty() { selected_model.get().unwrap_or_default() } else { model };
+        if model.is_empty() || benchmark_pending.get() {
             return;
         }
+        set_benchmark_pending.set(true);
+        let prompt = benchmark_prompt.get();
+        let runs = benchmark_runs.get();
+        spawn_local(async move {
+            match benchmark_model(model, prompt, runs).await {
+                Ok(result) => set_benchmark_results.update(|results| results.insert(0, result)),
+                Err(e) => set_last_error.set(Some(e.to_string())),
+            }
+            set_benchmark_pending.set(false);
+        });
+    };
 
-        set_deleting_model.set(Some(model_name.clone()));
+    // Read the models directory breakdown for the storage panel
+    let load_storage = move || {
+        if storage_loading.get_untracked() {
+            return;
+        }
+        set_storage_loading.set(true);
+        spawn_local(async move {
+            set_storage_info.set(Some(models_storage_info().await.map_err(|e| e.to_string())));
+            set_storage_loading.set(false);
+        });
+    };
 
-        let model = model_name.clone();
+    // Move where Ollama keeps models; this restarts Ollama
+    let apply_models_dir = move || {
+        if models_dir_pending.get_untracked() {
+            return;
+        }
+        set_models_dir_pending.set(true);
         spawn_local(async move {
-            if let Ok(success) = delete_model(model.clone()).await {
-                if success {
-                    // Clear selected model if it was deleted
-                    if selected_model.get().as_ref() == Some(&model) {
-                        set_selected_model.set(None);
-                    }
-                    // Refresh models list
-                    status_resource.refetch();
+            match set_models_dir(models_dir_input.get_untracked()).await {
+                Ok(message) => {
+                    push_toast(message, false);
+                    set_models_dir_input.set(String::new());
                 }
+                Err(e) => push_toast(e.to_string(), true),
             }
-            set_deleting_model.set(None);
+            set_models_dir_pending.set(false);
+            status_resource.refetch();
+            load_storage();
         });
     };
 
-    // Start download action
-    let start_download = move |model_name: String| {
+    // Two distinct models and a prompt are needed before comparing
+    let can_compare = move || {
+        let (a, b) = compare_models.get();
+        !a.is_empty() && !b.is_empty() && a != b
+            && !compare_prompt.get().trim().is_empty()
+            && !compare_columns.get().iter().any(|c| c.streaming)
+    };
+
+    // Send the same prompt to both compared models at once
+    let do_compare = move || {
+        if !can_compare() {
+            return;
+        }
+        let (a, b) = compare_models.get();
+        set_compare_columns.set([a, b].map(|model| CompareColumn { model, streaming: true, ..Default::default() }));
+
+        #[cfg(target_arch = "wasm32")]
+        for (side, model) in compare_columns.get_untracked().map(|c| c.model).into_iter().enumerate() {
+            let mut body = serde_json::json!({
+                "model": model,
+                "messages": [ApiMessage { role: "user".to_string(), content: compare_prompt.get_untracked(), ..Default::default() }]
+            });
+            let keep_alive_value = keep_alive.get_untracked();
+            if !keep_alive_value.is_empty() {
+                body["keep_alive"] = serde_json::Value::String(keep_alive_value);
+            }
+            spawn_local(async move {
+                let mut full_text = String::new();
+                stream_chat(&format!("compare:{}", side), &body, |event, data| {
+                    match event {
+                        "token" => full_text.push_str(data),
+                        "error" => full_text.push_str(&format!("[Error: {}]", data)),
+                        "done" => return false,
+                        _ => return true,
+                    }
+                    let current_text = full_text.clone();
+                    set_compare_columns.update(|columns| {
+                        let column = &mut columns[side];
+                        column.text = current_text;
+                        column.chunks += 1;
+                        column.first_chunk_at.get_or_insert_with(now_millis);
+                        column.finished_at = Some(now_millis());
+                    });
+                    true
+                }).await;
+                set_compare_columns.update(|columns| columns[side].streaming = false);
+            });
+        }
+    };
+
+    // Start download action; `update` re-pulls an installed model, which isn't held
+    // to the pull rate limit so "update all" can get through every model
+    let begin_pull = move |model_name: String, update: bool| {
         if model_name.trim().is_empty() {
             return;
         }
 
-        // Check if already downloading
+        // Check if already downloading; a paused pull is resumed by pulling again
         let downloads = active_downloads.get();
-        if downloads.iter().any(|d| d.model == model_name.trim() && !d.done) {
+        if downloads.iter().any(|d| d.model == model_name.trim() && !d.done && d.status != "Paused") {
             return;
         }
 
-        // Add to active downloads
+        // Add to active downloads, replacing a finished entry from an earlier pull
         set_active_downloads.update(|downloads| {
+            downloads.retain(|d| d.model != model_name.trim());
             downloads.push(PullProgress {
                 model: model_name.trim().to_string(),
                 status: "Starting...".to_string(),
@@ -820,31 +3081,260 @@ pub fn App() -> impl IntoView {
                 done: false,
                 error: None,
                 bytes_downloaded: 0,
+                total_bytes: 0,
                 speed: "".to_string(),
                 last_update: 0,
+                raw_status: String::new(),
             });
         });
 
         // Start the pull
         let model = model_name.trim().to_string();
         spawn_local(async move {
-            let _ = start_model_pull(model).await;
+            let started = if update {
+                start_model_update(model.clone()).await
+            } else {
+                start_model_pull(model.clone()).await
+            };
+            if let Err(e) = started {
+                // Settled as failed, so it isn't polled and later reported complete
+                set_active_downloads.update(|downloads| {
+                    if let Some(download) = downloads.iter_mut().find(|d| d.model == model) {
+                        download.status = "Error".to_string();
+                        download.done = true;
+                        download.error = Some(e.to_string());
+                    }
+                });
+                set_last_error.set(Some(e.to_string()));
+                return;
+            }
+            // Looked up every time, since an update may bring different layers
+            let size = remote_model_size(model.clone()).await.ok().flatten();
+            download_sizes.update_value(|sizes| match size {
+                Some(size) => {
+                    sizes.insert(model, size);
+                }
+                None => {
+                    sizes.remove(&model);
+                }
+            });
         });
 
         // Clear input
         set_new_model_name.set(String::new());
         set_show_add_model.set(false);
+        set_pull_warning.set(None);
+        set_pull_confirmed.set(None);
+    };
+    let start_download = move |model_name: String| begin_pull(model_name, false);
+
+    // Load a model's Modelfile into the editor, with a name for the copy to create
+    let load_modelfile = move |model: String| {
+        if model.is_empty() || modelfile_loading.get_untracked() {
+            return;
+        }
+        set_modelfile_source.set(model.clone());
+        set_modelfile_loading.set(true);
+        spawn_local(async move {
+            match get_modelfile(model.clone()).await {
+                Ok(text) => {
+                    set_modelfile_text.set(text);
+                    let base = model.split(':').next().unwrap_or(&model);
+                    set_modelfile_name.set(format!("{}-custom", base));
+                }
+                Err(e) => push_toast(e.to_string(), true),
+            }
+            set_modelfile_loading.set(false);
+        });
+    };
+
+    // Build a model from a Modelfile, followed in the download list like a pull
+    let start_create = move |name: String, modelfile: String| {
+        let name = name.trim().to_string();
+        if name.is_empty() || modelfile_create_fields(&modelfile).is_err() {
+            return;
+        }
+        if active_downloads.get_untracked().iter().any(|d| d.model == name && !d.done) {
+            return;
+        }
+        created_models.update_value(|created| {
+            created.insert(name.clone(), modelfile.clone());
+        });
+        set_active_downloads.update(|downloads| {
+            downloads.retain(|d| d.model != name);
+            downloads.push(PullProgress {
+                model: name.clone(),
+                status: "Creating...".to_string(),
+                percent: 0.0,
+                done: false,
+                error: None,
+                bytes_downloaded: 0,
+                total_bytes: 0,
+                speed: "".to_string(),
+                last_update: 0,
+                raw_status: String::new(),
+            });
+        });
+        spawn_local(async move {
+            if let Err(e) = create_model(name.clone(), modelfile).await {
+                set_active_downloads.update(|downloads| {
+                    if let Some(d) = downloads.iter_mut().find(|d| d.model == name) {
+                        d.status = "Error".to_string();
+                        d.done = true;
+                        d.error = Some(e.to_string());
+                    }
+                });
+            }
+        });
+        set_modelfile_open.set(false);
+    };
+
+    // Re-pull every installed model; Ollama only downloads layers that changed
+    let update_all_models = move || {
+        if !update_batch.get().is_empty() {
+            return;
+        }
+        let models: Vec<String> = status_resource
+            .get()
+            .and_then(|r| r.ok())
+            .map(|status| status.models)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|m| !m.to_lowercase().contains("cloud"))
+            .collect();
+        if models.is_empty() {
+            return;
+        }
+        set_update_batch.set(models.clone());
+        set_update_queue.set(models);
+    };
+
+    // Start the next queued update once nothing else is downloading, and report when done
+    Effect::new(move |_| {
+        let downloads = active_downloads.get();
+        if downloads.iter().any(|d| !d.done) {
+            return;
+        }
+        if let Some(next) = update_queue.get().first().cloned() {
+            set_update_queue.update(|queue| {
+                queue.remove(0);
+            });
+            begin_pull(next, true);
+            return;
+        }
+        let batch = update_batch.get_untracked();
+        if batch.is_empty() {
+            return;
+        }
+        let failed: Vec<String> = batch
+            .iter()
+            .filter(|m| downloads.iter().any(|d| &d.model == *m && d.error.is_some()))
+            .cloned()
+            .collect();
+        if failed.is_empty() {
+            push_toast(format!("Updated {} models", batch.len()), false);
+        } else {
+            push_toast(
+                format!("Updated {} of {} models; failed: {}", batch.len() - failed.len(), batch.len(), failed.join(", ")),
+                true,
+            );
+        }
+        set_update_batch.set(Vec::new());
+    });
+
+    // Stop a pull, then clear away its partial download if that's turned on
+    let cancel_download = move |model: String| {
+        spawn_local(async move {
+            let _ = cancel_model_pull(model.clone()).await;
+            if !clean_cancelled_pulls.get_untracked() {
+                return;
+            }
+            match remove_cancelled_pull(model.clone()).await {
+                Ok(0) => {}
+                Ok(freed) => push_toast(format!("Removed the partial download of {}, {} freed", model, format_bytes(freed)), false),
+                Err(e) => push_toast(format!("Couldn't clean up {}: {}", model, e), true),
+            }
+        });
+    };
+
+    // Cancel every running pull and anything still queued by "update all"
+    let cancel_all_downloads = move || {
+        set_update_queue.set(Vec::new());
+        set_update_batch.set(Vec::new());
+        let running: Vec<String> = active_downloads
+            .get()
+            .into_iter()
+            .filter(|d| !d.done)
+            .map(|d| d.model)
+            .collect();
+        for model in running.clone() {
+            cancel_download(model);
+        }
+        set_active_downloads.update(|downloads| {
+            for d in downloads.iter_mut().filter(|d| running.contains(&d.model)) {
+                d.done = true;
+                d.status = "Cancelled".to_string();
+            }
+        });
+    };
+
+    // Check the model fits on disk before pulling; a second attempt pulls anyway
+    let request_download = move |model_name: String| {
+        let model = model_name.trim().to_string();
+        if model.is_empty() {
+            return;
+        }
+        if pull_confirmed.get().as_ref() == Some(&model) {
+            start_download(model);
+            return;
+        }
+
+        spawn_local(async move {
+            let size = remote_model_size(model.clone()).await.ok().flatten();
+            let space = disk_free().await.ok();
+            if let Some(space) = space.clone() {
+                set_disk_space.set(Some(space));
+            }
+            match (size, space) {
+                (Some(size), Some(space)) if size > space.free_bytes => {
+                    set_pull_warning.set(Some(format!(
+                        "{} needs {}, but only {} is free in {}",
+                        model,
+                        format_bytes(size),
+                        format_bytes(space.free_bytes),
+                        space.path
+                    )));
+                    set_pull_confirmed.set(Some(model));
+                }
+                _ => start_download(model),
+            }
+        });
     };
 
+    // Show current free space whenever the add-model input opens
+    Effect::new(move |_| {
+        if show_add_model.get() {
+            spawn_local(async move {
+                if let Ok(space) = disk_free().await {
+                    set_disk_space.set(Some(space));
+                }
+            });
+        }
+    });
+
     // Poll for download progress
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen::prelude::*;
 
+        // Recent (time in ms, bytes done) readings per pull, for a steadier rate than one sample gives
+        let pull_samples = StoredValue::new(HashMap::<String, std::collections::VecDeque<(f64, u64)>>::new());
+
         let check_progress = move || {
             let downloads = active_downloads.get();
+            let max_gap_ms = download_poll_delay_ms(&downloads, polling_paused()).map_or(0.0, pull_rate_max_gap_ms);
             let pending: Vec<_> = downloads.iter()
-                .filter(|d| !d.done)
+                .filter(|d| !d.done && d.status != "Paused")
                 .map(|d| d.model.clone())
                 .collect();
 
@@ -853,103 +3343,301 @@ pub fn App() -> impl IntoView {
                 spawn_local(async move {
                     if let Ok(progress) = check_pull_progress(model_clone.clone()).await {
                         let is_complete = progress.done && progress.error.is_none();
+                        let failed = progress.done && progress.error.is_some() && progress.status != "Cancelled";
+                        if failed {
+                            push_toast(
+                                format!("{} failed: {}", model_clone, progress.error.clone().unwrap_or_default()),
+                                true,
+                            );
+                        }
+
+                        let now = js_sys::Date::now();
+                        let rate = pull_samples.try_update_value(|samples| {
+                            let window = samples.entry(model_clone.clone()).or_default();
+                            // Start over after a pause or when the byte count goes backwards
+                            if window.back().is_some_and(|&(at, bytes)| now - at > max_gap_ms || bytes > progress.bytes_downloaded) {
+                                window.clear();
+                            }
+                            window.push_back((now, progress.bytes_downloaded));
+                            if window.len() > PULL_RATE_SAMPLES {
+                                window.pop_front();
+                            }
+                            pull_rate(window)
+                        }).flatten();
 
                         set_active_downloads.update(|downloads| {
                             if let Some(d) = downloads.iter_mut().find(|d| d.model == model_clone) {
-                                // Calculate download speed
-                                let now = js_sys::Date::now() as i64;
-                                let time_diff = if d.last_update > 0 { (now - d.last_update) / 1000 } else { 0 };
-                                let percent_diff = progress.percent - d.percent;
-                                
-                                // Estimate speed based on percent change (rough estimate)
-                                let speed_str = if time_diff > 0 && percent_diff > 0.0 {
-                                    // Assume models are roughly 4GB for estimation
-                                    let estimated_bytes = (percent_diff / 100.0) * 4_000_000_000.0;
-                                    let bytes_per_sec = estimated_bytes / (time_diff as f32);
-                                    if bytes_per_sec > 1_000_000_000.0 {
-                                        format!("{:.1} GB/s", bytes_per_sec / 1_000_000_000.0)
-                                    } else if bytes_per_sec > 1_000_000.0 {
-                                        format!("{:.1} MB/s", bytes_per_sec / 1_000_000.0)
-                                    } else if bytes_per_sec > 1_000.0 {
-                                        format!("{:.1} KB/s", bytes_per_sec / 1_000.0)
-                                    } else {
-                                        format!("{:.0} B/s", bytes_per_sec)
-                                    }
+                                let speed_str = if progress.done || progress.total_bytes == 0 {
+                                    String::new()
                                 } else {
-                                    "".to_string()
+                                    let size = download_sizes.with_value(|sizes| sizes.get(&model_clone).copied());
+                                    pull_speed_text(rate, progress.bytes_downloaded, size)
                                 };
 
-                                d.status = progress.status;
-                                d.percent = progress.percent;
-                                d.done = progress.done;
-                                d.error = progress.error;
-                                d.speed = speed_str;
-                                d.last_update = now;
-                            }
-                        });
+                                d.status = progress.status;
+                                d.raw_status = progress.raw_status;
+                                d.percent = progress.percent;
+                                d.done = progress.done;
+                                d.error = progress.error;
+                                d.bytes_downloaded = progress.bytes_downloaded;
+                                d.total_bytes = progress.total_bytes;
+                                d.speed = speed_str;
+                                d.last_update = now as i64;
+                            }
+                        });
+
+                        // Refresh models list when complete
+                        if is_complete {
+                            status_resource.refetch();
+                            // "Update all" reports once at the end instead
+                            if !update_batch.get_untracked().contains(&model_clone) {
+                                let done = if created_models.with_value(|c| c.contains_key(&model_clone)) { "created" } else { "downloaded" };
+                                push_toast(format!("{} {}", model_clone, done), false);
+                            }
+                        }
+                    }
+                });
+            }
+        };
+
+        // Set up interval to check progress, slower while the tab is hidden
+        Effect::new(move |_| {
+            let downloads = active_downloads.get();
+            if let Some(delay) = download_poll_delay_ms(&downloads, polling_paused()) {
+                let cb = Closure::wrap(Box::new(move || {
+                    check_progress();
+                }) as Box<dyn Fn()>);
+
+                if let Some(window) = web_sys::window() {
+                    let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                        cb.as_ref().unchecked_ref(),
+                        delay,
+                    );
+                }
+                cb.forget();
+            }
+        });
+    }
+
+    // Update running state when status loads
+    Effect::new(move |_| {
+        match status_resource.get() {
+            Some(Ok(status)) => {
+                set_ollama_running.set(status.running);
+                if status.running {
+                    set_status_failures.set(0);
+                } else {
+                    set_status_failures.update(|n| *n += 1);
+                }
+            }
+            Some(Err(e)) => {
+                set_last_error.set(Some(e.to_string()));
+                set_status_failures.update(|n| *n += 1);
+            }
+            None => {}
+        }
+    });
+
+    // Tab icon follows Ollama's state once it's known, so several open instances can be told apart
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::prelude::*;
+        use wasm_bindgen::JsCast;
+
+        Effect::new(move |_| {
+            let running = ollama_running.get();
+            if status_resource.with(|status| status.as_ref().is_some_and(|s| s.is_ok())) {
+                set_favicon(Some(if running { FAVICON_RUNNING } else { FAVICON_STOPPED }));
+            }
+        });
+        if let Some(window) = web_sys::window() {
+            let unload = Closure::wrap(Box::new(move || set_favicon(None)) as Box<dyn Fn()>);
+            let _ = window.add_event_listener_with_callback("beforeunload", unload.as_ref().unchecked_ref());
+            unload.forget();
+        }
+    }
+
+    // Follow the sheet media query as the window is resized or rotated
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::prelude::*;
+        use wasm_bindgen::JsCast;
+
+        if let Some(query) = web_sys::window().and_then(|w| w.match_media(SHEET_MENU_QUERY).ok().flatten()) {
+            set_sheet_viewport.set(query.matches());
+            let changed = Closure::wrap(Box::new(move |ev: web_sys::MediaQueryListEvent| {
+                set_sheet_viewport.set(ev.matches());
+            }) as Box<dyn Fn(_)>);
+            let _ = query.add_event_listener_with_callback("change", changed.as_ref().unchecked_ref());
+            changed.forget();
+        }
+    }
+
+    // A sheet has no room for a second floating panel, so it opens straight onto the models
+    Effect::new(move |_| {
+        if menu_open.get() && sheet_menu() {
+            set_models_panel_open.set(true);
+        }
+    });
+
+    // Keep polling status, slowing down while Ollama is down. Any refetch
+    // (toggle, delete, pull) replaces the pending timer so checks never stack up.
+    // While the tab is hidden the timer is dropped, and coming back checks right away.
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            set_page_hidden.set(document.hidden());
+            let changed = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
+                let hidden = web_sys::window().and_then(|w| w.document()).is_some_and(|d| d.hidden());
+                set_page_hidden.set(hidden);
+                if !hidden && pause_hidden_polling.get_untracked() {
+                    status_resource.refetch();
+                }
+            }) as Box<dyn Fn()>);
+            let _ = document.add_event_listener_with_callback("visibilitychange", changed.as_ref().unchecked_ref());
+            changed.forget();
+        }
 
-                        // Refresh models list when complete
-                        if is_complete {
-                            status_resource.refetch();
-                        }
-                    }
-                });
+        let poll_timer = StoredValue::new(None::<i32>);
+        Effect::new(move |_| {
+            if status_resource.get().is_none() {
+                return;
             }
-        };
+            let Some(window) = web_sys::window() else { return };
+            if let Some(handle) = poll_timer.get_value() {
+                window.clear_timeout_with_handle(handle);
+                poll_timer.set_value(None);
+            }
+            if polling_paused() {
+                return;
+            }
+            let cb = wasm_bindgen::closure::Closure::once_into_js(move || {
+                poll_timer.set_value(None);
+                status_resource.refetch();
+            });
+            let delay = status_poll_delay_ms(status_failures.get_untracked());
+            if let Ok(handle) = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                cb.unchecked_ref(),
+                delay,
+            ) {
+                poll_timer.set_value(Some(handle));
+            }
+        });
+    }
 
-        // Set up interval to check progress
-        Effect::new(move |_| {
-            let downloads = active_downloads.get();
-            if downloads.iter().any(|d| !d.done) {
-                let cb = Closure::wrap(Box::new(move || {
-                    check_progress();
-                }) as Box<dyn Fn()>);
+    // Update running state when toggle completes
+    Effect::new(move |_| {
+        match toggle_action.value().get() {
+            Some(Ok(status)) => {
+                set_ollama_running.set(status.running);
+                set_toggle_pending.set(false);
+                push_toast(
+                    if status.running { "Ollama started" } else { "Ollama stopped" }.to_string(),
+                    false,
+                );
+                // Check again right away instead of waiting out the backoff
+                set_status_failures.set(0);
+                status_resource.refetch();
+            }
+            Some(Err(e)) => {
+                set_last_error.set(Some(e.to_string()));
+                set_toggle_pending.set(false);
+                push_toast(format!("Toggle failed: {}", e), true);
+                set_status_failures.set(0);
+                status_resource.refetch();
+            }
+            None => {}
+        }
+    });
 
-                if let Some(window) = web_sys::window() {
-                    let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
-                        cb.as_ref().unchecked_ref(),
-                        2000, // Check every 2 seconds
-                    );
+    // Ping Ollama for the diagnostics section
+    let refresh_reachability = move || {
+        spawn_local(async move {
+            match ping_ollama().await {
+                Ok(info) => {
+                    if let Some(err) = info.error.clone() {
+                        set_last_error.set(Some(err));
+                    }
+                    set_reachability.set(Some(info));
                 }
-                cb.forget();
+                Err(e) => set_last_error.set(Some(e.to_string())),
             }
         });
-    }
+    };
 
-    // Update running state when status loads
+    // Look for an Ollama update once the check is enabled (on load, or when switched on)
     Effect::new(move |_| {
-        if let Some(Ok(status)) = status_resource.get() {
-            set_ollama_running.set(status.running);
+        if !update_check.get() {
+            set_ollama_update.set(None);
+            return;
         }
+        spawn_local(async move {
+            set_ollama_update.set(ollama_update_check().await.ok().flatten());
+        });
     });
 
-    // Update running state when toggle completes
+    // Auto-select model when status loads (respect saved preference or pick first),
+    // and move off a selected model that was removed outside the UI (e.g. `ollama rm`)
     Effect::new(move |_| {
-        if let Some(Ok(status)) = toggle_action.value().get() {
-            set_ollama_running.set(status.running);
-            set_toggle_pending.set(false);
-            // Refetch models after toggle
-            status_resource.refetch();
+        if let Some(Ok(status)) = status_resource.get() {
+            if !status.running {
+                return;
+            }
+            let current = selected_model.get();
+            let reconciled = reconciled_model(current.as_deref(), &status.models);
+            if reconciled == current {
+                return;
+            }
+            if let Some(gone) = &current {
+                push_toast(
+                    match &reconciled {
+                        Some(model) => format!("{} is no longer installed; switched to {}", gone, model),
+                        None => format!("{} is no longer installed and no other models are available", gone),
+                    },
+                    false,
+                );
+            }
+            set_selected_model.set(reconciled);
         }
     });
 
-    // Auto-select model when status loads (respect saved preference or pick first)
+    // Fetch capability badges for models we haven't looked up yet
     Effect::new(move |_| {
         if let Some(Ok(status)) = status_resource.get() {
-            if !status.models.is_empty() {
-                let current = selected_model.get();
-                // If no model selected, or selected model no longer exists, pick one
-                let should_select = match &current {
-                    None => true,
-                    Some(model) => !status.models.iter().any(|m| m == model),
-                };
-                if should_select {
-                    set_selected_model.set(Some(status.models[0].clone()));
-                }
+            let known = model_caps.get_untracked();
+            let missing: Vec<String> = status.models.into_iter().filter(|m| !known.contains_key(m)).collect();
+            if missing.is_empty() {
+                return;
             }
+            spawn_local(async move {
+                let Ok(found) = model_capabilities(missing.clone()).await else {
+                    return;
+                };
+                set_model_caps.update(|caps| {
+                    for model in missing {
+                        let list = found.get(&model).cloned().unwrap_or_default();
+                        caps.insert(model, list);
+                    }
+                });
+            });
         }
     });
 
+    // Refresh memory figures whenever the model list is reloaded or the models menu opens
+    Effect::new(move |_| {
+        let _ = status_resource.get();
+        if !models_panel_open.get() && memory.get_untracked().is_some() {
+            return;
+        }
+        spawn_local(async move {
+            if let Ok(info) = system_memory().await {
+                set_memory.set(Some(info));
+            }
+        });
+    });
+
     // Check cloud login status on load
     Effect::new(move |_| {
         if let Some(Ok(email_opt)) = cloud_login_resource.get() {
@@ -1074,11 +3762,9 @@ pub fn App() -> impl IntoView {
         });
     };
 
-    // Auto-scroll chat window when messages change
-    #[cfg(target_arch = "wasm32")]
-    Effect::new(move |_| {
-        let _ = messages.get(); // Subscribe to messages changes
-        // Use requestAnimationFrame to ensure DOM is updated before scrolling
+    // Scroll the chat window to the newest message, once the DOM has caught up
+    let scroll_to_bottom = move || {
+        #[cfg(target_arch = "wasm32")]
         if let Some(window) = web_sys::window() {
             use wasm_bindgen::prelude::*;
             use wasm_bindgen::JsCast;
@@ -1094,20 +3780,412 @@ pub fn App() -> impl IntoView {
             let _ = window.request_animation_frame(cb.as_ref().unchecked_ref());
             cb.forget();
         }
+    };
+
+    // Auto-scroll chat window when messages change, unless the user scrolled up to read
+    #[cfg(target_arch = "wasm32")]
+    Effect::new(move |_| {
+        let _ = messages.get(); // Subscribe to messages changes
+        if at_bottom.get_untracked() {
+            scroll_to_bottom();
+        }
+    });
+
+    // Drop the oldest messages over the cap once a reply is done; a summary note at the top stays
+    Effect::new(move |_| {
+        let limit = max_messages.get();
+        if limit == 0 || is_streaming.get() {
+            return;
+        }
+        let (start, excess) = messages.with(|msgs| {
+            let start = usize::from(msgs.first().is_some_and(|m| m.role == "system"));
+            (start, (msgs.len() - start).saturating_sub(limit))
+        });
+        if excess > 0 {
+            set_messages.update(|msgs| {
+                msgs.drain(start..start + excess);
+            });
+            push_toast(format!("Dropped the {} oldest message{} (limit {})", excess, if excess == 1 { "" } else { "s" }, limit), false);
+        }
+    });
+
+    // Refresh the storage readout whenever the settings are opened
+    Effect::new(move |_| {
+        if settings_submenu_open.get() {
+            #[cfg(target_arch = "wasm32")]
+            set_storage_used.set(storage_usage());
+            #[cfg(not(target_arch = "wasm32"))]
+            let _ = set_storage_used;
+        }
     });
 
+    // JSON mode request format: "json", or the schema object when one is given
+    let json_format = move || -> Result<Option<serde_json::Value>, String> {
+        if !json_mode.get() {
+            return Ok(None);
+        }
+        let schema = json_schema.get();
+        if schema.trim().is_empty() {
+            return Ok(Some(serde_json::Value::String("json".to_string())));
+        }
+        match serde_json::from_str::<serde_json::Value>(&schema) {
+            Ok(value) if value.is_object() => Ok(Some(value)),
+            Ok(_) => Err("JSON schema must be an object".to_string()),
+            Err(e) => Err(format!("Invalid JSON schema: {}", e)),
+        }
+    };
+
+    // Reply length cap: -1 is unlimited, 0 only loads the model
+    let num_predict_value = move || -> Result<Option<i64>, String> {
+        let value = num_predict.get();
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        match value.parse::<i64>() {
+            Ok(n) if n >= -1 => Ok(Some(n)),
+            _ => Err("Max tokens must be a whole number, or -1 for unlimited".to_string()),
+        }
+    };
+
+    // `num_gpu`, `main_gpu` and `low_vram` options; unset ones are left to Ollama
+    let gpu_options_value = move || -> Result<serde_json::Map<String, serde_json::Value>, String> {
+        let mut options = serde_json::Map::new();
+        for (key, label, value) in [("num_gpu", "GPU layers", num_gpu.get()), ("main_gpu", "Main GPU", main_gpu.get())] {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match value.parse::<u32>() {
+                Ok(n) => options.insert(key.to_string(), serde_json::Value::from(n)),
+                Err(_) => return Err(format!("{} must be a whole number, 0 or more", label)),
+            };
+        }
+        if low_vram.get() {
+            options.insert("low_vram".to_string(), serde_json::Value::Bool(true));
+        }
+        Ok(options)
+    };
+
+    // Stop sequences for `options.stop`; blank lines are skipped
+    let stop_value = move || -> Vec<String> {
+        stop_sequences
+            .get()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(unescape_stop_sequence)
+            .collect()
+    };
+
     // Send message handler
+    // Read a dropped file as context for the next message, rejecting binaries
+    let attach_file = move |file: web_sys::File| {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen::JsCast;
+
+            let max_chars = context_max_chars.get_untracked();
+            // Up to four bytes per character; anything past that is truncated anyway
+            let limit = (max_chars * 4) as f64;
+            let cut = file.size() > limit;
+            let Ok(blob) = file.slice_with_f64_and_f64(0.0, limit) else { return };
+            let Ok(reader) = web_sys::FileReader::new() else { return };
+            let name = file.name();
+            let onload = Closure::once_into_js({
+                let reader = reader.clone();
+                move || {
+                    let bytes = reader
+                        .result()
+                        .map(|buffer| js_sys::Uint8Array::new(&buffer).to_vec())
+                        .unwrap_or_default();
+                    match file_text(&bytes, cut) {
+                        Some(text) => {
+                            let truncated = cut || text.chars().count() > max_chars;
+                            let content = text.chars().take(max_chars).collect();
+                            set_attached_file.set(Some(AttachedFile { name, content, truncated }));
+                        }
+                        None => push_toast(format!("{} doesn't look like a text file", name), true),
+                    }
+                }
+            });
+            reader.set_onload(Some(onload.unchecked_ref()));
+            let _ = reader.read_as_array_buffer(&blob);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = file;
+    };
+
+    // Whether the selected model takes images; models whose capabilities are unknown get the benefit of the doubt
+    let model_accepts_images = move || {
+        selected_model
+            .get()
+            .and_then(|model| model_caps.get().get(&model).cloned())
+            .is_none_or(|caps| caps.is_empty() || caps.iter().any(|c| c == "vision"))
+    };
+
+    // Attach an image pasted into the input to the next message. Anything else on
+    // the clipboard pastes as usual.
+    let paste_image = move |ev: web_sys::Event| {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen::JsCast;
+
+            let Ok(ev) = ev.dyn_into::<web_sys::ClipboardEvent>() else { return };
+            let Some(items) = ev.clipboard_data().map(|data| data.items()) else { return };
+            let image = (0..items.length())
+                .filter_map(|i| items.get(i))
+                .find(|item| item.kind() == "file" && item.type_().starts_with("image/"))
+                .and_then(|item| item.get_as_file().ok().flatten());
+            let Some(image) = image else { return };
+            ev.prevent_default();
+            if !model_accepts_images() {
+                let model = selected_model.get_untracked().unwrap_or_else(|| "This model".to_string());
+                push_toast(format!("{} doesn't accept images", model), true);
+                return;
+            }
+            if image.size() > MAX_PASTED_IMAGE_BYTES {
+                push_toast(format!("Image is too large to attach (over {})", format_bytes(MAX_PASTED_IMAGE_BYTES as u64)), true);
+                return;
+            }
+            let Ok(reader) = web_sys::FileReader::new() else { return };
+            let onload = Closure::once_into_js({
+                let reader = reader.clone();
+                move || {
+                    if let Some(data_uri) = reader.result().ok().and_then(|result| result.as_string()) {
+                        set_pasted_images.update(|images| images.push(data_uri));
+                    }
+                }
+            });
+            reader.set_onload(Some(onload.unchecked_ref()));
+            let _ = reader.read_as_data_url(&image);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = (ev, set_pasted_images);
+    };
+
+    // Tool definitions for the request; the server checks each one in detail
+    let tools_value = move || -> Result<Vec<serde_json::Value>, String> {
+        let definitions = tool_definitions.get();
+        if !tools_enabled.get() || definitions.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let tools = match serde_json::from_str::<serde_json::Value>(&definitions) {
+            Ok(serde_json::Value::Array(tools)) => tools,
+            Ok(_) => return Err("Tools must be a JSON array".to_string()),
+            Err(e) => return Err(format!("Invalid tools JSON: {}", e)),
+        };
+        match tools.iter().position(|tool| tool["function"]["name"].as_str().is_none_or(str::is_empty)) {
+            Some(index) => Err(format!("Tool {} has no function name", index + 1)),
+            None => Ok(tools),
+        }
+    };
+
+    // Chat settings as request body fields, or `None` while one of them is invalid
+    let request_settings = move || -> Option<serde_json::Map<String, serde_json::Value>> {
+        // Don't send with a schema Ollama would reject
+        let format = json_format().ok()?;
+        let max_tokens = num_predict_value().ok()?;
+        let gpu_options = gpu_options_value().ok()?;
+        let tools = tools_value().ok()?;
+
+        let mut settings = serde_json::Map::new();
+        let keep_alive_value = keep_alive.get();
+        if !keep_alive_value.is_empty() {
+            settings.insert("keep_alive".to_string(), serde_json::Value::String(keep_alive_value));
+        }
+        if let Some(format) = format {
+            settings.insert("format".to_string(), format);
+        }
+        let raw = raw_mode.get();
+        if raw {
+            settings.insert("raw".to_string(), serde_json::Value::Bool(true));
+        }
+        let generate = endpoint.get() == "generate";
+        if generate {
+            settings.insert("endpoint".to_string(), serde_json::Value::from("generate"));
+        }
+        if auto_start.get() {
+            settings.insert("auto_start".to_string(), serde_json::Value::Bool(true));
+        }
+        let mut options = serde_json::Map::new();
+        if let Some(max_tokens) = max_tokens {
+            options.insert("num_predict".to_string(), serde_json::Value::from(max_tokens));
+        }
+        let stop = stop_value();
+        if !stop.is_empty() {
+            options.insert("stop".to_string(), serde_json::Value::from(stop));
+        }
+        options.extend(gpu_options);
+        if !options.is_empty() {
+            settings.insert("options".to_string(), serde_json::Value::Object(options));
+        }
+        // Skip tools for models known not to support them, rather than failing every message
+        let supports_tools = selected_model
+            .get()
+            .and_then(|model| model_caps.get().get(&model).cloned())
+            .is_none_or(|caps| caps.is_empty() || caps.iter().any(|c| c == "tools"));
+        if !tools.is_empty() && !raw && !generate && supports_tools {
+            settings.insert("tools".to_string(), serde_json::Value::Array(tools));
+        }
+        Some(settings)
+    };
+
+    // Stream the model's reply into the placeholder message at the end of the conversation
+    #[cfg(target_arch = "wasm32")]
+    let stream_reply = move |body: serde_json::Value| async move {
+        set_last_request.set(serde_json::to_string_pretty(&body).ok());
+        set_last_request_id.set(None);
+        set_last_response.set(None);
+
+        let key = chat_stream_key(conversation_id.get_untracked().as_deref());
+        reply_stream_key.set_value(Some(key.clone()));
+
+        let mut full_text = String::new();
+        let mut full_thinking = String::new();
+        let mut last_token_at = 0.0;
+        fresh_from.set_value(None);
+        stream_chat(&key, &body, |event, data| {
+            // A status like "Starting Ollama..." holds until the reply begins
+            if event != "status" && event != "request_id" && stream_status.get_untracked().is_some() {
+                set_stream_status.set(None);
+            }
+            match event {
+                "request_id" => set_last_request_id.set(Some(data.to_string())),
+                "status" => set_stream_status.set(Some(data.to_string())),
+                "thinking" => {
+                    full_thinking.push_str(data);
+
+                    let current_thinking = full_thinking.clone();
+                    set_messages.update(|msgs| {
+                        if let Some(last) = msgs.last_mut() {
+                            if last.role == "ai" {
+                                last.thinking = current_thinking;
+                            }
+                        }
+                    });
+                }
+                "context" => {
+                    let window = serde_json::from_str::<serde_json::Value>(data)
+                        .ok()
+                        .and_then(|info| info["window"].as_u64());
+                    set_messages.update(|msgs| {
+                        if let Some(last) = msgs.last_mut() {
+                            if last.role == "ai" {
+                                last.context_window = window;
+                            }
+                        }
+                    });
+                }
+                "tool_calls" => {
+                    if let Ok(calls) = serde_json::from_str::<Vec<serde_json::Value>>(data) {
+                        set_messages.update(|msgs| {
+                            if let Some(last) = msgs.last_mut() {
+                                if last.role == "ai" {
+                                    last.tool_calls.extend(calls);
+                                }
+                            }
+                        });
+                    }
+                }
+                "done" => {
+                    set_is_streaming.set(false);
+                    set_messages.update(|msgs| {
+                        if let Some(last) = msgs.last_mut() {
+                            last.created_at = now_millis();
+                        }
+                    });
+                    return false;
+                }
+                "token" | "error" => {
+                    if event == "error" {
+                        fresh_from.set_value(None);
+                        set_last_error.set(Some(data.to_string()));
+                        full_text.push_str(&format!("[Error: {}]", data));
+                    } else {
+                        let now = js_sys::Date::now();
+                        let fast = now - last_token_at < TYPEWRITER_MIN_GAP_MS;
+                        last_token_at = now;
+                        fresh_from.set_value((typewriter.get_untracked() && !fast).then_some(full_text.len()));
+                        full_text.push_str(data);
+                    }
+
+                    let current_text = full_text.clone();
+                    set_messages.update(|msgs| {
+                        if let Some(last) = msgs.last_mut() {
+                            if last.role == "ai" {
+                                last.text = current_text;
+                            }
+                        }
+                    });
+                }
+                _ => {}
+            }
+            true
+        }).await;
+        if reply_stream_key.get_value().as_ref() == Some(&key) {
+            reply_stream_key.set_value(None);
+        }
+        set_stream_status.set(None);
+        set_is_streaming.set(false);
+    };
+
+    // Stop the reply streaming into this conversation; what arrived so far is kept
+    let stop_reply = move || {
+        #[cfg(target_arch = "wasm32")]
+        if let Some(key) = reply_stream_key.get_value() {
+            stop_stream(&key);
+        }
+    };
+
     let do_send = move || {
-        let text = input.get();
-        if text.trim().is_empty() || selected_model.get().is_none() || is_streaming.get() {
+        let typed = input.get();
+        let images = pasted_images.get();
+        if (typed.trim().is_empty() && images.is_empty()) || selected_model.get().is_none() || is_streaming.get() {
+            return;
+        }
+        if !images.is_empty() && !model_accepts_images() {
+            push_toast("The selected model doesn't accept images; remove them to send".to_string(), true);
             return;
         }
+        let Some(settings) = request_settings() else {
+            return;
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = settings;
+
+        // A dropped file goes first, so the question reads after its context
+        let text = match attached_file.get() {
+            Some(file) => format!("Contents of `{}`:\n\n```\n{}\n```\n\n{}", file.name, file.content, typed),
+            None => typed.clone(),
+        };
+        set_attached_file.set(None);
+        set_pasted_images.set(Vec::new());
+
+        // Conversation so far, in Ollama's chat format
+        #[cfg(target_arch = "wasm32")]
+        let history: Vec<ApiMessage> = messages
+            .get()
+            .into_iter()
+            .filter(ChatMessage::has_content)
+            .map(ChatMessage::to_api)
+            .collect();
 
         // Add user message
         set_messages.update(|msgs| {
             msgs.push(ChatMessage {
                 role: "user".to_string(),
                 text: text.clone(),
+                thinking: String::new(),
+                created_at: now_millis(),
+                model: None,
+                tool_calls: Vec::new(),
+                tool_name: None,
+                context_window: None,
+                pinned: false,
+                versions: Vec::new(),
+                images: images.clone(),
             });
         });
 
@@ -1116,11 +4194,41 @@ pub fn App() -> impl IntoView {
             msgs.push(ChatMessage {
                 role: "ai".to_string(),
                 text: "".to_string(),
+                thinking: String::new(),
+                created_at: now_millis(),
+                model: selected_model.get(),
+                tool_calls: Vec::new(),
+                tool_name: None,
+                context_window: None,
+                pinned: false,
+                versions: Vec::new(),
+                images: Vec::new(),
             });
         });
 
+        // Remember the prompt for arrow-key recall
+        set_prompt_history.update(|history| {
+            if history.last() != Some(&typed) {
+                history.push(typed.clone());
+            }
+            if history.len() > PROMPT_HISTORY_LIMIT {
+                let excess = history.len() - PROMPT_HISTORY_LIMIT;
+                history.drain(..excess);
+            }
+        });
+        set_history_index.set(None);
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Ok(json) = serde_json::to_string(&prompt_history.get_untracked()) {
+                storage_set("prompt_history", &json);
+            }
+        }
+
         set_input.set(String::new());
+        set_stream_status.set(None);
         set_is_streaming.set(true);
+        // Follow the new reply even if the user had scrolled up
+        set_at_bottom.set(true);
 
         // Start streaming
         let model = selected_model.get().unwrap();
@@ -1130,13 +4238,8 @@ pub fn App() -> impl IntoView {
 
         #[cfg(target_arch = "wasm32")]
         {
-            use wasm_bindgen::prelude::*;
-            use wasm_bindgen::JsCast;
-
             // Use fetch with SSE
             wasm_bindgen_futures::spawn_local(async move {
-                let window = web_sys::window().unwrap();
-
                 // Build the prompt - optionally with search results
                 let prompt = if search_enabled && !api_token.trim().is_empty() {
                     // First, perform web search
@@ -1165,75 +4268,461 @@ pub fn App() -> impl IntoView {
                     user_query.clone()
                 };
 
-                let opts = web_sys::RequestInit::new();
-                opts.set_method("POST");
-                opts.set_body(&JsValue::from_str(&serde_json::json!({
-                    "model": model,
-                    "prompt": prompt
-                }).to_string()));
+                let mut chat_messages = history;
+                chat_messages.push(ApiMessage {
+                    role: "user".to_string(),
+                    content: prompt,
+                    images: images.iter().map(|image| data_uri_base64(image).to_string()).collect(),
+                    ..Default::default()
+                });
+
+                let mut body = serde_json::json!({
+                    "model": model,
+                    "messages": chat_messages
+                });
+                if let Some(fields) = body.as_object_mut() {
+                    fields.extend(settings);
+                }
+                stream_reply(body).await;
+            });
+        }
+    };
+
+    // Answer the last reply's tool calls with the results typed in, and let the model carry on
+    let send_tool_results = move || {
+        let Some(model) = selected_model.get() else { return };
+        if is_streaming.get() {
+            return;
+        }
+        let Some(settings) = request_settings() else {
+            return;
+        };
+        let Some(calls) = messages
+            .get()
+            .last()
+            .filter(|m| m.role == "ai")
+            .map(|m| m.tool_calls.clone())
+            .filter(|calls| !calls.is_empty())
+        else {
+            return;
+        };
+        let results = tool_results.get();
+        set_messages.update(|msgs| {
+            for (index, call) in calls.iter().enumerate() {
+                msgs.push(ChatMessage {
+                    role: "tool".to_string(),
+                    text: results.get(index).cloned().unwrap_or_default(),
+                    thinking: String::new(),
+                    created_at: now_millis(),
+                    model: None,
+                    tool_calls: Vec::new(),
+                    tool_name: call["function"]["name"].as_str().map(str::to_string),
+                    context_window: None,
+                    pinned: false,
+                    versions: Vec::new(),
+                    images: Vec::new(),
+                });
+            }
+        });
+        let history: Vec<ApiMessage> = messages
+            .get()
+            .into_iter()
+            .filter(ChatMessage::has_content)
+            .map(ChatMessage::to_api)
+            .collect();
+        set_messages.update(|msgs| {
+            msgs.push(ChatMessage {
+                role: "ai".to_string(),
+                text: String::new(),
+                thinking: String::new(),
+                created_at: now_millis(),
+                model: Some(model.clone()),
+                tool_calls: Vec::new(),
+                tool_name: None,
+                context_window: None,
+                pinned: false,
+                versions: Vec::new(),
+                images: Vec::new(),
+            });
+        });
+        set_tool_results.set(Vec::new());
+        set_stream_status.set(None);
+        set_is_streaming.set(true);
+        set_at_bottom.set(true);
+
+        let mut body = serde_json::json!({ "model": model, "messages": history });
+        if let Some(fields) = body.as_object_mut() {
+            fields.extend(settings);
+        }
+        #[cfg(target_arch = "wasm32")]
+        spawn_local(stream_reply(body));
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = body;
+    };
+
+    // Ask for the last reply again with the selected model, keeping the current answer as a version
+    let regenerate = move || {
+        let Some(model) = selected_model.get() else { return };
+        if is_streaming.get() {
+            return;
+        }
+        let Some(settings) = request_settings() else {
+            return;
+        };
+        let msgs = messages.get();
+        let Some((last, earlier)) = msgs.split_last().filter(|(last, _)| last.role == "ai" && !last.text.is_empty()) else {
+            return;
+        };
+        let created_at = last.created_at;
+        let history: Vec<ApiMessage> = earlier
+            .iter()
+            .filter(|m| m.has_content())
+            .cloned()
+            .map(ChatMessage::to_api)
+            .collect();
+        set_messages.update(|msgs| {
+            if let Some(last) = msgs.last_mut() {
+                last.versions.push(ReplyVersion {
+                    text: std::mem::take(&mut last.text),
+                    thinking: std::mem::take(&mut last.thinking),
+                    model: last.model.take(),
+                    created_at,
+                });
+                if last.versions.len() > MAX_REPLY_VERSIONS {
+                    let excess = last.versions.len() - MAX_REPLY_VERSIONS;
+                    last.versions.drain(..excess);
+                }
+                last.tool_calls.clear();
+                last.context_window = None;
+                last.model = Some(model.clone());
+                last.created_at = now_millis();
+            }
+        });
+        set_compare_version.set(None);
+        set_stream_status.set(None);
+        set_is_streaming.set(true);
+        set_at_bottom.set(true);
+
+        let mut body = serde_json::json!({ "model": model, "messages": history });
+        if let Some(fields) = body.as_object_mut() {
+            fields.extend(settings);
+        }
+        #[cfg(target_arch = "wasm32")]
+        spawn_local(stream_reply(body));
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = body;
+    };
+
+    // Make an earlier version the shown reply again; the one it replaces becomes a version
+    let keep_version = move |created_at: i64, index: usize| {
+        set_messages.update(|msgs| {
+            let Some(msg) = msgs.iter_mut().find(|m| m.role == "ai" && m.created_at == created_at) else { return };
+            if index >= msg.versions.len() {
+                return;
+            }
+            let kept = msg.versions.remove(index);
+            msg.versions.push(ReplyVersion {
+                text: std::mem::replace(&mut msg.text, kept.text),
+                thinking: std::mem::replace(&mut msg.thinking, kept.thinking),
+                model: std::mem::replace(&mut msg.model, kept.model),
+                created_at: msg.created_at,
+            });
+            msg.created_at = kept.created_at;
+        });
+        set_compare_version.set(None);
+    };
+
+    // Drop the earlier versions of a reply
+    let forget_versions = move |created_at: i64| {
+        set_messages.update(|msgs| {
+            if let Some(msg) = msgs.iter_mut().find(|m| m.role == "ai" && m.created_at == created_at) {
+                msg.versions.clear();
+            }
+        });
+        set_compare_version.set(None);
+    };
 
-                let headers = web_sys::Headers::new().unwrap();
-                headers.set("Content-Type", "application/json").unwrap();
-                opts.set_headers(&headers);
+    // Messages matching the search, in conversation order
+    let search_hits = move || {
+        let query = search_query.get();
+        if query.is_empty() {
+            return 0;
+        }
+        messages.get().iter().filter(|m| !find_matches(&m.text, &query).is_empty()).count()
+    };
 
-                let request = web_sys::Request::new_with_str_and_init("/api/stream", &opts).unwrap();
+    // Scroll the `offset`-th next (or previous, if negative) search hit into view
+    let jump_to_hit = move |offset: isize| {
+        let count = search_hits();
+        if count == 0 {
+            return;
+        }
+        let index = (search_index.get_untracked() as isize + offset).rem_euclid(count as isize) as usize;
+        set_search_index.set(index);
+        #[cfg(target_arch = "wasm32")]
+        {
+            use wasm_bindgen::JsCast;
+            let hit = web_sys::window()
+                .and_then(|w| w.document())
+                .and_then(|d| d.query_selector_all(".chat-bubble.search-hit").ok())
+                .and_then(|hits| hits.get(index as u32))
+                .and_then(|node| node.dyn_into::<web_sys::Element>().ok());
+            if let Some(hit) = hit {
+                let options = web_sys::ScrollIntoViewOptions::new();
+                options.set_block(web_sys::ScrollLogicalPosition::Center);
+                hit.scroll_into_view_with_scroll_into_view_options(&options);
+            }
+        }
+    };
 
-                let resp_value = wasm_bindgen_futures::JsFuture::from(window.fetch_with_request(&request)).await;
+    // Pin or unpin the reply created at `created_at`
+    let toggle_pin = move |created_at: i64| {
+        set_messages.update(|msgs| {
+            if let Some(msg) = msgs.iter_mut().find(|m| m.role == "ai" && m.created_at == created_at) {
+                msg.pinned = !msg.pinned;
+            }
+        });
+    };
 
-                if let Ok(resp) = resp_value {
-                    let resp: web_sys::Response = resp.dyn_into().unwrap();
-                    if let Some(body) = resp.body() {
-                        let reader: web_sys::ReadableStreamDefaultReader = body.get_reader().unchecked_into();
+    // Scroll a pinned reply into view
+    let jump_to_reply = move |created_at: i64| {
+        set_at_bottom.set(false);
+        #[cfg(target_arch = "wasm32")]
+        {
+            let reply = web_sys::window()
+                .and_then(|w| w.document())
+                .and_then(|d| d.get_element_by_id(&format!("reply-{}", created_at)));
+            if let Some(reply) = reply {
+                let options = web_sys::ScrollIntoViewOptions::new();
+                options.set_block(web_sys::ScrollLogicalPosition::Center);
+                reply.scroll_into_view_with_scroll_into_view_options(&options);
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = created_at;
+    };
 
-                        let mut full_text = String::new();
+    // Start a fresh conversation, keeping the selected model
+    let clear_conversation = move || {
+        set_messages.set(Vec::new());
+        set_input.set(String::new());
+        set_render_markdown.set(true);
+        set_last_request.set(None);
+        set_locked_model.set(None);
+        set_conversation_id.set(None);
+        #[cfg(target_arch = "wasm32")]
+        storage_set("conversation", "");
+    };
 
-                        loop {
-                            let read_promise = reader.read();
-                            let result = wasm_bindgen_futures::JsFuture::from(read_promise).await;
-                            if let Ok(chunk) = result {
-                                let done = js_sys::Reflect::get(&chunk, &JsValue::from_str("done")).unwrap();
+    // Auto-clear for shared screens: the conversation and prompt recall go after a stretch with
+    // no input (a streaming reply counts as activity), and from storage when the page unloads
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+        let last_activity = StoredValue::new(now_millis());
+        if let Some(window) = web_sys::window() {
+            let touch = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
+                last_activity.set_value(now_millis());
+            }) as Box<dyn Fn()>);
+            for event in ["pointerdown", "keydown", "wheel", "touchstart"] {
+                let _ = window.add_event_listener_with_callback(event, touch.as_ref().unchecked_ref());
+            }
+            touch.forget();
 
-                                if done.as_bool().unwrap_or(true) {
-                                    break;
-                                }
+            let check = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
+                let minutes = idle_clear_minutes.get_untracked();
+                if minutes == 0 || is_streaming.get_untracked() {
+                    last_activity.set_value(now_millis());
+                    return;
+                }
+                let idle = now_millis() - last_activity.get_value() >= i64::from(minutes) * 60_000;
+                if idle && !(messages.with_untracked(|msgs| msgs.is_empty()) && model_chats.with_value(|chats| chats.is_empty())) {
+                    clear_conversation();
+                    model_chats.set_value(HashMap::new());
+                    storage_set("model_chats", "{}");
+                    set_prompt_history.set(Vec::new());
+                    storage_set("prompt_history", "[]");
+                    push_toast("Conversation cleared after inactivity".to_string(), false);
+                }
+            }) as Box<dyn Fn()>);
+            let _ = window.set_interval_with_callback_and_timeout_and_arguments_0(check.as_ref().unchecked_ref(), 15_000);
+            check.forget();
+
+            let unload = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
+                if clear_on_unload.get_untracked() {
+                    storage_set("conversation", "");
+                    storage_set("model_chats", "{}");
+                    storage_set("prompt_history", "[]");
+                }
+            }) as Box<dyn Fn()>);
+            let _ = window.add_event_listener_with_callback("beforeunload", unload.as_ref().unchecked_ref());
+            unload.forget();
+        }
+    }
 
-                                let value = js_sys::Reflect::get(&chunk, &JsValue::from_str("value")).unwrap();
-                                let array: js_sys::Uint8Array = value.dyn_into().unwrap();
-                                let bytes = array.to_vec();
-                                let text = String::from_utf8_lossy(&bytes);
-
-                                // Parse SSE format
-                                for line in text.lines() {
-                                    if line.starts_with("data:") {
-                                        let data = line.trim_start_matches("data:").trim();
-                                        if data == "__END__" || data.is_empty() {
-                                            if data == "__END__" {
-                                                set_is_streaming.set(false);
-                                            }
-                                            break;
-                                        }
-                                        full_text.push_str(data);
-                                        full_text.push(' '); // Add space between chunks
-
-                                        let current_text = full_text.clone();
-                                        set_messages.update(|msgs| {
-                                            if let Some(last) = msgs.last_mut() {
-                                                if last.role == "ai" {
-                                                    last.text = current_text;
-                                                }
-                                            }
-                                        });
-                                    }
-                                }
-                            } else {
-                                break;
-                            }
-                        }
+    // Save the conversation whenever a reply is done: to localStorage always, and to the server with sync on
+    Effect::new(move |_| {
+        let msgs = messages.get();
+        let sync = server_sync.get();
+        if is_streaming.get() || msgs.is_empty() {
+            return;
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let id = conversation_id.get_untracked().unwrap_or_else(|| {
+                let id = format!("{:x}-{:06x}", now_millis(), (js_sys::Math::random() * 16_777_216.0) as u32);
+                set_conversation_id.set(Some(id.clone()));
+                id
+            });
+            let conversation = SavedConversation {
+                id,
+                title: conversation_title(&msgs),
+                updated_at: now_millis(),
+                messages: msgs,
+            };
+            if let Ok(json) = serde_json::to_string(&conversation) {
+                storage_set("conversation", &json);
+            }
+            if sync {
+                spawn_local(async move {
+                    if let Err(e) = save_conversation(conversation).await {
+                        push_toast(format!("Couldn't sync the conversation: {}", e), true);
+                    }
+                });
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = (msgs, sync);
+    });
+
+    let load_conversations = move || {
+        spawn_local(async move {
+            set_conversation_list.set(Some(list_conversations().await.map_err(|e| e.to_string())));
+        });
+    };
+
+    // Replace the current conversation with one saved on the server
+    let open_conversation = move |id: String| {
+        if is_streaming.get_untracked() {
+            return;
+        }
+        spawn_local(async move {
+            match load_conversation(id.clone()).await {
+                Ok(Some(saved)) => {
+                    set_messages.set(saved.messages);
+                    set_conversation_id.set(Some(saved.id));
+                    set_locked_model.set(None);
+                    set_last_request.set(None);
+                    set_at_bottom.set(true);
+                    set_conversations_open.set(false);
+                }
+                Ok(None) => {
+                    push_toast("That conversation is no longer saved".to_string(), true);
+                    load_conversations();
+                }
+                Err(e) => push_toast(format!("Couldn't open the conversation: {}", e), true),
+            }
+        });
+    };
+
+    let remove_conversation = move |id: String| {
+        spawn_local(async move {
+            match delete_conversation(id.clone()).await {
+                Ok(()) => {
+                    // Saving the open conversation again starts a new file
+                    if conversation_id.get_untracked().as_ref() == Some(&id) {
+                        set_conversation_id.set(None);
                     }
+                    load_conversations();
+                }
+                Err(e) => push_toast(format!("Couldn't delete the conversation: {}", e), true),
+            }
+        });
+    };
+
+    let new_chat = move || {
+        if is_streaming.get() || messages.get().is_empty() {
+            return;
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Some(window) = web_sys::window() {
+                if !window.confirm_with_message("Clear this conversation and start a new chat?").unwrap_or(false) {
+                    return;
                 }
-                set_is_streaming.set(false);
+            }
+        }
+        clear_conversation();
+    };
+
+    // Ask the selected model to summarize the conversation, without changing it
+    let summarize = move || {
+        let Some(model) = selected_model.get() else { return };
+        if summarizing.get() || is_streaming.get() {
+            return;
+        }
+        let history: Vec<ApiMessage> = messages
+            .get()
+            .into_iter()
+            .filter(ChatMessage::has_content)
+            .map(ChatMessage::to_api)
+            .collect();
+        if history.is_empty() {
+            push_toast("Nothing to summarize yet".to_string(), true);
+            return;
+        }
+        set_summary.set(Some(String::new()));
+        set_summarizing.set(true);
+
+        #[cfg(target_arch = "wasm32")]
+        spawn_local(async move {
+            let mut chat_messages = history;
+            chat_messages.push(ApiMessage {
+                role: "user".to_string(),
+                content: "Summarize our conversation so far in a few short paragraphs. Keep the key facts, \
+                          decisions, code and open questions so we can continue from the summary alone."
+                    .to_string(),
+                ..Default::default()
             });
+            let body = serde_json::json!({ "model": model, "messages": chat_messages });
+            let mut full_text = String::new();
+            stream_chat("summary", &body, |event, data| {
+                match event {
+                    "token" => full_text.push_str(data),
+                    "error" => full_text.push_str(&format!("[Error: {}]", data)),
+                    "done" => return false,
+                    _ => return true,
+                }
+                set_summary.set(Some(full_text.clone()));
+                true
+            }).await;
+            set_summarizing.set(false);
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = model;
+    };
+
+    // Swap the conversation for its summary, to free up the context window
+    let continue_from_summary = move || {
+        let Some(text) = summary.get().filter(|t| !t.trim().is_empty()) else { return };
+        if is_streaming.get() {
+            return;
         }
+        set_messages.set(vec![ChatMessage {
+            role: "system".to_string(),
+            text,
+            thinking: String::new(),
+            created_at: now_millis(),
+            model: selected_model.get(),
+            tool_calls: Vec::new(),
+            tool_name: None,
+            context_window: None,
+            pinned: false,
+            versions: Vec::new(),
+            images: Vec::new(),
+        }]);
+        set_summary.set(None);
+        set_last_request.set(None);
     };
 
     // Close all menus
@@ -1241,6 +4730,7 @@ pub fn App() -> impl IntoView {
         set_menu_open.set(false);
         set_models_panel_open.set(false);
         set_cloud_panel_open.set(false);
+        set_copied_model.set(None);
     };
 
     // Toggle menu
@@ -1249,24 +4739,160 @@ pub fn App() -> impl IntoView {
         if menu_open.get() {
             close_menus();
         } else {
+            #[cfg(target_arch = "wasm32")]
+            {
+                use wasm_bindgen::JsCast;
+                set_touch_opened.set(
+                    ev.dyn_ref::<web_sys::PointerEvent>().is_some_and(|p| p.pointer_type() == "touch")
+                );
+            }
             set_menu_open.set(true);
+            // Opened from the keyboard (Enter/Space report no click count)
+            if ev.detail() == 0 {
+                set_models_panel_open.set(true);
+                focus_first_menu_item("model-menu");
+            }
         }
     };
 
     // Select model and persist to localStorage
     let select_model = move |model: String| {
+        let switching = selected_model.get().is_some_and(|current| current != model);
+        if per_model_chats.get() && switching && is_streaming.get() {
+            push_toast("Wait for the reply to finish before switching models".to_string(), true);
+            close_menus();
+            return;
+        }
+        // A locked conversation keeps its model; switching means starting over.
+        // With a chat per model it's set aside instead, so there's nothing to lose.
+        if let Some(locked) = locked_model.get().filter(|locked| *locked != model && !per_model_chats.get()) {
+            if messages.get().is_empty() {
+                set_locked_model.set(Some(model.clone()));
+            } else if is_streaming.get() {
+                push_toast(format!("This chat is locked to {}", locked), true);
+                close_menus();
+                return;
+            } else {
+                #[cfg(target_arch = "wasm32")]
+                {
+                    let message = format!("This chat is locked to {}. Start a new chat with {}?", locked, model);
+                    let confirmed = web_sys::window()
+                        .and_then(|w| w.confirm_with_message(&message).ok())
+                        .unwrap_or(false);
+                    if !confirmed {
+                        close_menus();
+                        return;
+                    }
+                }
+                clear_conversation();
+            }
+        }
         set_selected_model.set(Some(model.clone()));
         #[cfg(target_arch = "wasm32")]
         {
-            if let Some(window) = web_sys::window() {
-                if let Ok(Some(storage)) = window.local_storage() {
-                    let _ = storage.set_item("selected_model", &model);
+            storage_set("selected_model", &model);
+        }
+        close_menus();
+    };
+
+    // With a chat per model, go on with the current chat under `model` instead of switching
+    // to its own, e.g. to regenerate a reply with another model
+    let continue_with_model = move |model: String| {
+        if selected_model.get_untracked().as_ref() == Some(&model) {
+            return;
+        }
+        if is_streaming.get_untracked() {
+            push_toast("Wait for the reply to finish before switching models".to_string(), true);
+            close_menus();
+            return;
+        }
+        if let Some(locked) = locked_model.get_untracked() {
+            push_toast(format!("This chat is locked to {}", locked), true);
+            close_menus();
+            return;
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            if model_chats.with_value(|chats| chats.contains_key(&model)) {
+                let message = format!("Continue this chat with {}? The chat kept for {} is discarded.", model, model);
+                let confirmed = web_sys::window()
+                    .and_then(|w| w.confirm_with_message(&message).ok())
+                    .unwrap_or(false);
+                if !confirmed {
+                    close_menus();
+                    return;
                 }
             }
         }
-        close_menus();
+        carry_chat_over.set_value(true);
+        select_model(model);
+    };
+
+    // Drop the chats kept for other models, oldest first, beyond the conversations cap
+    let trim_kept_chats = move |limit: usize| {
+        let dropped = model_chats.try_update_value(|chats| trim_model_chats(chats, limit)).unwrap_or(0);
+        if dropped > 0 {
+            #[cfg(target_arch = "wasm32")]
+            {
+                if let Ok(json) = model_chats.with_value(serde_json::to_string) {
+                    storage_set("model_chats", &json);
+                }
+            }
+            push_toast(format!("Dropped the chat{} kept for {} other model{} (limit {} conversations)",
+                if dropped == 1 { "" } else { "s" }, dropped, if dropped == 1 { "" } else { "s" }, limit), false);
+        }
     };
 
+    // Lowering the cap applies right away
+    Effect::new(move |_| trim_kept_chats(max_conversations.get()));
+
+    // With a chat per model, set the current chat aside when the model changes and bring
+    // back the new model's. Picking the first model (on load) keeps the restored chat.
+    Effect::new(move |previous: Option<Option<String>>| {
+        let current = selected_model.get();
+        let (Some(Some(from)), Some(to)) = (previous, current.clone()) else { return current };
+        if from == to || !per_model_chats.get_untracked() {
+            return current;
+        }
+        if carry_chat_over.get_value() {
+            // The current chat goes on with `to`, taking the place of the one kept for it
+            carry_chat_over.set_value(false);
+            model_chats.update_value(|chats| {
+                chats.remove(&to);
+            });
+            #[cfg(target_arch = "wasm32")]
+            {
+                if let Ok(json) = model_chats.with_value(serde_json::to_string) {
+                    storage_set("model_chats", &json);
+                }
+            }
+            return current;
+        }
+        let outgoing = ModelChat {
+            conversation_id: conversation_id.get_untracked(),
+            messages: messages.get_untracked(),
+            locked: locked_model.get_untracked().is_some(),
+            set_aside_at: now_millis(),
+        };
+        let incoming = model_chats.try_update_value(|chats| swap_model_chat(chats, &from, &to, outgoing)).unwrap_or_default();
+        trim_kept_chats(max_conversations.get_untracked());
+        set_conversation_id.set(incoming.conversation_id);
+        set_locked_model.set(incoming.locked.then(|| to.clone()));
+        set_last_request.set(None);
+        set_at_bottom.set(true);
+        #[cfg(target_arch = "wasm32")]
+        {
+            if incoming.messages.is_empty() {
+                storage_set("conversation", "");
+            }
+            if let Ok(json) = model_chats.with_value(serde_json::to_string) {
+                storage_set("model_chats", &json);
+            }
+        }
+        set_messages.set(incoming.messages);
+        current
+    });
+
     // Handle runner item interaction (hover/click)
     let open_models_panel = move |ev: web_sys::MouseEvent| {
         ev.stop_propagation();
@@ -1274,31 +4900,65 @@ pub fn App() -> impl IntoView {
     };
 
     view! {
-        <Stylesheet id="leptos" href="/pkg/ollama-rust.css"/>
+        <Stylesheet id="leptos" href=format!("{}/ollama-rust.css", routes::PKG)/>
         <Title text="Ollama Rust"/>
 
         // Backdrop to close menus when clicking outside
         <div class="menu-backdrop"
              class:hidden=move || !menu_open.get()
+             class:sheet-backdrop=sheet_menu
              on:click=move |_| close_menus()
              on:touchend=move |_| close_menus()>
         </div>
 
-        <div class="chat-container">
+        <div class="chat-container"
+             class:drag-over=move || drag_over.get()
+             class:focus-mode=move || focus_mode.get()
+             on:dragover=move |ev: web_sys::DragEvent| {
+                 // Only take file drags; dragged text still drops into the textarea
+                 let has_files = ev.data_transfer()
+                     .is_some_and(|dt| dt.types().includes(&"Files".into(), 0));
+                 if has_files {
+                     ev.prevent_default();
+                     set_drag_over.set(true);
+                 }
+             }
+             on:dragleave=move |_| set_drag_over.set(false)
+             on:drop=move |ev: web_sys::DragEvent| {
+                 set_drag_over.set(false);
+                 if let Some(file) = ev.data_transfer().and_then(|dt| dt.files()).and_then(|files| files.get(0)) {
+                     ev.prevent_default();
+                     attach_file(file);
+                 }
+             }>
             // Header
             <div class="chat-header">
                 <div class="header-left">
-                    <div class="model-dropdown">
-                        <button id="model-button" type="button" on:click=toggle_menu>
+                    <div class="model-dropdown"
+                         on:keydown=move |ev: web_sys::KeyboardEvent| {
+                             if menu_open.get() {
+                                 menu_keydown(&ev, "model-menu", "model-button", close_menus);
+                             }
+                         }>
+                        <button id="model-button"
+                                type="button"
+                                title=move || selected_model.get()
+                                aria-haspopup="menu"
+                                aria-controls="model-menu"
+                                aria-expanded=move || menu_open.get().to_string()
+                                on:click=toggle_menu
+                                on:keydown=move |ev: web_sys::KeyboardEvent| {
+                                    if ev.key() == "ArrowDown" && !menu_open.get() {
+                                        ev.prevent_default();
+                                        set_menu_open.set(true);
+                                        set_models_panel_open.set(true);
+                                        focus_first_menu_item("model-menu");
+                                    }
+                                }>
                             {move || {
                                 if let Some(model) = selected_model.get() {
-                                    // Truncate long model names
-                                    let display = if model.len() > 15 {
-                                        format!("{}...", &model[..12])
-                                    } else {
-                                        model
-                                    };
-                                    format!("🧠 {}", display)
+                                    let lock = if locked_model.get().is_some() { " 🔒" } else { "" };
+                                    format!("🧠 {}{}", truncate_model_name(&model), lock)
                                 } else {
                                     "🧠 Model".to_string()
                                 }
@@ -1307,10 +4967,28 @@ pub fn App() -> impl IntoView {
 
                         <div id="model-menu"
                              class="model-menu"
+                             role="menu"
+                             aria-label="Models"
                              class:hidden=move || !menu_open.get()
+                             class:sheet=sheet_menu
                              on:click=move |ev: web_sys::MouseEvent| ev.stop_propagation()>
                             <div class="runner-list">
+                                {move || sheet_menu().then(|| view! {
+                                    <div class="sheet-header">
+                                        <span class="sheet-title">"Models"</span>
+                                        <button type="button"
+                                                class="sheet-close"
+                                                aria-label="Close"
+                                                on:click=move |_| close_menus()>
+                                            "✕"
+                                        </button>
+                                    </div>
+                                }.into_any())}
                                 <div class="runner-item"
+                                     role="menuitem"
+                                     tabindex="-1"
+                                     aria-haspopup="menu"
+                                     aria-expanded=move || models_panel_open.get().to_string()
                                      on:mouseenter=open_models_panel
                                      on:click=open_models_panel
                                      on:touchstart=move |ev: web_sys::TouchEvent| {
@@ -1321,6 +4999,7 @@ pub fn App() -> impl IntoView {
 
                                     <div id="models-panel"
                                          class="models-panel"
+                                         role="menu"
                                          class:hidden=move || !models_panel_open.get()
                                          on:click=move |ev: web_sys::MouseEvent| ev.stop_propagation()>
                                         // Add Model section
@@ -1330,6 +5009,8 @@ pub fn App() -> impl IntoView {
                                                target="_blank"
                                                rel="noopener noreferrer"
                                                class="model-option library-link"
+                                               role="menuitem"
+                                               tabindex="-1"
                                                on:click=move |ev: web_sys::MouseEvent| ev.stop_propagation()>
                                                 "📚 Browse Models"
                                             </a>
@@ -1342,13 +5023,17 @@ pub fn App() -> impl IntoView {
                                                             class="add-model-input"
                                                             placeholder="model name (e.g. llama3)"
                                                             prop:value=move || new_model_name.get()
-                                                            on:input=move |ev| set_new_model_name.set(event_target_value(&ev))
+                                                            on:input=move |ev| {
+                                                                set_new_model_name.set(event_target_value(&ev));
+                                                                set_pull_warning.set(None);
+                                                                set_pull_confirmed.set(None);
+                                                            }
                                                             on:click=move |ev: web_sys::MouseEvent| ev.stop_propagation()
                                                             on:keydown=move |ev: web_sys::KeyboardEvent| {
                                                                 ev.stop_propagation();
                                                                 if ev.key() == "Enter" {
                                                                     let name = new_model_name.get();
-                                                                    start_download(name);
+                                                                    request_download(name);
                                                                 }
                                                             }
                                                         />
@@ -1357,10 +5042,10 @@ pub fn App() -> impl IntoView {
                                                             on:click=move |ev: web_sys::MouseEvent| {
                                                                 ev.stop_propagation();
                                                                 let name = new_model_name.get();
-                                                                start_download(name);
+                                                                request_download(name);
                                                             }
                                                         >
-                                                            "Pull"
+                                                            {move || if pull_confirmed.get().is_some() { "Pull anyway" } else { "Pull" }}
                                                         </button>
                                                         <button
                                                             class="add-model-btn cancel-btn"
@@ -1368,15 +5053,27 @@ pub fn App() -> impl IntoView {
                                                                 ev.stop_propagation();
                                                                 set_show_add_model.set(false);
                                                                 set_new_model_name.set(String::new());
+                                                                set_pull_warning.set(None);
+                                                                set_pull_confirmed.set(None);
                                                             }
                                                         >
                                                             "✕"
                                                         </button>
                                                     </div>
+                                                    {move || pull_warning.get().map(|warning| view! {
+                                                        <div class="pull-status pull-warning">{format!("⚠️ {}", warning)}</div>
+                                                    })}
+                                                    {move || disk_space.get().map(|space| view! {
+                                                        <div class="pull-status" title=space.path.clone()>
+                                                            {format!("{} free of {}", format_bytes(space.free_bytes), format_bytes(space.total_bytes))}
+                                                        </div>
+                                                    })}
                                                 }.into_any()
                                             } else {
                                                 view! {
                                                     <div class="model-option add-model-option"
+                                                         role="menuitem"
+                                                         tabindex="-1"
                                                          on:click=move |ev: web_sys::MouseEvent| {
                                                              ev.stop_propagation();
                                                              set_show_add_model.set(true);
@@ -1385,13 +5082,68 @@ pub fn App() -> impl IntoView {
                                                     </div>
                                                 }.into_any()
                                             }}
+
+                                            <div class="model-option select-models-option"
+                                                 role="menuitem"
+                                                 tabindex="-1"
+                                                 class:disabled=move || !update_batch.get().is_empty()
+                                                 title="Re-pull every installed model to pick up upstream changes"
+                                                 on:click=move |ev: web_sys::MouseEvent| {
+                                                     ev.stop_propagation();
+                                                     update_all_models();
+                                                 }>
+                                                {move || {
+                                                    let batch = update_batch.get().len();
+                                                    if batch == 0 {
+                                                        "⟳ Update all models".to_string()
+                                                    } else {
+                                                        format!("⟳ Updating… ({} of {})", batch - update_queue.get().len(), batch)
+                                                    }
+                                                }}
+                                            </div>
+                                            <div class="model-option select-models-option"
+                                                 role="menuitem"
+                                                 tabindex="-1"
+                                                 on:click=move |ev: web_sys::MouseEvent| {
+                                                     ev.stop_propagation();
+                                                     set_select_mode.update(|v| *v = !*v);
+                                                     set_selected_for_delete.set(Vec::new());
+                                                 }>
+                                                {move || if select_mode.get() { "✕ Cancel selection" } else { "☑ Select models" }}
+                                            </div>
+                                            <div class="model-option select-models-option"
+                                                 role="menuitem"
+                                                 tabindex="-1"
+                                                 title="Estimated from model size against free RAM and GPU memory; a rough guide only"
+                                                 on:click=move |ev: web_sys::MouseEvent| {
+                                                     ev.stop_propagation();
+                                                     set_hide_unfit.update(|v| *v = !*v);
+                                                     #[cfg(target_arch = "wasm32")]
+                                                     storage_set("hide_unfit_models", if hide_unfit.get_untracked() { "true" } else { "false" });
+                                                 }>
+                                                {move || if hide_unfit.get() { "◐ Show all models" } else { "◐ Only models that fit in memory" }}
+                                            </div>
+                                            {move || select_mode.get().then(|| view! {
+                                                <button class="add-model-btn bulk-delete-btn"
+                                                        disabled=move || selected_for_delete.get().is_empty() || bulk_deleting.get()
+                                                        on:click=move |ev: web_sys::MouseEvent| {
+                                                            ev.stop_propagation();
+                                                            do_bulk_delete();
+                                                        }>
+                                                    {move || if bulk_deleting.get() {
+                                                        "Deleting...".to_string()
+                                                    } else {
+                                                        format!("Delete selected ({})", selected_for_delete.get().len())
+                                                    }}
+                                                </button>
+                                            })}
                                         </div>
 
                                         // Divider
                                         <div class="model-divider"></div>
 
                                         // Models list
-                                        <Suspense fallback=move || view! { <div class="loading-models">"Loading..."</div> }>
+                                        <Suspense fallback=move || view! { <SkeletonRows/> }>
                                             {move || {
                                                 status_resource.get().map(|result| {
                                                     match result {
@@ -1403,19 +5155,91 @@ pub fn App() -> impl IntoView {
                                                             } else {
                                                                 view! {
                                                                     <div id="ollama-models" class="model-submenu">
-                                                                        {status.models.into_iter().map(|model| {
+                                                                        {move || {
+                                                                        // Favorites first; stars for models that are no longer installed are simply not shown
+                                                                        let favorites = favorite_models.get();
+                                                                        let memory_now = memory.get();
+                                                                        let pending = pending_deletes.get();
+                                                                        let unfit = |m: &String| {
+                                                                            memory_now.as_ref().zip(status.sizes.get(m)).is_some_and(|(mem, size)| mem.may_not_fit(*size))
+                                                                        };
+                                                                        let hidden = if hide_unfit.get() { status.models.iter().filter(|m| unfit(m)).count() } else { 0 };
+                                                                        let (starred, others): (Vec<String>, Vec<String>) = status.models
+                                                                            .clone()
+                                                                            .into_iter()
+                                                                            .filter(|m| hidden == 0 || !unfit(m))
+                                                                            .filter(|m| !pending.contains_key(m))
+                                                                            .partition(|m| favorites.contains(m));
+                                                                        let separator_at = (!starred.is_empty() && !others.is_empty()).then_some(starred.len());
+                                                                        let rows = starred.into_iter().chain(others).enumerate().map(|(index, model)| {
+                                                                            let is_favorite = favorites.contains(&model);
+                                                                            let m_star = model.clone();
+                                                                            let m_check = model.clone();
+                                                                            let m_check_for_closure = model.clone();
                                                                             let m_click = model.clone();
                                                                             let m_touch = model.clone();
                                                                             let m_display = model.clone();
+                                                                            let m_caps = model.clone();
+                                                                            let m_title = model.clone();
+                                                                            let m_continue = model.clone();
+                                                                            let m_copy = model.clone();
+                                                                            let m_copy_for_closure = model.clone();
+                                                                            let m_warm = model.clone();
+                                                                            let m_warm_for_closure = model.clone();
+                                                                            let m_warm_for_label = model.clone();
                                                                             let m_delete = model.clone();
+                                                                            let m_delete_key = model.clone();
                                                                             let m_delete_for_closure = m_delete.clone();
                                                                             let is_cloud_model = model.to_lowercase().contains("cloud");
-                                                                            let is_deleting = move || {
+                                                                            let fit_warning = memory_now.as_ref().zip(status.sizes.get(&model)).filter(|(mem, size)| mem.may_not_fit(**size)).map(|(mem, size)| {
+                                                                                format!(
+                                                                                    "Needs roughly {} with its context cache; about {} is available. Advisory only.",
+                                                                                    format_bytes(size + size / 5),
+                                                                                    format_bytes(mem.budget())
+                                                                                )
+                                                                            });
+                                                                            let is_deleting = Memo::new(move |_| {
                                                                                 deleting_model.get().as_ref() == Some(&m_delete_for_closure)
-                                                                            };
+                                                                            });
                                                                             view! {
-                                                                                <div class="model-option-row">
+                                                                                {(separator_at == Some(index)).then(|| view! { <div class="model-separator"></div> })}
+                                                                                <div class="model-option-row"
+                                                                                     class:deleting=move || is_deleting.get()
+                                                                                     aria-busy=move || is_deleting.get().then_some("true")>
+                                                                                    {move || {
+                                                                                        let m_check = m_check.clone();
+                                                                                        let m_checked = m_check_for_closure.clone();
+                                                                                        select_mode.get().then(|| view! {
+                                                                                            <input type="checkbox"
+                                                                                                   class="model-select-checkbox"
+                                                                                                   prop:checked=move || selected_for_delete.get().contains(&m_checked)
+                                                                                                   on:click=move |ev: web_sys::MouseEvent| ev.stop_propagation()
+                                                                                                   on:change=move |_| {
+                                                                                                       let model = m_check.clone();
+                                                                                                       set_selected_for_delete.update(|selected| {
+                                                                                                           if let Some(pos) = selected.iter().position(|m| m == &model) {
+                                                                                                               selected.remove(pos);
+                                                                                                           } else {
+                                                                                                               selected.push(model);
+                                                                                                           }
+                                                                                                       });
+                                                                                                   } />
+                                                                                        })
+                                                                                    }}
+                                                                                    <button
+                                                                                        class="model-star-btn"
+                                                                                        class:starred=is_favorite
+                                                                                        title=if is_favorite { "Remove from favorites" } else { "Add to favorites" }
+                                                                                        on:click=move |ev: web_sys::MouseEvent| {
+                                                                                            ev.stop_propagation();
+                                                                                            toggle_favorite(m_star.clone());
+                                                                                        }>
+                                                                                        {if is_favorite { "★" } else { "☆" }}
+                                                                                    </button>
                                                                                     <div class="model-option"
+                                                                                         role="menuitem"
+                                                                                         tabindex="-1"
+                                                                                         title=m_title
                                                                                          on:click=move |ev: web_sys::MouseEvent| {
                                                                                              ev.stop_propagation();
                                                                                              select_model(m_click.clone());
@@ -1423,8 +5247,23 @@ pub fn App() -> impl IntoView {
                                                                                          on:touchend=move |ev: web_sys::TouchEvent| {
                                                                                              ev.stop_propagation();
                                                                                              select_model(m_touch.clone());
+                                                                                         }
+                                                                                         aria-keyshortcuts="Delete"
+                                                                                         on:keydown=move |ev: web_sys::KeyboardEvent| {
+                                                                                             if ev.key() == "Delete" && !bulk_deleting.get_untracked() {
+                                                                                                 ev.prevent_default();
+                                                                                                 mark_for_delete(m_delete_key.clone());
+                                                                                                 // The row is about to go away; keep focus in the menu
+                                                                                                 focus_first_menu_item("model-menu");
+                                                                                             }
                                                                                          }>
                                                                                         {m_display}
+                                                                                        {move || model_caps.with(|caps| {
+                                                                                            caps.get(&m_caps).map(|list| capability_badges(list))
+                                                                                        })}
+                                                                                        {fit_warning.map(|title| view! {
+                                                                                            <span class="model-badge fit-warning" title=title>"may not fit"</span>
+                                                                                        })}
                                                                                         {if is_cloud_model {
                                                                                             view! {
                                                                                                 <span class="cloud-warning" title="Cloud models not supported at this time">"⚠️"</span>
@@ -1433,19 +5272,68 @@ pub fn App() -> impl IntoView {
                                                                                             view! { <></> }.into_any()
                                                                                         }}
                                                                                     </div>
+                                                                                    {move || {
+                                                                                        let model = m_continue.clone();
+                                                                                        let offered = per_model_chats.get()
+                                                                                            && !messages.with(|m| m.is_empty())
+                                                                                            && selected_model.get().as_ref() != Some(&model);
+                                                                                        offered.then(|| view! {
+                                                                                            <button
+                                                                                                class="model-continue-btn"
+                                                                                                title=format!("Continue this chat with {}", model)
+                                                                                                aria-label=format!("Continue this chat with {}", model)
+                                                                                                on:click=move |ev: web_sys::MouseEvent| {
+                                                                                                    ev.stop_propagation();
+                                                                                                    continue_with_model(model.clone());
+                                                                                                }>
+                                                                                                "↪"
+                                                                                            </button>
+                                                                                        }.into_any())
+                                                                                    }}
+                                                                                    <button
+                                                                                        class="model-copy-btn"
+                                                                                        title="Copy model name"
+                                                                                        on:click=move |ev: web_sys::MouseEvent| {
+                                                                                            ev.stop_propagation();
+                                                                                            copy_to_clipboard(&m_copy);
+                                                                                            set_copied_model.set(Some(m_copy.clone()));
+                                                                                        }>
+                                                                                        {move || if copied_model.get().as_ref() == Some(&m_copy_for_closure) { "✓" } else { "📋" }}
+                                                                                    </button>
+                                                                                    <button
+                                                                                        class="model-warm-btn"
+                                                                                        title="Warm up (load into memory)"
+                                                                                        disabled=move || warming_models.get().contains(&m_warm_for_closure)
+                                                                                        on:click=move |ev: web_sys::MouseEvent| {
+                                                                                            ev.stop_propagation();
+                                                                                            do_warm_up(m_warm.clone());
+                                                                                        }>
+                                                                                        {move || if warming_models.get().contains(&m_warm_for_label) { "⏳" } else { "🔥" }}
+                                                                                    </button>
                                                                                     <button
                                                                                         class="model-delete-btn"
                                                                                         title="Delete model"
-                                                                                        disabled=is_deleting()
+                                                                                        disabled=move || is_deleting.get() || bulk_deleting.get()
                                                                                         on:click=move |ev: web_sys::MouseEvent| {
                                                                                             ev.stop_propagation();
-                                                                                            do_delete_model(m_delete.clone());
+                                                                                            mark_for_delete(m_delete.clone());
                                                                                         }>
-                                                                                        {if is_deleting() { "..." } else { "❌" }}
+                                                                                        {move || if is_deleting.get() {
+                                                                                            view! { <span class="delete-spinner" aria-label="Deleting"></span> }.into_any()
+                                                                                        } else {
+                                                                                            "❌".into_any()
+                                                                                        }}
                                                                                     </button>
                                                                                 </div>
                                                                             }
-                                                                        }).collect_view()}
+                                                                        }).collect_view();
+                                                                        view! {
+                                                                            {rows}
+                                                                            {(hidden > 0).then(|| view! {
+                                                                                <div class="no-models">{format!("{} hidden that may not fit in memory", if hidden == 1 { "1 model".to_string() } else { format!("{} models", hidden) })}</div>
+                                                                            })}
+                                                                        }
+                                                                        }}
                                                                     </div>
                                                                 }.into_any()
                                                             }
@@ -1521,6 +5409,8 @@ pub fn App() -> impl IntoView {
                                                        target="_blank"
                                                        rel="noopener noreferrer"
                                                        class="model-option library-link"
+                                                       role="menuitem"
+                                                       tabindex="-1"
                                                        on:click=move |ev: web_sys::MouseEvent| ev.stop_propagation()>
                                                         "📚 Browse Models"
                                                     </a>
@@ -1578,6 +5468,8 @@ pub fn App() -> impl IntoView {
                                                     } else {
                                                         view! {
                                                             <div class="model-option add-model-option"
+                                                                 role="menuitem"
+                                                                 tabindex="-1"
                                                                  on:click=move |ev: web_sys::MouseEvent| {
                                                                      ev.stop_propagation();
                                                                      set_show_add_cloud_model.set(true);
@@ -1590,7 +5482,7 @@ pub fn App() -> impl IntoView {
 
                                                 <div class="model-divider"></div>
 
-                                                <Suspense fallback=move || view! { <div class="loading-models">"Loading cloud models..."</div> }>
+                                                <Suspense fallback=move || view! { <SkeletonRows rows=2/> }>
                                                     {move || {
                                                         cloud_models_resource.get().map(|result| {
                                                             match result {
@@ -1768,11 +5660,30 @@ pub fn App() -> impl IntoView {
                 </div>
 
                 <div class="header-right">
-                    <div class="status-dropdown">
-                        <button class="status-button"
+                    <div class="status-dropdown"
+                         on:keydown=move |ev: web_sys::KeyboardEvent| {
+                             if status_dropdown_open.get() {
+                                 menu_keydown(&ev, "status-menu", "status-button", move || set_status_dropdown_open.set(false));
+                             }
+                         }>
+                        <button id="status-button"
+                                class="status-button"
+                                aria-haspopup="menu"
+                                aria-controls="status-menu"
+                                aria-expanded=move || status_dropdown_open.get().to_string()
                                 on:click=move |ev: web_sys::MouseEvent| {
                                     ev.stop_propagation();
                                     set_status_dropdown_open.update(|v| *v = !*v);
+                                    if status_dropdown_open.get_untracked() && ev.detail() == 0 {
+                                        focus_first_menu_item("status-menu");
+                                    }
+                                }
+                                on:keydown=move |ev: web_sys::KeyboardEvent| {
+                                    if ev.key() == "ArrowDown" && !status_dropdown_open.get() {
+                                        ev.prevent_default();
+                                        set_status_dropdown_open.set(true);
+                                        focus_first_menu_item("status-menu");
+                                    }
                                 }>
                             <span class="status-dot"
                                   class:status-green=move || ollama_running.get() && !(brave_search_enabled.get() && brave_api_token.get().trim().is_empty())
@@ -1781,7 +5692,10 @@ pub fn App() -> impl IntoView {
                             </span>
                             "Status"
                         </button>
-                        <div class="status-menu"
+                        <div id="status-menu"
+                             class="status-menu"
+                             role="menu"
+                             aria-label="Status"
                              class:hidden=move || !status_dropdown_open.get()
                              on:click=move |ev: web_sys::MouseEvent| ev.stop_propagation()>
                             <div class="status-menu-item">
@@ -1789,6 +5703,9 @@ pub fn App() -> impl IntoView {
                                 <label class="toggle-switch">
                                     <input type="checkbox"
                                            id="ollama-toggle"
+                                           role="menuitemcheckbox"
+                                           aria-label="Ollama Serve"
+                                           aria-checked=move || ollama_running.get().to_string()
                                            prop:checked=move || ollama_running.get()
                                            prop:disabled=move || toggle_pending.get()
                                            on:change=move |_| {
@@ -1807,17 +5724,16 @@ pub fn App() -> impl IntoView {
                                 <label class="toggle-switch">
                                     <input type="checkbox"
                                            id="brave-toggle"
+                                           role="menuitemcheckbox"
+                                           aria-label="Web Search"
+                                           aria-checked=move || brave_search_enabled.get().to_string()
                                            prop:checked=move || brave_search_enabled.get()
                                            on:change=move |_| {
                                                let new_val = !brave_search_enabled.get();
                                                set_brave_search_enabled.set(new_val);
                                                #[cfg(target_arch = "wasm32")]
                                                {
-                                                   if let Some(window) = web_sys::window() {
-                                                       if let Ok(Some(storage)) = window.local_storage() {
-                                                           let _ = storage.set_item("brave_search_enabled", if new_val { "true" } else { "false" });
-                                                       }
-                                                   }
+                                                   storage_set("brave_search_enabled", if new_val { "true" } else { "false" });
                                                }
                                            } />
                                     <span class="slider"></span>
@@ -1848,11 +5764,7 @@ pub fn App() -> impl IntoView {
                                                         let token = brave_api_token.get();
                                                         #[cfg(target_arch = "wasm32")]
                                                         {
-                                                            if let Some(window) = web_sys::window() {
-                                                                if let Ok(Some(storage)) = window.local_storage() {
-                                                                    let _ = storage.set_item("brave_api_token", &token);
-                                                                }
-                                                            }
+                                                            storage_set("brave_api_token", &token);
                                                         }
                                                         set_brave_test_status.set(Some("Saved!".to_string()));
                                                     }
@@ -1867,11 +5779,7 @@ pub fn App() -> impl IntoView {
                                                     let token = brave_api_token.get();
                                                     #[cfg(target_arch = "wasm32")]
                                                     {
-                                                        if let Some(window) = web_sys::window() {
-                                                            if let Ok(Some(storage)) = window.local_storage() {
-                                                                let _ = storage.set_item("brave_api_token", &token);
-                                                            }
-                                                        }
+                                                        storage_set("brave_api_token", &token);
                                                     }
                                                     set_brave_test_status.set(Some("Saved!".to_string()));
                                                 }>
@@ -1931,6 +5839,670 @@ pub fn App() -> impl IntoView {
                                 </div>
                             </div>
 
+                            // Chat settings submenu (appears on hover)
+                            <div class="status-menu-item settings-item"
+                                 role="menuitem"
+                                 tabindex="-1"
+                                 aria-haspopup="true"
+                                 aria-expanded=move || settings_submenu_open.get().to_string()
+                                 on:mouseenter=move |_| set_settings_submenu_open.set(true)
+                                 on:mouseleave=move |_| set_settings_submenu_open.set(false)
+                                 on:click=move |_| set_settings_submenu_open.set(true)>
+                                <span class="status-label">"Chat Settings"</span>
+                                <span class="diagnostics-caret">"◂"</span>
+
+                                <div class="settings-submenu"
+                                     class:hidden=move || !settings_submenu_open.get()
+                                     on:click=move |ev: web_sys::MouseEvent| ev.stop_propagation()>
+                                    <div class="settings-submenu-content">
+                                        <div class="settings-row">
+                                            <label class="settings-label" for="keep-alive-select">"Keep model loaded"</label>
+                                            <select id="keep-alive-select"
+                                                    class="settings-select"
+                                                    prop:value=move || keep_alive.get()
+                                                    on:change=move |ev| {
+                                                        let value = event_target_value(&ev);
+                                                        set_keep_alive.set(value.clone());
+                                                        #[cfg(target_arch = "wasm32")]
+                                                        {
+                                                            storage_set("keep_alive", &value);
+                                                        }
+                                                    }>
+                                                <option value="">"Ollama default"</option>
+                                                <option value="5m">"5 minutes"</option>
+                                                <option value="30m">"30 minutes"</option>
+                                                <option value="1h">"1 hour"</option>
+                                                <option value="-1">"Forever"</option>
+                                                <option value="0">"Unload after reply"</option>
+                                            </select>
+                                        </div>
+                                        <div class="settings-row">
+                                            <span class="settings-label">"Show timestamps"</span>
+                                            <label class="toggle-switch">
+                                                <input type="checkbox"
+                                                       prop:checked=move || show_timestamps.get()
+                                                       on:change=move |_| {
+                                                           let new_val = !show_timestamps.get();
+                                                           set_show_timestamps.set(new_val);
+                                                           set_clock.set(now_millis());
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("show_timestamps", if new_val { "true" } else { "false" });
+                                                           }
+                                                       } />
+                                                <span class="slider"></span>
+                                            </label>
+                                        </div>
+                                        <div class="settings-row" title="Start each reply with [hostname]:">
+                                            <span class="settings-label">"Show host name on replies"</span>
+                                            <label class="toggle-switch">
+                                                <input type="checkbox"
+                                                       prop:checked=move || show_host_prefix.get()
+                                                       on:change=move |_| {
+                                                           let new_val = !show_host_prefix.get();
+                                                           set_show_host_prefix.set(new_val);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("show_host_prefix", if new_val { "true" } else { "false" });
+                                                           }
+                                                       } />
+                                                <span class="slider"></span>
+                                            </label>
+                                        </div>
+                                        {view! {
+                                            <div class="settings-row" title="New text fades in as a reply streams; skipped while tokens arrive faster than it could show">
+                                                <span class="settings-label">"Typewriter effect"</span>
+                                                <label class="toggle-switch">
+                                                    <input type="checkbox"
+                                                           prop:checked=move || typewriter.get()
+                                                           on:change=move |_| {
+                                                               let new_val = !typewriter.get();
+                                                               set_typewriter.set(new_val);
+                                                               #[cfg(target_arch = "wasm32")]
+                                                               {
+                                                                   storage_set("typewriter", if new_val { "true" } else { "false" });
+                                                               }
+                                                           } />
+                                                    <span class="slider"></span>
+                                                </label>
+                                            </div>
+                                        }.into_any()}
+                                        {view! {
+                                            <div class="settings-row" title="Stop checking Ollama's status while this tab is in the background; downloads are checked less often">
+                                                <span class="settings-label">"Pause polling in background"</span>
+                                                <label class="toggle-switch">
+                                                    <input type="checkbox"
+                                                           prop:checked=move || pause_hidden_polling.get()
+                                                           on:change=move |_| {
+                                                               let new_val = !pause_hidden_polling.get();
+                                                               set_pause_hidden_polling.set(new_val);
+                                                               #[cfg(target_arch = "wasm32")]
+                                                               {
+                                                                   storage_set("pause_hidden_polling", if new_val { "true" } else { "false" });
+                                                               }
+                                                           } />
+                                                    <span class="slider"></span>
+                                                </label>
+                                            </div>
+                                        }.into_any()}
+                                        {view! {
+                                            <div class="settings-row" title="Each model keeps its own chat, brought back when you select it again; ↪ next to a model continues the current chat with it instead. Off: switching models continues the current chat with the new model.">
+                                                <span class="settings-label">"Separate chat per model"</span>
+                                                <label class="toggle-switch">
+                                                    <input type="checkbox"
+                                                           prop:checked=move || per_model_chats.get()
+                                                           on:change=move |_| {
+                                                               let new_val = !per_model_chats.get();
+                                                               set_per_model_chats.set(new_val);
+                                                               #[cfg(target_arch = "wasm32")]
+                                                               {
+                                                                   storage_set("per_model_chats", if new_val { "true" } else { "false" });
+                                                               }
+                                                           } />
+                                                    <span class="slider"></span>
+                                                </label>
+                                            </div>
+                                        }.into_any()}
+                                        {view! {
+                                            <div class="settings-row" title="When a download of a model that isn't installed yet is cancelled, delete what it downloaded. Off keeps it, so pulling again resumes where it stopped.">
+                                                <span class="settings-label">"Delete cancelled downloads"</span>
+                                                <label class="toggle-switch">
+                                                    <input type="checkbox"
+                                                           prop:checked=move || clean_cancelled_pulls.get()
+                                                           on:change=move |_| {
+                                                               let new_val = !clean_cancelled_pulls.get();
+                                                               set_clean_cancelled_pulls.set(new_val);
+                                                               #[cfg(target_arch = "wasm32")]
+                                                               {
+                                                                   storage_set("clean_cancelled_pulls", if new_val { "true" } else { "false" });
+                                                               }
+                                                           } />
+                                                    <span class="slider"></span>
+                                                </label>
+                                            </div>
+                                        }.into_any()}
+                                        <div class="settings-row">
+                                            <label class="settings-label" for="num-predict-input">"Max reply tokens"</label>
+                                            <input id="num-predict-input"
+                                                   type="number"
+                                                   min="-1"
+                                                   step="1"
+                                                   class="settings-input settings-number"
+                                                   placeholder="default"
+                                                   title="-1 for unlimited, 0 to only load the model"
+                                                   prop:value=move || num_predict.get()
+                                                   on:input=move |ev| {
+                                                       let value = event_target_value(&ev);
+                                                       set_num_predict.set(value.clone());
+                                                       #[cfg(target_arch = "wasm32")]
+                                                       {
+                                                           storage_set("num_predict", &value);
+                                                       }
+                                                   }
+                                                   on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation() />
+                                        </div>
+                                        {move || num_predict_value().err().map(|err| view! { <div class="settings-error">{err}</div> })}
+                                        <div class="settings-row">
+                                            <label class="settings-label" for="stop-sequences-input">"Stop sequences"</label>
+                                        </div>
+                                        <div class="settings-block">
+                                            <textarea id="stop-sequences-input"
+                                                      class="settings-textarea"
+                                                      rows="2"
+                                                      placeholder="One per line, e.g. ### or \n\n"
+                                                      title="Generation stops when the reply produces any of these. Write \n for a newline and \t for a tab."
+                                                      prop:value=move || stop_sequences.get()
+                                                      on:input=move |ev| {
+                                                          let value = event_target_value(&ev);
+                                                          set_stop_sequences.set(value.clone());
+                                                          #[cfg(target_arch = "wasm32")]
+                                                          {
+                                                              storage_set("stop_sequences", &value);
+                                                          }
+                                                      }
+                                                      on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation()>
+                                            </textarea>
+                                        </div>
+                                        // How much of the model runs on the GPU; what helps depends on the model and hardware.
+                                        // Type-erased so the settings menu's view type stays small enough to compile.
+                                        {view! {
+                                            <div class="settings-row">
+                                                <label class="settings-label" for="num-gpu-input">"GPU layers"</label>
+                                                <input id="num-gpu-input"
+                                                       type="number"
+                                                       min="0"
+                                                       step="1"
+                                                       class="settings-input settings-number"
+                                                       placeholder="auto"
+                                                       title="Layers offloaded to the GPU (num_gpu); 0 runs on the CPU only. Effects depend on the model and hardware."
+                                                       prop:value=move || num_gpu.get()
+                                                       on:input=move |ev| {
+                                                           let value = event_target_value(&ev);
+                                                           set_num_gpu.set(value.clone());
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("num_gpu", &value);
+                                                           }
+                                                       }
+                                                       on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation() />
+                                            </div>
+                                            <div class="settings-row">
+                                                <label class="settings-label" for="main-gpu-input">"Main GPU"</label>
+                                                <input id="main-gpu-input"
+                                                       type="number"
+                                                       min="0"
+                                                       step="1"
+                                                       class="settings-input settings-number"
+                                                       placeholder="auto"
+                                                       title="Index of the GPU that holds small tensors and scratch buffers when several are used (main_gpu)"
+                                                       prop:value=move || main_gpu.get()
+                                                       on:input=move |ev| {
+                                                           let value = event_target_value(&ev);
+                                                           set_main_gpu.set(value.clone());
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("main_gpu", &value);
+                                                           }
+                                                       }
+                                                       on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation() />
+                                            </div>
+                                            <div class="settings-row" title="Trade speed for less GPU memory (low_vram); not every Ollama version or backend honours it">
+                                                <span class="settings-label">"Low VRAM mode"</span>
+                                                <label class="toggle-switch">
+                                                    <input type="checkbox"
+                                                           prop:checked=move || low_vram.get()
+                                                           on:change=move |_| {
+                                                               let new_val = !low_vram.get();
+                                                               set_low_vram.set(new_val);
+                                                               #[cfg(target_arch = "wasm32")]
+                                                               {
+                                                                   storage_set("low_vram", if new_val { "true" } else { "false" });
+                                                               }
+                                                           } />
+                                                    <span class="slider"></span>
+                                                </label>
+                                            </div>
+                                            {move || gpu_options_value().err().map(|err| view! { <div class="settings-error">{err}</div> })}
+                                        }.into_any()}
+                                        <div class="settings-row">
+                                            <label class="settings-label" for="context-max-input">"Attached file limit"</label>
+                                            <input id="context-max-input"
+                                                   type="number"
+                                                   min="1"
+                                                   step="1000"
+                                                   class="settings-input settings-number"
+                                                   title="Characters of a dropped file sent as context"
+                                                   prop:value=move || context_max_chars.get().to_string()
+                                                   on:input=move |ev| {
+                                                       if let Ok(max_chars) = event_target_value(&ev).parse::<usize>() {
+                                                           let max_chars = max_chars.max(1);
+                                                           set_context_max_chars.set(max_chars);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("context_max_chars", &max_chars.to_string());
+                                                           }
+                                                       }
+                                                   }
+                                                   on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation() />
+                                        </div>
+                                        <div class="settings-row">
+                                            <label class="settings-label" for="max-messages-input">"Max messages"</label>
+                                            <input id="max-messages-input"
+                                                   type="number"
+                                                   min="0"
+                                                   step="10"
+                                                   class="settings-input settings-number"
+                                                   title="Oldest messages beyond this are dropped from the conversation; 0 keeps everything"
+                                                   prop:value=move || max_messages.get().to_string()
+                                                   on:input=move |ev| {
+                                                       if let Ok(limit) = event_target_value(&ev).parse::<usize>() {
+                                                           set_max_messages.set(limit);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("max_messages", &limit.to_string());
+                                                           }
+                                                       }
+                                                   }
+                                                   on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation() />
+                                        </div>
+                                        <div class="settings-row">
+                                            <label class="settings-label" for="max-conversations-input">"Max conversations"</label>
+                                            <input id="max-conversations-input"
+                                                   type="number"
+                                                   min="0"
+                                                   step="1"
+                                                   class="settings-input settings-number"
+                                                   title="Conversations kept in this browser, counting the current one and each model's own chat; the chats set aside longest ago are dropped first. 0 keeps everything"
+                                                   prop:value=move || max_conversations.get().to_string()
+                                                   on:input=move |ev| {
+                                                       if let Ok(limit) = event_target_value(&ev).parse::<usize>() {
+                                                           set_max_conversations.set(limit);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("max_conversations", &limit.to_string());
+                                                           }
+                                                       }
+                                                   }
+                                                   on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation() />
+                                        </div>
+                                        <div class="settings-row">
+                                            <label class="settings-label" for="idle-clear-input">"Clear after idle (min)"</label>
+                                            <input id="idle-clear-input"
+                                                   type="number"
+                                                   min="0"
+                                                   step="5"
+                                                   class="settings-input settings-number"
+                                                   title="Clear the conversation and prompt history after this many minutes without input, for shared screens; 0 never clears"
+                                                   prop:value=move || idle_clear_minutes.get().to_string()
+                                                   on:input=move |ev| {
+                                                       if let Ok(minutes) = event_target_value(&ev).parse::<u32>() {
+                                                           set_idle_clear_minutes.set(minutes);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("idle_clear_minutes", &minutes.to_string());
+                                                           }
+                                                       }
+                                                   }
+                                                   on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation() />
+                                        </div>
+                                        <div class="settings-row" title="Start fresh on every visit: the conversation and prompt history aren't kept when the page closes or reloads">
+                                            <span class="settings-label">"Clear when page closes"</span>
+                                            <label class="toggle-switch">
+                                                <input type="checkbox"
+                                                       prop:checked=move || clear_on_unload.get()
+                                                       on:change=move |_| {
+                                                           let new_val = !clear_on_unload.get();
+                                                           set_clear_on_unload.set(new_val);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("clear_on_unload", if new_val { "true" } else { "false" });
+                                                           }
+                                                       } />
+                                                <span class="slider"></span>
+                                            </label>
+                                        </div>
+                                        <div class="settings-row" title="Settings, prompt history and conversations saved in this browser">
+                                            <span class="settings-label">"Browser storage"</span>
+                                            <span class="settings-value">
+                                                {move || {
+                                                    let conversations = model_chats.with_value(|chats| chats.len())
+                                                        + usize::from(!messages.with(|msgs| msgs.is_empty()));
+                                                    let conversations = match max_conversations.get() {
+                                                        0 => format!("{} conversation{}", conversations, if conversations == 1 { "" } else { "s" }),
+                                                        limit => format!("{} of {} conversations", conversations, limit),
+                                                    };
+                                                    match storage_used.get() {
+                                                        Some(bytes) => format!("{} used, {}", format_bytes(bytes), conversations),
+                                                        None => "Unavailable".to_string(),
+                                                    }
+                                                }}
+                                            </span>
+                                        </div>
+                                        <div class="settings-row" title="Once a day, the server asks GitHub for the latest Ollama release">
+                                            <span class="settings-label">"Check for Ollama updates"</span>
+                                            <label class="toggle-switch">
+                                                <input type="checkbox"
+                                                       prop:checked=move || update_check.get()
+                                                       on:change=move |_| {
+                                                           let new_val = !update_check.get();
+                                                           set_update_check.set(new_val);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("update_check", if new_val { "true" } else { "false" });
+                                                           }
+                                                       } />
+                                                <span class="slider"></span>
+                                            </label>
+                                        </div>
+                                        <div class="settings-row" title="Also save conversations on this server, to open them from other browsers">
+                                            <span class="settings-label">"Server sync"</span>
+                                            <label class="toggle-switch">
+                                                <input type="checkbox"
+                                                       prop:checked=move || server_sync.get()
+                                                       on:change=move |_| {
+                                                           let new_val = !server_sync.get();
+                                                           set_server_sync.set(new_val);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("server_sync", if new_val { "true" } else { "false" });
+                                                           }
+                                                       } />
+                                                <span class="slider"></span>
+                                            </label>
+                                        </div>
+                                        <div class="settings-row">
+                                            <label class="settings-label" for="send-key-select">"Send with"</label>
+                                            <select id="send-key-select"
+                                                    class="settings-select"
+                                                    prop:value=move || send_key.get()
+                                                    on:change=move |ev| {
+                                                        let value = event_target_value(&ev);
+                                                        set_send_key.set(value.clone());
+                                                        #[cfg(target_arch = "wasm32")]
+                                                        {
+                                                            storage_set("send_key", &value);
+                                                        }
+                                                    }>
+                                                <option value="enter">"Enter"</option>
+                                                <option value="ctrl_enter">"Ctrl+Enter"</option>
+                                            </select>
+                                        </div>
+                                        <div class="settings-row">
+                                            <span class="settings-label">"JSON mode"</span>
+                                            <label class="toggle-switch">
+                                                <input type="checkbox"
+                                                       prop:checked=move || json_mode.get()
+                                                       on:change=move |_| {
+                                                           let new_val = !json_mode.get();
+                                                           set_json_mode.set(new_val);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("json_mode", if new_val { "true" } else { "false" });
+                                                           }
+                                                       } />
+                                                <span class="slider"></span>
+                                            </label>
+                                        </div>
+                                        <div class="settings-block" class:hidden=move || !json_mode.get()>
+                                            <textarea class="settings-textarea"
+                                                      rows="4"
+                                                      placeholder="Optional JSON schema, e.g. {\"type\": \"object\", ...}"
+                                                      prop:value=move || json_schema.get()
+                                                      on:input=move |ev| {
+                                                          let schema = event_target_value(&ev);
+                                                          set_json_schema.set(schema.clone());
+                                                          #[cfg(target_arch = "wasm32")]
+                                                          {
+                                                              storage_set("json_schema", &schema);
+                                                          }
+                                                      }
+                                                      on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation()>
+                                            </textarea>
+                                            {move || json_format().err().map(|err| view! { <div class="settings-error">{err}</div> })}
+                                        </div>
+                                        <div class="settings-row" title="Offer these functions to models that support tool calling">
+                                            <span class="settings-label">"Tools"</span>
+                                            <label class="toggle-switch">
+                                                <input type="checkbox"
+                                                       prop:checked=move || tools_enabled.get()
+                                                       on:change=move |_| {
+                                                           let new_val = !tools_enabled.get();
+                                                           set_tools_enabled.set(new_val);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("tools_enabled", if new_val { "true" } else { "false" });
+                                                           }
+                                                       } />
+                                                <span class="slider"></span>
+                                            </label>
+                                        </div>
+                                        <div class="settings-block" class:hidden=move || !tools_enabled.get()>
+                                            <textarea class="settings-textarea"
+                                                      rows="6"
+                                                      placeholder="[{\"type\": \"function\", \"function\": {\"name\": \"get_weather\", \"parameters\": {\"type\": \"object\", ...}}}]"
+                                                      prop:value=move || tool_definitions.get()
+                                                      on:input=move |ev| {
+                                                          let tools = event_target_value(&ev);
+                                                          set_tool_definitions.set(tools.clone());
+                                                          #[cfg(target_arch = "wasm32")]
+                                                          {
+                                                              storage_set("tools", &tools);
+                                                          }
+                                                      }
+                                                      on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation()>
+                                            </textarea>
+                                            {move || tools_value().err().map(|err| view! { <div class="settings-error">{err}</div> })}
+                                        </div>
+                                        {view! {
+                                            <div class="settings-row" title="Chat sends the whole conversation to /api/chat. Generate sends only your latest message (and the latest system message) to /api/generate, through the model's template.">
+                                                <label class="settings-label" for="endpoint-select">"Endpoint"</label>
+                                                <select id="endpoint-select"
+                                                        class="settings-select"
+                                                        prop:value=move || endpoint.get()
+                                                        on:change=move |ev| {
+                                                            let value = event_target_value(&ev);
+                                                            set_endpoint.set(value.clone());
+                                                            #[cfg(target_arch = "wasm32")]
+                                                            {
+                                                                storage_set("endpoint", &value);
+                                                            }
+                                                        }>
+                                                    <option value="chat">"Chat"</option>
+                                                    <option value="generate">"Generate"</option>
+                                                </select>
+                                            </div>
+                                        }.into_any()}
+                                        <div class="settings-row" title="Send the prompt verbatim to /api/generate without the model's template. Earlier messages are not sent.">
+                                            <span class="settings-label">"Raw mode"</span>
+                                            <label class="toggle-switch">
+                                                <input type="checkbox"
+                                                       prop:checked=move || raw_mode.get()
+                                                       on:change=move |_| {
+                                                           let new_val = !raw_mode.get();
+                                                           set_raw_mode.set(new_val);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("raw_mode", if new_val { "true" } else { "false" });
+                                                           }
+                                                       } />
+                                                <span class="slider"></span>
+                                            </label>
+                                        </div>
+                                        <div class="settings-row" title="If Ollama is off when you send a message, start it and retry">
+                                            <span class="settings-label">"Start Ollama if needed"</span>
+                                            <label class="toggle-switch">
+                                                <input type="checkbox"
+                                                       prop:checked=move || auto_start.get()
+                                                       on:change=move |_| {
+                                                           let new_val = !auto_start.get();
+                                                           set_auto_start.set(new_val);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("auto_start", if new_val { "true" } else { "false" });
+                                                           }
+                                                       } />
+                                                <span class="slider"></span>
+                                            </label>
+                                        </div>
+                                        <div class="settings-row">
+                                            <span class="settings-label">"Show raw request and response"</span>
+                                            <label class="toggle-switch">
+                                                <input type="checkbox"
+                                                       prop:checked=move || show_raw_request.get()
+                                                       on:change=move |_| {
+                                                           let new_val = !show_raw_request.get();
+                                                           set_show_raw_request.set(new_val);
+                                                           #[cfg(target_arch = "wasm32")]
+                                                           {
+                                                               storage_set("show_raw_request", if new_val { "true" } else { "false" });
+                                                           }
+                                                       } />
+                                                <span class="slider"></span>
+                                            </label>
+                                        </div>
+                                    </div>
+                                </div>
+                            </div>
+
+                            <div class="status-menu-item clickable-item"
+                                 role="menuitem"
+                                 tabindex="-1"
+                                 on:click=move |_| {
+                                     set_benchmark_open.set(true);
+                                     set_status_dropdown_open.set(false);
+                                 }>
+                                <span class="status-label">"Benchmark…"</span>
+                            </div>
+
+                            <div class="status-menu-item clickable-item"
+                                 role="menuitem"
+                                 tabindex="-1"
+                                 on:click=move |_| {
+                                     set_compare_open.set(true);
+                                     set_status_dropdown_open.set(false);
+                                 }>
+                                <span class="status-label">"Compare models…"</span>
+                            </div>
+
+                            <div class="status-menu-item clickable-item"
+                                 role="menuitem"
+                                 tabindex="-1"
+                                 on:click=move |_| {
+                                     set_storage_open.set(true);
+                                     set_status_dropdown_open.set(false);
+                                     load_storage();
+                                 }>
+                                <span class="status-label">"Storage…"</span>
+                            </div>
+
+                            {view! {
+                                <div class="status-menu-item clickable-item"
+                                     role="menuitem"
+                                     tabindex="-1"
+                                     on:click=move |_| {
+                                         set_modelfile_open.set(true);
+                                         set_status_dropdown_open.set(false);
+                                         if modelfile_text.get_untracked().is_empty() {
+                                             load_modelfile(selected_model.get_untracked().unwrap_or_default());
+                                         }
+                                     }>
+                                    <span class="status-label">"Edit Modelfile…"</span>
+                                </div>
+                            }.into_any()}
+
+                            <div class="status-menu-item clickable-item"
+                                 role="menuitem"
+                                 tabindex="-1"
+                                 on:click=move |_| {
+                                     set_conversations_open.set(true);
+                                     set_status_dropdown_open.set(false);
+                                     load_conversations();
+                                 }>
+                                <span class="status-label">"Saved conversations…"</span>
+                            </div>
+
+                            {move || ollama_update.get().map(|update| view! {
+                                <a class="status-menu-item update-hint"
+                                   role="menuitem"
+                                   tabindex="-1"
+                                   href=update.url
+                                   target="_blank"
+                                   rel="noopener noreferrer"
+                                   title=format!("Running Ollama {}", update.current)>
+                                    {format!("Ollama {} is available ↗", update.latest)}
+                                </a>
+                            })}
+
+                            // Reachability diagnostics (collapsed by default)
+                            <div class="status-menu-item diagnostics-toggle"
+                                 role="menuitem"
+                                 tabindex="-1"
+                                 aria-expanded=move || diagnostics_open.get().to_string()
+                                 on:click=move |_| {
+                                     let open = !diagnostics_open.get();
+                                     set_diagnostics_open.set(open);
+                                     if open {
+                                         refresh_reachability();
+                                     }
+                                 }>
+                                <span class="status-label">"Diagnostics"</span>
+                                <span class="diagnostics-caret">{move || if diagnostics_open.get() { "▾" } else { "▸" }}</span>
+                            </div>
+                            <div class="diagnostics-panel" class:hidden=move || !diagnostics_open.get()>
+                                <div class="diagnostics-row">
+                                    <span class="diagnostics-key">"URL"</span>
+                                    <span class="diagnostics-value">
+                                        {move || reachability.get().map(|r| r.base_url).unwrap_or_else(|| "…".to_string())}
+                                    </span>
+                                </div>
+                                <div class="diagnostics-row">
+                                    <span class="diagnostics-key">"Ping"</span>
+                                    <span class="diagnostics-value">
+                                        {move || match reachability.get() {
+                                            Some(r) if r.reachable => format!(
+                                                "{} ms{}",
+                                                r.latency_ms.unwrap_or(0),
+                                                r.version.map(|v| format!(" (v{})", v)).unwrap_or_default()
+                                            ),
+                                            Some(_) => "unreachable".to_string(),
+                                            None => "…".to_string(),
+                                        }}
+                                    </span>
+                                </div>
+                                <div class="diagnostics-row">
+                                    <span class="diagnostics-key">"Failed checks"</span>
+                                    <span class="diagnostics-value">
+                                        {move || status_failures.get().to_string()}
+                                    </span>
+                                </div>
+                                <div class="diagnostics-row">
+                                    <span class="diagnostics-key">"Last error"</span>
+                                    <span class="diagnostics-value diagnostics-error">
+                                        {move || last_error.get().unwrap_or_else(|| "none".to_string())}
+                                    </span>
+                                </div>
+                            </div>
+
                             <div class="status-divider"></div>
 
                             <div class="theme-section">
@@ -1983,6 +6555,24 @@ pub fn App() -> impl IntoView {
                                     </div>
                                 </div>
                             </div>
+
+                            <div class="status-divider"></div>
+
+                            <div class="theme-section">
+                                <div class="theme-label">"Density"</div>
+                                <div class="theme-options">
+                                    <div class="theme-option"
+                                         class:active=move || density.get() == "comfortable"
+                                         on:click=move |_| apply_density("comfortable".to_string())>
+                                        "Comfortable"
+                                    </div>
+                                    <div class="theme-option"
+                                         class:active=move || density.get() == "compact"
+                                         on:click=move |_| apply_density("compact".to_string())>
+                                        "Compact"
+                                    </div>
+                                </div>
+                            </div>
                         </div>
                     </div>
                 </div>
@@ -1997,6 +6587,15 @@ pub fn App() -> impl IntoView {
 
             // Download progress bars
             <div class="download-progress-container">
+                {move || (active_downloads.get().iter().filter(|d| !d.done).count() + update_queue.get().len() > 1).then(|| view! {
+                    <div class="download-actions">
+                        <button class="download-cancel download-cancel-all"
+                                title="Cancel all downloads"
+                                on:click=move |_| cancel_all_downloads()>
+                            "✕ Cancel all"
+                        </button>
+                    </div>
+                })}
                 {move || {
                     let downloads: Vec<_> = active_downloads.get()
                         .into_iter()
@@ -2008,7 +6607,11 @@ pub fn App() -> impl IntoView {
                         let model_for_hide = dl.model.clone();
                         let model_for_cancel = dl.model.clone();
                         let model_for_cancel_update = dl.model.clone();
+                        let model_for_pause = dl.model.clone();
+                        let model_for_retry = dl.model.clone();
+                        let error = dl.error.clone();
                         let status = dl.status.clone();
+                        let raw_status = dl.raw_status.clone();
                         let status_for_check = status.clone();
                         let percent = dl.percent;
                         let speed = dl.speed.clone();
@@ -2016,13 +6619,17 @@ pub fn App() -> impl IntoView {
 
                         let is_complete = status_for_check == "Complete";
                         let is_cancelled = status_for_check == "Cancelled";
+                        let is_paused = status_for_check == "Paused";
                         let can_cancel = !is_done && !is_complete && !is_cancelled;
+                        let created_from = created_models.with_value(|c| c.get(&dl.model).cloned());
+                        let is_create = created_from.is_some();
 
                         view! {
                             <div class="download-progress-bar">
                                 <div class="download-info">
                                     <span class="download-model">{model_name}</span>
                                     <span class="download-status"
+                                          title=(!raw_status.is_empty()).then_some(raw_status)
                                           class:download-complete=is_complete>
                                         {status}
                                     </span>
@@ -2031,17 +6638,37 @@ pub fn App() -> impl IntoView {
                                     } else {
                                         view! { <></> }.into_any()
                                     }}
+                                    // Pause stops the download but keeps what's there; resume pulls again
+                                    {(can_cancel && !is_create).then(|| view! {
+                                        <button class="download-cancel download-pause"
+                                                title=if is_paused { "Resume download" } else { "Pause download" }
+                                                on:click=move |_| {
+                                                    let model = model_for_pause.clone();
+                                                    if is_paused {
+                                                        start_download(model);
+                                                        return;
+                                                    }
+                                                    set_active_downloads.update(|downloads| {
+                                                        if let Some(d) = downloads.iter_mut().find(|d| d.model == model) {
+                                                            d.status = "Paused".to_string();
+                                                            d.speed = String::new();
+                                                        }
+                                                    });
+                                                    spawn_local(async move {
+                                                        let _ = pause_model_pull(model).await;
+                                                    });
+                                                }>
+                                            {if is_paused { "▶" } else { "⏸" }}
+                                        </button>
+                                    })}
                                     // Cancel button - stops the download
                                     {if can_cancel {
                                         view! {
                                             <button class="download-cancel"
                                                     title="Cancel download"
                                                     on:click=move |_| {
-                                                        let model = model_for_cancel.clone();
                                                         let model_update = model_for_cancel_update.clone();
-                                                        spawn_local(async move {
-                                                            let _ = cancel_model_pull(model).await;
-                                                        });
+                                                        cancel_download(model_for_cancel.clone());
                                                         set_active_downloads.update(|downloads| {
                                                             if let Some(d) = downloads.iter_mut().find(|d| d.model == model_update) {
                                                                 d.done = true;
@@ -2055,6 +6682,17 @@ pub fn App() -> impl IntoView {
                                     } else {
                                         view! { <></> }.into_any()
                                     }}
+                                    // Pulling again picks up what Ollama already has, so retries are quick
+                                    {error.map(|error| view! {
+                                        <button class="download-cancel download-retry"
+                                                title=format!("Retry ({})", error)
+                                                on:click=move |_| match created_from.clone() {
+                                                    Some(modelfile) => start_create(model_for_retry.clone(), modelfile),
+                                                    None => start_download(model_for_retry.clone()),
+                                                }>
+                                            "↻"
+                                        </button>
+                                    }.into_any())}
                                     // Hide button - just removes from UI
                                     <button class="download-hide"
                                             title="Hide"
@@ -2077,31 +6715,675 @@ pub fn App() -> impl IntoView {
                 }}
             </div>
 
+            // Benchmark panel
+            <div class="tool-panel" class:hidden=move || !benchmark_open.get()>
+                <div class="tool-panel-header">
+                    <span>"Benchmark"</span>
+                    <button class="tool-panel-close" title="Close" on:click=move |_| set_benchmark_open.set(false)>"✕"</button>
+                </div>
+                <div class="benchmark-form">
+                    <select class="settings-select"
+                            prop:value=move || {
+                                let model = benchmark_model_name.get();
+                                if model.is_empty() { selected_model.get().unwrap_or_default() } else { model }
+                            }
+                            on:change=move |ev| set_benchmark_model_name.set(event_target_value(&ev))>
+                        <Suspense fallback=|| ()>
+                            {move || status_resource.get()
+                                .and_then(|r| r.ok())
+                                .map(|status| status.models)
+                                .unwrap_or_default()
+                                .into_iter()
+                                .map(|m| view! { <option value=m.clone()>{m.clone()}</option> })
+                                .collect_view()}
+                        </Suspense>
+                    </select>
+                    <input type="text"
+                           class="settings-input benchmark-prompt"
+                           placeholder="Prompt"
+                           prop:value=move || benchmark_prompt.get()
+                           on:input=move |ev| set_benchmark_prompt.set(event_target_value(&ev)) />
+                    <input type="number"
+                           class="settings-input benchmark-runs"
+                           min="1"
+                           max="10"
+                           title="Runs"
+                           prop:value=move || benchmark_runs.get().to_string()
+                           on:input=move |ev| {
+                               if let Ok(runs) = event_target_value(&ev).parse::<u32>() {
+                                   set_benchmark_runs.set(runs.clamp(1, 10));
+                               }
+                           } />
+                    <button class="toolbar-btn"
+                            disabled=move || benchmark_pending.get()
+                            on:click=move |_| do_benchmark()>
+                        {move || if benchmark_pending.get() { "Running…" } else { "Run" }}
+                    </button>
+                </div>
+                <table class="benchmark-table" class:hidden=move || benchmark_results.get().is_empty()>
+                    <thead>
+                        <tr>
+                            <th>"Model"</th>
+                            <th>"Runs"</th>
+                            <th>"Tokens/s (min / avg / max)"</th>
+                            <th>"First token ms (min / avg / max)"</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {move || benchmark_results.get().into_iter().map(|result| {
+                            let fmt = |stat: Option<BenchmarkStat>, precision: usize| stat
+                                .map(|s| format!("{:.p$} / {:.p$} / {:.p$}", s.min, s.avg, s.max, p = precision))
+                                .unwrap_or_else(|| "—".to_string());
+                            view! {
+                                <tr>
+                                    <td>{result.model.clone()}</td>
+                                    <td>{result.runs.len()}</td>
+                                    <td>{fmt(result.tokens_per_sec.clone(), 1)}</td>
+                                    <td>{fmt(result.first_token_ms.clone(), 0)}</td>
+                                </tr>
+                                {result.error.clone().map(|err| view! {
+                                    <tr><td colspan="4" class="benchmark-error">{err}</td></tr>
+                                })}
+                            }
+                        }).collect_view()}
+                    </tbody>
+                </table>
+            </div>
+
+            // Storage panel
+            <div class="tool-panel" class:hidden=move || !storage_open.get()>
+                <div class="tool-panel-header">
+                    <span>"Storage"</span>
+                    <button class="tool-panel-close" title="Close" on:click=move |_| set_storage_open.set(false)>"✕"</button>
+                </div>
+                <div class="benchmark-form">
+                    <span class="storage-path">
+                        {move || match storage_info.get() {
+                            Some(Ok(info)) => format!("{} · {} in use", info.path, format_bytes(info.total_bytes)),
+                            _ => String::new(),
+                        }}
+                    </span>
+                    <button class="toolbar-btn"
+                            disabled=move || storage_loading.get()
+                            on:click=move |_| load_storage()>
+                        {move || if storage_loading.get() { "Reading…" } else { "Refresh" }}
+                    </button>
+                </div>
+                <div class="benchmark-form">
+                    <input type="text"
+                           class="settings-input benchmark-prompt"
+                           placeholder="New models directory (empty for the default)"
+                           prop:value=move || models_dir_input.get()
+                           on:input=move |ev| set_models_dir_input.set(event_target_value(&ev))
+                           on:keydown=move |ev| {
+                               ev.stop_propagation();
+                               if ev.key() == "Enter" { apply_models_dir() }
+                           } />
+                    <button class="toolbar-btn"
+                            title="Existing models are not moved; copy them over first to keep them"
+                            disabled=move || models_dir_pending.get()
+                            on:click=move |_| apply_models_dir()>
+                        {move || if models_dir_pending.get() { "Restarting…" } else { "Set" }}
+                    </button>
+                    <span class="storage-path">"Restarts Ollama, stopping any running chats and downloads"</span>
+                </div>
+                {move || match storage_info.get() {
+                    Some(Err(err)) => Some(view! { <div class="benchmark-error">{err}</div> }.into_any()),
+                    Some(Ok(StorageInfo { error: Some(err), .. })) => Some(view! { <div class="benchmark-error">{err}</div> }.into_any()),
+                    Some(Ok(info)) => Some(view! {
+                        <div class="storage-path" title="Deleting a model only frees its unique layers">
+                            {format!("{} unique, {} shared between models", format_bytes(info.unique_bytes), format_bytes(info.shared_bytes))}
+                        </div>
+                        {(info.skipped_manifests > 0 || info.missing_blobs > 0).then(|| {
+                            let mut notes = Vec::new();
+                            if info.skipped_manifests > 0 {
+                                notes.push(format!("{} manifest(s) couldn't be read and aren't counted", info.skipped_manifests));
+                            }
+                            if info.missing_blobs > 0 {
+                                notes.push(format!("{} layer(s) missing from the blobs directory; sizes are estimated", info.missing_blobs));
+                            }
+                            view! { <div class="benchmark-error">{notes.join(" · ")}</div> }
+                        })}
+                        <table class="benchmark-table">
+                            <thead>
+                                <tr>
+                                    <th>"Model"</th>
+                                    <th title="All layers, including ones shared with other models">"Size"</th>
+                                    <th title="Space freed by deleting only this model">"Unique"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {info.models.into_iter().map(|model| view! {
+                                    <tr>
+                                        <td>{model.name}</td>
+                                        <td>{format_bytes(model.total_bytes)}</td>
+                                        <td>{format_bytes(model.unique_bytes)}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_any()),
+                    None => None,
+                }}
+            </div>
+
+            // Modelfile editor: change a model's system prompt or parameters and save it as a new model
+            {view! {
+                <div class="tool-panel" class:hidden=move || !modelfile_open.get()>
+                    <div class="tool-panel-header">
+                        <span>"Modelfile"</span>
+                        <button class="tool-panel-close" title="Close" on:click=move |_| set_modelfile_open.set(false)>"✕"</button>
+                    </div>
+                    <div class="benchmark-form">
+                        <select class="settings-select"
+                                prop:value=move || modelfile_source.get()
+                                on:change=move |ev| load_modelfile(event_target_value(&ev))>
+                            <option value="" disabled=true>"Model to start from"</option>
+                            <Suspense fallback=|| ()>
+                                {move || status_resource.get()
+                                    .and_then(|r| r.ok())
+                                    .map(|status| status.models)
+                                    .unwrap_or_default()
+                                    .into_iter()
+                                    .map(|m| view! { <option value=m.clone()>{m.clone()}</option> })
+                                    .collect_view()}
+                            </Suspense>
+                        </select>
+                        <span class="storage-path">{move || if modelfile_loading.get() { "Loading…" } else { "" }}</span>
+                    </div>
+                    <textarea class="settings-textarea modelfile-editor"
+                              rows="14"
+                              spellcheck="false"
+                              placeholder="FROM llama3\nSYSTEM \"\"\"You are a helpful assistant.\"\"\"\nPARAMETER temperature 0.7"
+                              prop:value=move || modelfile_text.get()
+                              on:input=move |ev| set_modelfile_text.set(event_target_value(&ev))
+                              on:keydown=move |ev| ev.stop_propagation()></textarea>
+                    {move || {
+                        let text = modelfile_text.get();
+                        (!text.trim().is_empty())
+                            .then(|| modelfile_create_fields(&text).err())
+                            .flatten()
+                            .map(|err| view! { <div class="benchmark-error">{err}</div> })
+                    }}
+                    <div class="benchmark-form">
+                        <input type="text"
+                               class="settings-input benchmark-prompt"
+                               placeholder="New model name"
+                               prop:value=move || modelfile_name.get()
+                               on:input=move |ev| set_modelfile_name.set(event_target_value(&ev))
+                               on:keydown=move |ev| ev.stop_propagation() />
+                        <button class="toolbar-btn"
+                                title="Build the model with Ollama; progress shows with the downloads"
+                                disabled=move || {
+                                    modelfile_name.get().trim().is_empty() || modelfile_create_fields(&modelfile_text.get()).is_err()
+                                }
+                                on:click=move |_| start_create(modelfile_name.get_untracked(), modelfile_text.get_untracked())>
+                            "Create"
+                        </button>
+                    </div>
+                </div>
+            }.into_any()}
+
+            // Conversations saved on the server
+            <div class="tool-panel" class:hidden=move || !conversations_open.get()>
+                <div class="tool-panel-header">
+                    <span>"Saved conversations"</span>
+                    <button class="tool-panel-close" title="Close" on:click=move |_| set_conversations_open.set(false)>"✕"</button>
+                </div>
+                {move || (!server_sync.get()).then(|| view! {
+                    <div class="benchmark-form">"Turn on Server sync in Chat Settings to save conversations here."</div>
+                })}
+                {move || match conversation_list.get() {
+                    Some(Err(err)) => Some(view! { <div class="benchmark-error">{err}</div> }.into_any()),
+                    Some(Ok(list)) if list.is_empty() => Some(view! { <div class="benchmark-form">"Nothing saved yet"</div> }.into_any()),
+                    Some(Ok(list)) => Some(view! {
+                        <table class="benchmark-table">
+                            <thead>
+                                <tr>
+                                    <th>"Conversation"</th>
+                                    <th>"Messages"</th>
+                                    <th>"Updated"</th>
+                                    <th></th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {list.into_iter().map(|saved| {
+                                    let open_id = saved.id.clone();
+                                    let delete_id = saved.id.clone();
+                                    let is_current = conversation_id.get_untracked().as_ref() == Some(&saved.id);
+                                    view! {
+                                        <tr class:current-conversation=is_current>
+                                            <td>
+                                                <button class="conversation-open"
+                                                        type="button"
+                                                        title="Open this conversation"
+                                                        on:click=move |_| open_conversation(open_id.clone())>
+                                                    {saved.title}
+                                                </button>
+                                            </td>
+                                            <td>{saved.message_count}</td>
+                                            <td title=format_clock_time(saved.updated_at)>{format_relative_time(saved.updated_at, clock.get_untracked())}</td>
+                                            <td>
+                                                <button class="tool-panel-close"
+                                                        type="button"
+                                                        title="Delete"
+                                                        aria-label="Delete conversation"
+                                                        on:click=move |_| remove_conversation(delete_id.clone())>
+                                                    "🗑"
+                                                </button>
+                                            </td>
+                                        </tr>
+                                    }
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_any()),
+                    None => None,
+                }}
+            </div>
+
+            // Model comparison panel
+            <div class="tool-panel" class:hidden=move || !compare_open.get()>
+                <div class="tool-panel-header">
+                    <span>"Compare models"</span>
+                    <button class="tool-panel-close" title="Close" on:click=move |_| set_compare_open.set(false)>"✕"</button>
+                </div>
+                <div class="benchmark-form">
+                    {[0usize, 1].map(|side| view! {
+                        <select class="settings-select"
+                                prop:value=move || { let (a, b) = compare_models.get(); if side == 0 { a } else { b } }
+                                on:change=move |ev| {
+                                    let model = event_target_value(&ev);
+                                    set_compare_models.update(|(a, b)| if side == 0 { *a = model } else { *b = model });
+                                }>
+                            <option value="" disabled=true>"Choose a model"</option>
+                            <Suspense fallback=|| ()>
+                                {move || status_resource.get()
+                                    .and_then(|r| r.ok())
+                                    .map(|status| status.models)
+                                    .unwrap_or_default()
+                                    .into_iter()
+                                    .map(|m| view! { <option value=m.clone()>{m.clone()}</option> })
+                                    .collect_view()}
+                            </Suspense>
+                        </select>
+                    })}
+                    <input type="text"
+                           class="settings-input benchmark-prompt"
+                           placeholder="Prompt"
+                           prop:value=move || compare_prompt.get()
+                           on:input=move |ev| set_compare_prompt.set(event_target_value(&ev))
+                           on:keydown=move |ev| if ev.key() == "Enter" { do_compare() } />
+                    <button class="toolbar-btn"
+                            title="Choose two different models to compare"
+                            disabled=move || !can_compare()
+                            on:click=move |_| do_compare()>
+                        {move || if compare_columns.get().iter().any(|c| c.streaming) { "Streaming…" } else { "Send" }}
+                    </button>
+                </div>
+                <div class="compare-columns" class:hidden=move || compare_columns.get()[0].model.is_empty()>
+                    {[0usize, 1].map(|side| view! {
+                        <div class="compare-column">
+                            <div class="compare-column-header">
+                                <span class="compare-model">{move || compare_columns.get()[side].model.clone()}</span>
+                                <span class="compare-rate">
+                                    {move || compare_columns.get()[side].tokens_per_sec()
+                                        .map(|rate| format!("{:.1} tok/s", rate))
+                                        .unwrap_or_default()}
+                                </span>
+                            </div>
+                            <div class="compare-text">{move || compare_columns.get()[side].text.clone()}</div>
+                        </div>
+                    })}
+                </div>
+            </div>
+
+            // Conversation toolbar
+            <div class="chat-toolbar" class:hidden=move || messages.get().is_empty()>
+                <button class="toolbar-btn"
+                        type="button"
+                        title="Clear the conversation and start over"
+                        disabled=move || is_streaming.get()
+                        on:click=move |_| new_chat()>
+                    "＋ New chat"
+                </button>
+                <button class="toolbar-btn"
+                        type="button"
+                        class:active=move || locked_model.get().is_some()
+                        title=move || match locked_model.get() {
+                            Some(model) => format!("Locked to {}: switching models starts a new chat", model),
+                            None => "Keep this conversation on the current model".to_string(),
+                        }
+                        disabled=move || locked_model.get().is_none() && selected_model.get().is_none()
+                        on:click=move |_| {
+                            if locked_model.get().is_some() {
+                                set_locked_model.set(None);
+                            } else {
+                                set_locked_model.set(selected_model.get());
+                            }
+                        }>
+                    {move || if locked_model.get().is_some() { "🔒 Model locked" } else { "🔓 Lock model" }}
+                </button>
+                <button class="toolbar-btn"
+                        type="button"
+                        title="Switch assistant messages between rendered markdown and raw text"
+                        on:click=move |_| set_render_markdown.update(|v| *v = !*v)>
+                    {move || if render_markdown.get() { "📝 Show raw" } else { "📝 Show rendered" }}
+                </button>
+                <button class="toolbar-btn"
+                        type="button"
+                        title="Copy the last request as a curl command against Ollama"
+                        disabled=move || last_request.get().is_none()
+                        on:click=move |_| {
+                            let base_url = ollama_base_url_resource
+                                .get()
+                                .and_then(|r| r.ok())
+                                .unwrap_or_else(|| "http://localhost:11434".to_string());
+                            if let Some(command) = last_request.get().and_then(|raw| model_caps.with(|caps| curl_command(&base_url, &raw, caps))) {
+                                copy_to_clipboard(&command);
+                                push_toast("curl command copied".to_string(), false);
+                            }
+                        }>
+                    "⧉ Copy as curl"
+                </button>
+                {view! {
+                    <button class="toolbar-btn"
+                            type="button"
+                            title="Copy the conversation as an OpenAI-style messages array"
+                            on:click=move |_| {
+                                copy_to_clipboard(&messages.with(|msgs| openai_messages_json(msgs)));
+                                push_toast("Messages JSON copied".to_string(), false);
+                            }>
+                        "⧉ Copy as messages JSON"
+                    </button>
+                    <button class="toolbar-btn"
+                            type="button"
+                            title="Save the conversation as an OpenAI-style messages array"
+                            on:click=move |_| download_file(
+                                "conversation-messages.json",
+                                "application/json",
+                                &messages.with(|msgs| openai_messages_json(msgs)),
+                            )>
+                        "⤓ Download messages JSON"
+                    </button>
+                }.into_any()}
+                <button class="toolbar-btn"
+                        type="button"
+                        title="Ask the model for a summary of this conversation"
+                        disabled=move || is_streaming.get() || summarizing.get() || selected_model.get().is_none()
+                        on:click=move |_| summarize()>
+                    "✎ Summarize"
+                </button>
+                <button class="toolbar-btn"
+                        type="button"
+                        title="Find text in this conversation"
+                        class:active=move || search_open.get()
+                        on:click=move |_| {
+                            let open = !search_open.get();
+                            set_search_open.set(open);
+                            if !open {
+                                set_search_query.set(String::new());
+                            }
+                        }>
+                    "🔍 Search"
+                </button>
+                <button class="toolbar-btn"
+                        type="button"
+                        title="Replies you pinned in this conversation"
+                        class:active=move || pins_open.get()
+                        on:click=move |_| set_pins_open.update(|open| *open = !*open)>
+                    {move || match messages.with(|msgs| msgs.iter().filter(|m| m.pinned).count()) {
+                        0 => "📌 Pinned".to_string(),
+                        count => format!("📌 Pinned ({})", count),
+                    }}
+                </button>
+                <button class="toolbar-btn"
+                        type="button"
+                        title="Hide everything but the conversation. Esc or start typing to come back."
+                        on:click=move |_| set_focus_mode.set(true)>
+                    "⤢ Focus"
+                </button>
+            </div>
+
+            // Way out of focus mode for pointer and touch users
+            <button class="focus-exit"
+                    type="button"
+                    title="Leave focus mode (Esc)"
+                    class:hidden=move || !focus_mode.get()
+                    on:click=move |_| set_focus_mode.set(false)>
+                "⤡ Exit focus"
+            </button>
+
+            // Pinned replies, newest last like the conversation
+            <div class="tool-panel" class:hidden=move || !pins_open.get()>
+                <div class="tool-panel-header">
+                    <span>"Pinned replies"</span>
+                    <button class="tool-panel-close" title="Close" on:click=move |_| set_pins_open.set(false)>"✕"</button>
+                </div>
+                {move || {
+                    let pinned: Vec<(i64, String)> = messages.with(|msgs| {
+                        msgs.iter()
+                            .filter(|m| m.pinned)
+                            .map(|m| {
+                                let snippet: String = m.text.split_whitespace().collect::<Vec<_>>().join(" ");
+                                let snippet = if snippet.chars().count() > 140 {
+                                    format!("{}…", snippet.chars().take(140).collect::<String>())
+                                } else {
+                                    snippet
+                                };
+                                (m.created_at, snippet)
+                            })
+                            .collect()
+                    });
+                    if pinned.is_empty() {
+                        view! { <div class="benchmark-form">"Pin a reply with 📌 to keep it here."</div> }.into_any()
+                    } else {
+                        view! {
+                            <ul class="pinned-list">
+                                {pinned.into_iter().map(|(created_at, snippet)| view! {
+                                    <li class="pinned-item">
+                                        <button class="pinned-jump"
+                                                type="button"
+                                                title="Show in the conversation"
+                                                on:click=move |_| jump_to_reply(created_at)>
+                                            {snippet}
+                                        </button>
+                                        <button class="tool-panel-close"
+                                                type="button"
+                                                title="Unpin"
+                                                aria-label="Unpin reply"
+                                                on:click=move |_| toggle_pin(created_at)>
+                                            "✕"
+                                        </button>
+                                    </li>
+                                }).collect_view()}
+                            </ul>
+                        }.into_any()
+                    }
+                }}
+            </div>
+
+            // Conversation summary
+            {move || summary.get().map(|text| {
+                let empty = text.trim().is_empty();
+                view! {
+                    <div class="tool-panel summary-panel">
+                        <div class="tool-panel-header">
+                            <span>{move || if summarizing.get() { "Summarizing…" } else { "Summary" }}</span>
+                            <button class="tool-panel-close" title="Close" on:click=move |_| set_summary.set(None)>"✕"</button>
+                        </div>
+                        <div class="summary-text markdown-content" inner_html=markdown_to_html(&text, "")></div>
+                        <div class="benchmark-form">
+                            <button class="toolbar-btn"
+                                    type="button"
+                                    disabled=empty
+                                    on:click={
+                                        let text = text.clone();
+                                        move |_| {
+                                            copy_to_clipboard(&text);
+                                            push_toast("Summary copied".to_string(), false);
+                                        }
+                                    }>
+                                "⧉ Copy"
+                            </button>
+                            <button class="toolbar-btn"
+                                    type="button"
+                                    title="Replace the conversation with this summary and keep chatting"
+                                    disabled=move || empty || summarizing.get() || is_streaming.get()
+                                    on:click=move |_| continue_from_summary()>
+                                "Continue from summary"
+                            </button>
+                        </div>
+                    </div>
+                }
+            })}
+
+            // Conversation search
+            {move || search_open.get().then(|| view! {
+                <div class="chat-search">
+                    <input type="search"
+                           class="settings-input chat-search-input"
+                           placeholder="Search messages"
+                           autofocus=true
+                           prop:value=move || search_query.get()
+                           on:input=move |ev| {
+                               set_search_query.set(event_target_value(&ev));
+                               set_search_index.set(0);
+                           }
+                           on:keydown=move |ev: web_sys::KeyboardEvent| {
+                               ev.stop_propagation();
+                               if ev.key() == "Enter" {
+                                   ev.prevent_default();
+                                   jump_to_hit(if ev.shift_key() { -1 } else { 1 });
+                               } else if ev.key() == "Escape" {
+                                   set_search_open.set(false);
+                                   set_search_query.set(String::new());
+                               }
+                           } />
+                    <span class="chat-search-count">
+                        {move || match (search_query.get().is_empty(), search_hits()) {
+                            (true, _) => String::new(),
+                            (false, 0) => "No matches".to_string(),
+                            (false, count) => format!("{} of {}", search_index.get().min(count - 1) + 1, count),
+                        }}
+                    </span>
+                    <button class="toolbar-btn" type="button" title="Previous match (Shift+Enter)"
+                            disabled=move || search_hits() == 0
+                            on:click=move |_| jump_to_hit(-1)>"▲"</button>
+                    <button class="toolbar-btn" type="button" title="Next match (Enter)"
+                            disabled=move || search_hits() == 0
+                            on:click=move |_| jump_to_hit(1)>"▼"</button>
+                </div>
+            })}
+
             // Chat window
-            <div id="chat-window" class="chat-window">
+            <div id="chat-window"
+                 class="chat-window"
+                 on:scroll=move |ev| {
+                     let chat_window = event_target::<web_sys::Element>(&ev);
+                     let distance = chat_window.scroll_height() - chat_window.scroll_top() - chat_window.client_height();
+                     let near_bottom = distance < 80;
+                     if near_bottom != at_bottom.get_untracked() {
+                         set_at_bottom.set(near_bottom);
+                     }
+                 }>
+                // First-run guidance: Ollama is up but has no models yet
+                <Suspense fallback=|| ()>
+                    {move || {
+                        let no_models = status_resource
+                            .get()
+                            .and_then(|r| r.ok())
+                            .is_some_and(|status| status.running && status.models.is_empty());
+                        let pulling_starter = active_downloads.get().iter().any(|d| d.model == STARTER_MODEL && !d.done);
+                        (no_models && messages.get().is_empty()).then(|| view! {
+                            <div class="onboarding">
+                                <div class="onboarding-title">"No models installed yet"</div>
+                                <p class="onboarding-text">
+                                    "Pull a model to start chatting. "
+                                    {STARTER_MODEL}
+                                    " is small and quick to download; you can add others later from the models menu."
+                                </p>
+                                <button class="add-model-btn pull-btn onboarding-btn"
+                                        disabled=pulling_starter
+                                        on:click=move |_| request_download(STARTER_MODEL.to_string())>
+                                    {if pulling_starter { format!("Pulling {}…", STARTER_MODEL) } else { format!("Pull {}", STARTER_MODEL) }}
+                                </button>
+                                {move || pull_warning.get().map(|warning| view! {
+                                    <div class="pull-status pull-warning">{format!("⚠️ {}", warning)}</div>
+                                })}
+                                <a class="onboarding-link"
+                                   href="https://ollama.com/library"
+                                   target="_blank"
+                                   rel="noopener noreferrer">
+                                    "Browse the model library"
+                                </a>
+                            </div>
+                        })
+                    }}
+                </Suspense>
                 <For
                     each=move || messages.get()
-                    key=|msg| format!("{}-{}", msg.role, msg.text.len())
+                    key=|msg| format!("{}-{}-{}-{}-{}-{}", msg.role, msg.text.len(), msg.thinking.len(), msg.tool_calls.len(), msg.created_at, msg.versions.len())
                     children=move |msg| {
                         let is_user = msg.role == "user";
-                        let is_empty_ai = msg.role == "ai" && msg.text.is_empty();
+                        let is_empty_ai = msg.role == "ai" && msg.text.is_empty() && msg.thinking.is_empty() && msg.tool_calls.is_empty();
+                        let created_at = msg.created_at;
+                        let is_pinned = move || messages.with(|msgs| {
+                            msgs.iter().any(|m| m.role == "ai" && m.created_at == created_at && m.pinned)
+                        });
+                        let tool_calls = msg.tool_calls.clone();
+                        let tool_name = msg.tool_name.clone().unwrap_or_else(|| "tool".to_string());
+                        // Tool calls still waiting on results: this is the newest reply and it's done
+                        let awaiting_results = move || {
+                            !is_streaming.get()
+                                && messages.with(|msgs| {
+                                    msgs.last().is_some_and(|last| last.role == "ai" && last.created_at == created_at)
+                                })
+                        };
+                        // Start of the text that just streamed into this reply, to fade in
+                        let fresh_from = (msg.role == "ai" && is_streaming.get_untracked())
+                            .then(|| fresh_from.get_value())
+                            .flatten()
+                            .filter(|_| messages.with_untracked(|msgs| msgs.last().is_some_and(|last| last.created_at == created_at)));
                         let msg_text = msg.text.clone();
+                        let msg_thinking = msg.thinking.clone();
+                        let msg_model = msg.model.clone();
+                        let versions = msg.versions.clone();
+                        let is_latest_reply = move || {
+                            !is_streaming.get()
+                                && messages.with(|msgs| {
+                                    msgs.last().is_some_and(|last| last.role == "ai" && last.created_at == created_at)
+                                })
+                        };
+
+                        let search_text = msg.text.clone();
+                        let search_hit = move || {
+                            let query = search_query.get();
+                            !query.is_empty() && !find_matches(&search_text, &query).is_empty()
+                        };
 
                         view! {
                             <div class="chat-bubble"
+                                 id=(msg.role == "ai").then(|| format!("reply-{}", created_at))
+                                 class:pinned=move || is_pinned()
                                  class:user-bubble=is_user
-                                 class:ai-bubble=!is_user>
+                                 class:ai-bubble=!is_user
+                                 class:tool-bubble=msg.role == "tool"
+                                 class:search-hit=search_hit.clone()
+                                 class:search-miss=move || !search_query.get().is_empty() && !search_hit()>
                                 {if is_empty_ai {
                                     // Thinking animation
                                     view! {
                                         <span class="thinking">
-                                            <span class="msg-prefix">
-                                                <Suspense fallback=move || view! { "[...]" }>
-                                                    {move || hostname_resource.get().map(|h| {
-                                                        format!("[{}]", h.unwrap_or_else(|_| "ollama".to_string()))
-                                                    })}
-                                                </Suspense>
-                                            </span>
+                                            {move || show_host_prefix.get().then(|| view! {
+                                                <span class="msg-prefix">
+                                                    <Suspense fallback=move || view! { "[...]" }>
+                                                        {move || hostname_resource.get().map(|h| {
+                                                            format!("[{}]", h.unwrap_or_else(|_| "ollama".to_string()))
+                                                        })}
+                                                    </Suspense>
+                                                </span>
+                                            })}
                                             <span class="thinking-dots">
                                                 <span class="thinking-dot"></span>
                                                 <span class="thinking-dot"></span>
@@ -2109,55 +7391,826 @@ pub fn App() -> impl IntoView {
                                             </span>
                                         </span>
                                     }.into_any()
+                                } else if msg.role == "system" {
+                                    // Summary that replaced earlier messages
+                                    view! {
+                                        <div class="summary-note">
+                                            <div class="summary-note-label">"Summary of the earlier conversation"</div>
+                                            <div class="markdown-content" inner_html=move || markdown_to_html(&msg_text, &search_query.get())></div>
+                                        </div>
+                                    }.into_any()
+                                } else if msg.role == "tool" {
+                                    // Result handed back for a tool call
+                                    view! {
+                                        <div class="tool-result">
+                                            <div class="tool-result-label">{format!("Result of {}", tool_name)}</div>
+                                            <pre class="tool-result-content">{highlighted_text(&msg_text, &search_query.get())}</pre>
+                                        </div>
+                                    }.into_any()
                                 } else if is_user {
-                                    // User message - plain text
-                                    view! { <span>{msg_text}</span> }.into_any()
+                                    // User message - plain text, with any images it was sent with
+                                    let images = msg.images.clone();
+                                    view! {
+                                        {(!images.is_empty()).then(|| view! {
+                                            <div class="message-images">
+                                                {images.into_iter().map(|image| view! {
+                                                    <img class="message-image" src=image alt="Sent image" />
+                                                }).collect_view()}
+                                            </div>
+                                        })}
+                                        <span>{move || highlighted_text(&msg_text, &search_query.get())}</span>
+                                    }.into_any()
                                 } else {
-                                    // AI message with hostname prefix and markdown rendering
-                                    let rendered_html = markdown_to_html(&msg_text);
+                                    // AI message with hostname prefix and markdown (or raw) rendering
                                     view! {
                                         <div class="ai-message-content">
-                                            <span class="msg-prefix">
-                                                <Suspense fallback=move || view! { "[...]:" }>
-                                                    {move || hostname_resource.get().map(|h| {
-                                                        format!("[{}]: ", h.unwrap_or_else(|_| "ollama".to_string()))
-                                                    })}
-                                                </Suspense>
-                                            </span>
-                                            <div class="markdown-content" inner_html=rendered_html></div>
+                                            {move || show_host_prefix.get().then(|| view! {
+                                                <span class="msg-prefix">
+                                                    <Suspense fallback=move || view! { "[...]:" }>
+                                                        {move || hostname_resource.get().map(|h| {
+                                                            format!("[{}]: ", h.unwrap_or_else(|_| "ollama".to_string()))
+                                                        })}
+                                                    </Suspense>
+                                                </span>
+                                            })}
+                                            // Reasoning from thinking models, collapsed by default
+                                            {(!msg_thinking.is_empty()).then(|| view! {
+                                                <details class="thought">
+                                                    <summary>"Thought process"</summary>
+                                                    <div class="thought-content">{msg_thinking.clone()}</div>
+                                                </details>
+                                            })}
+                                            {move || if let Some(pretty) = json_mode.get()
+                                                .then(|| serde_json::from_str::<serde_json::Value>(&msg_text).ok())
+                                                .flatten()
+                                                .and_then(|value| serde_json::to_string_pretty(&value).ok())
+                                            {
+                                                view! { <pre class="json-content">{pretty}</pre> }.into_any()
+                                            } else if render_markdown.get() {
+                                                view! {
+                                                    <div class="markdown-content" inner_html=markdown_to_html_fresh(&msg_text, &search_query.get(), fresh_from)></div>
+                                                }.into_any()
+                                            } else {
+                                                view! { <div class="raw-content">{highlighted_text(&msg_text, &search_query.get())}</div> }.into_any()
+                                            }}
+                                            {(!tool_calls.is_empty()).then(|| {
+                                                let call_count = tool_calls.len();
+                                                view! {
+                                                    <div class="tool-calls">
+                                                        {tool_calls.iter().enumerate().map(|(index, call)| {
+                                                            let name = call["function"]["name"].as_str().unwrap_or("?").to_string();
+                                                            let arguments = serde_json::to_string_pretty(&call["function"]["arguments"]).unwrap_or_default();
+                                                            let placeholder = format!("Result of {}", name);
+                                                            view! {
+                                                                <div class="tool-call">
+                                                                    <div class="tool-call-name">{format!("🔧 {}", name)}</div>
+                                                                    <pre class="json-content tool-call-args">{arguments}</pre>
+                                                                    {move || awaiting_results().then(|| view! {
+                                                                        <textarea class="settings-textarea tool-result-input"
+                                                                                  rows="2"
+                                                                                  placeholder=placeholder.clone()
+                                                                                  prop:value=move || tool_results.get().get(index).cloned().unwrap_or_default()
+                                                                                  on:input=move |ev| {
+                                                                                      let value = event_target_value(&ev);
+                                                                                      set_tool_results.update(|results| {
+                                                                                          if results.len() < call_count {
+                                                                                              results.resize(call_count, String::new());
+                                                                                          }
+                                                                                          results[index] = value;
+                                                                                      });
+                                                                                  }
+                                                                                  on:keydown=move |ev: web_sys::KeyboardEvent| ev.stop_propagation()>
+                                                                        </textarea>
+                                                                    })}
+                                                                </div>
+                                                            }
+                                                        }).collect_view()}
+                                                        {move || awaiting_results().then(|| view! {
+                                                            <button class="toolbar-btn tool-results-send"
+                                                                    type="button"
+                                                                    on:click=move |_| send_tool_results()>
+                                                                {if call_count == 1 { "Send result" } else { "Send results" }}
+                                                            </button>
+                                                        })}
+                                                    </div>
+                                                }
+                                            })}
                                         </div>
                                     }.into_any()
                                 }}
+                                // Ollama silently dropped the start of the conversation for this reply
+                                {msg.context_window.map(|window| view! {
+                                    <div class="context-note" title="The model only saw the most recent messages that fit; it may have forgotten earlier ones">
+                                        {format!("Earlier messages were dropped to fit the {}-token context window", window)}
+                                        <button class="context-note-action"
+                                                type="button"
+                                                title="Summarize the conversation, then continue from the summary"
+                                                on:click=move |_| summarize()>
+                                            "Summarize"
+                                        </button>
+                                    </div>
+                                })}
+                                // Earlier answers from regenerating, each comparable with the shown one
+                                {(!versions.is_empty()).then(|| {
+                                    let shown = msg.text.clone();
+                                    let count = versions.len();
+                                    let buttons = versions.iter().enumerate().map(|(index, version)| {
+                                        let title = format!(
+                                            "Compare with the answer{}",
+                                            version.model.as_ref().map(|m| format!(" from {}", m)).unwrap_or_default()
+                                        );
+                                        view! {
+                                            <button class="reply-version-btn"
+                                                    type="button"
+                                                    title=title
+                                                    class:active=move || compare_version.get() == Some((created_at, index))
+                                                    on:click=move |_| set_compare_version.update(|current| {
+                                                        *current = (*current != Some((created_at, index))).then_some((created_at, index));
+                                                    })>
+                                                {format!("v{}", index + 1)}
+                                            </button>
+                                        }
+                                    }).collect_view();
+                                    view! {
+                                        <div class="reply-versions">
+                                            <span class="reply-versions-label">"Versions"</span>
+                                            {buttons}
+                                            <span class="reply-version-btn current" title="The answer shown above">{format!("v{}", count + 1)}</span>
+                                            <button class="reply-version-forget"
+                                                    type="button"
+                                                    title="Forget earlier versions"
+                                                    on:click=move |_| forget_versions(created_at)>
+                                                "✕"
+                                            </button>
+                                        </div>
+                                        {move || compare_version.get()
+                                            .filter(|(at, _)| *at == created_at)
+                                            .and_then(|(_, index)| versions.get(index).cloned().map(|version| (index, version)))
+                                            .map(|(index, version)| view! {
+                                                <div class="reply-diff">
+                                                    <div class="reply-diff-header">
+                                                        <span>
+                                                            {format!("v{} → v{}", index + 1, count + 1)}
+                                                            <span class="reply-diff-legend">" (struck out: only in v"{index + 1}"; highlighted: only in v"{count + 1}")"</span>
+                                                        </span>
+                                                        <button class="context-note-action"
+                                                                type="button"
+                                                                title="Show this version as the reply instead"
+                                                                on:click=move |_| keep_version(created_at, index)>
+                                                            {format!("Keep v{}", index + 1)}
+                                                        </button>
+                                                    </div>
+                                                    <div class="reply-diff-body">
+                                                        {word_diff(&version.text, &shown).into_iter().map(|part| match part {
+                                                            DiffPart::Same(text) => text.into_any(),
+                                                            DiffPart::Removed(text) => view! { <del class="diff-removed">{text}</del> }.into_any(),
+                                                            DiffPart::Added(text) => view! { <ins class="diff-added">{text}</ins> }.into_any(),
+                                                        }).collect_view()}
+                                                    </div>
+                                                </div>
+                                            })}
+                                    }
+                                })}
+                                {(msg.role == "ai" && !is_empty_ai).then(|| view! {
+                                    <button class="regenerate-btn"
+                                            type="button"
+                                            class:hidden=move || !is_latest_reply()
+                                            title="Regenerate this reply; the current answer is kept as a version to compare"
+                                            on:click=move |_| regenerate()>
+                                        "↻"
+                                    </button>
+                                })}
+                                {(msg.role == "ai" && !is_empty_ai).then(|| view! {
+                                    <button class="pin-btn"
+                                            type="button"
+                                            class:active=move || is_pinned()
+                                            title=move || if is_pinned() { "Unpin" } else { "Pin this reply" }
+                                            aria-pressed=move || is_pinned().to_string()
+                                            on:click=move |_| toggle_pin(created_at)>
+                                        "📌"
+                                    </button>
+                                })}
+                                {msg_model.filter(|_| !is_empty_ai).map(|model| view! {
+                                    <div class="msg-model" title=model.clone()>{truncate_model_name(&model)}</div>
+                                })}
+                                {move || (show_timestamps.get() && created_at > 0 && !is_empty_ai).then(|| view! {
+                                    <div class="msg-time" title=format_clock_time(created_at)>
+                                        {format_relative_time(created_at, clock.get())}
+                                    </div>
+                                })}
                             </div>
                         }
                     }
                 />
+                // Way back to the newest message after scrolling up
+                <button class="scroll-bottom-btn"
+                        type="button"
+                        title="Jump to the latest message"
+                        aria-label="Jump to the latest message"
+                        class:hidden=move || at_bottom.get() || messages.get().is_empty()
+                        on:click=move |_| {
+                            set_at_bottom.set(true);
+                            scroll_to_bottom();
+                        }>
+                    {move || if is_streaming.get() { "↓ New reply" } else { "↓" }}
+                </button>
             </div>
 
+            // Debug view of the last request body
+            {move || show_raw_request.get().then(|| last_request.get()).flatten().map(|raw| {
+                let raw_copy = raw.clone();
+                view! {
+                    <details class="raw-request">
+                        <summary>
+                            "Last request"
+                            <button class="toolbar-btn raw-request-copy"
+                                    type="button"
+                                    title="Copy request JSON"
+                                    on:click=move |ev: web_sys::MouseEvent| {
+                                        ev.prevent_default();
+                                        copy_to_clipboard(&raw_copy);
+                                    }>
+                                "📋"
+                            </button>
+                            {move || last_request_id.get().map(|id| view! {
+                                <span class="raw-request-id" title="Find this request in the server log">{id}</span>
+                            })}
+                        </summary>
+                        <pre class="json-content raw-request-body">{raw}</pre>
+                    </details>
+                }
+            })}
+
+            // Debug view of what Ollama streamed back for it
+            {move || show_raw_request.get().then(|| last_response.get()).flatten().map(|response| match response {
+                Ok(raw) => {
+                    let pretty = pretty_raw_chunks(&raw);
+                    let pretty_copy = pretty.clone();
+                    view! {
+                        <details class="raw-request">
+                            <summary>
+                                {format!("Last response ({} chunks)", raw.chunks.len())}
+                                <button class="toolbar-btn raw-request-copy"
+                                        type="button"
+                                        title="Copy response JSON"
+                                        on:click=move |ev: web_sys::MouseEvent| {
+                                            ev.prevent_default();
+                                            copy_to_clipboard(&pretty_copy);
+                                        }>
+                                    "📋"
+                                </button>
+                            </summary>
+                            <pre class="json-content raw-request-body">{pretty}</pre>
+                        </details>
+                    }.into_any()
+                }
+                Err(err) => view! {
+                    <details class="raw-request">
+                        <summary>"Last response"</summary>
+                        <div class="benchmark-error">{err}</div>
+                    </details>
+                }.into_any(),
+            })}
+
+            // Active request options
+            {view! {
+                <div class="input-hints">
+                    {move || attached_file.get().map(|file| view! {
+                        <span class="input-hint attached-file"
+                              title=if file.truncated { "Truncated to the attached file limit" } else { "Sent ahead of your next message" }>
+                            {format!("📎 {}{}", file.name, if file.truncated { " (truncated)" } else { "" })}
+                            <button class="attached-file-remove"
+                                    type="button"
+                                    title="Remove"
+                                    aria-label="Remove attached file"
+                                    on:click=move |_| set_attached_file.set(None)>
+                                "✕"
+                            </button>
+                        </span>
+                    })}
+                    {view! {
+                        {move || pasted_images.get().into_iter().enumerate().map(|(index, image)| view! {
+                            <span class="input-hint attached-file pasted-image" title="Sent with your next message">
+                                <img class="pasted-image-preview" src=image alt="Pasted image" />
+                                <button class="attached-file-remove"
+                                        type="button"
+                                        title="Remove"
+                                        aria-label="Remove pasted image"
+                                        on:click=move |_| set_pasted_images.update(|images| {
+                                            if index < images.len() {
+                                                images.remove(index);
+                                            }
+                                        })>
+                                    "✕"
+                                </button>
+                            </span>
+                        }).collect_view()}
+                    }.into_any()}
+                    {move || stream_status.get().map(|status| view! {
+                        <span class="input-hint" role="status">{status}</span>
+                    })}
+                    {move || {
+                        let others = queue_depth.get().saturating_sub(1);
+                        (is_streaming.get() && others > 0).then(|| view! {
+                            <span class="input-hint" title="Ollama handles one reply at a time per model unless OLLAMA_NUM_PARALLEL is raised, so this one may wait its turn">
+                                {format!("⏳ {} other chat{} running", others, if others == 1 { "" } else { "s" })}
+                            </span>
+                        })
+                    }}
+                    {move || raw_mode.get().then(|| view! {
+                        <span class="input-hint" title="No chat template, no conversation history">"Raw"</span>
+                    })}
+                    {move || (!raw_mode.get() && endpoint.get() == "generate").then(|| view! {
+                        <span class="input-hint" title="Only the latest message is sent, to /api/generate">"Generate"</span>
+                    }.into_any())}
+                    {move || json_mode.get().then(|| {
+                        let label = if json_schema.get().trim().is_empty() { "{ } JSON" } else { "{ } JSON schema" };
+                        view! { <span class="input-hint">{label}</span> }
+                    })}
+                    {move || json_format().err().map(|err| view! { <span class="input-hint input-hint-error">{err}</span> })}
+                    {move || match tools_value() {
+                        Ok(tools) if !tools.is_empty() => Some(view! {
+                            <span class="input-hint" title="Function definitions offered to the model">{format!("🔧 {} tool{}", tools.len(), if tools.len() == 1 { "" } else { "s" })}</span>
+                        }.into_any()),
+                        Ok(_) => None,
+                        Err(err) => Some(view! { <span class="input-hint input-hint-error">{err}</span> }.into_any()),
+                    }}
+                    {move || {
+                        let count = stop_value().len();
+                        (count > 0).then(|| view! {
+                            <span class="input-hint" title="Generation stops at any of these">
+                                {format!("⏹ {} stop sequence{}", count, if count == 1 { "" } else { "s" })}
+                            </span>
+                        })
+                    }}
+                    {move || gpu_options_value().err().map(|err| view! { <span class="input-hint input-hint-error">{err}</span> })}
+                    {move || match num_predict_value() {
+                        Ok(Some(-1)) => Some(view! { <span class="input-hint">"No length limit"</span> }.into_any()),
+                        Ok(Some(0)) => Some(view! { <span class="input-hint">"Load only (0 tokens)"</span> }.into_any()),
+                        Ok(Some(n)) => Some(view! { <span class="input-hint">{format!("≤ {} tokens", n)}</span> }.into_any()),
+                        Ok(None) => None,
+                        Err(err) => Some(view! { <span class="input-hint input-hint-error">{err}</span> }.into_any()),
+                    }}
+                </div>
+            }.into_any()}
+
             // Input area
             <div class="chat-input-area">
+                // Switch models without going through the status menu
+                <select class="settings-select inline-model-select"
+                        aria-label="Model"
+                        title=move || selected_model.get().unwrap_or_default()
+                        class:hidden=move || status_resource.get().and_then(|r| r.ok()).is_none_or(|status| status.models.is_empty())
+                        prop:value=move || selected_model.get().unwrap_or_default()
+                        disabled=move || is_streaming.get()
+                        on:change=move |ev| {
+                            select_model(event_target_value(&ev));
+                            // A locked chat may have kept its model; put the select back to match
+                            set_selected_model.update(|_| {});
+                        }>
+                    <Suspense fallback=|| ()>
+                        {move || status_resource.get()
+                            .and_then(|r| r.ok())
+                            .map(|status| status.models)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|m| view! { <option value=m.clone()>{m.clone()}</option> })
+                            .collect_view()}
+                    </Suspense>
+                </select>
                 <textarea
                     id="prompt-input"
-                    placeholder="Type your message..."
+                    placeholder=move || if send_key.get() == "ctrl_enter" {
+                        "Type your message... (Ctrl+Enter to send)"
+                    } else {
+                        "Type your message..."
+                    }
                     rows="1"
                     autofocus=true
+                    // Label the touch keyboard's return key to match what it does
+                    enterkeyhint=move || if send_key.get() == "ctrl_enter" { "enter" } else { "send" }
                     prop:value=move || input.get()
-                    on:input=move |ev| set_input.set(event_target_value(&ev))
+                    on:input=move |ev| {
+                        set_input.set(event_target_value(&ev));
+                        set_history_index.set(None);
+                    }
                     on:keydown=move |ev: web_sys::KeyboardEvent| {
-                        if ev.key() == "Enter" && !ev.shift_key() && !ev.alt_key() {
+                        let sends = if send_key.get() == "ctrl_enter" {
+                            ev.ctrl_key() || ev.meta_key()
+                        } else {
+                            !ev.shift_key() && !ev.alt_key()
+                        };
+                        // Ignore the Enter that confirms an IME composition
+                        if ev.key() == "Enter" && sends && !ev.is_composing() {
                             ev.prevent_default();
                             do_send();
+                        } else if ev.key() == "ArrowUp"
+                            && (input.get().is_empty() || history_index.get().is_some())
+                            && caret_on_edge_line(&ev, true)
+                        {
+                            // Recall the previous prompt, shell-style
+                            let history = prompt_history.get();
+                            if history.is_empty() {
+                                return;
+                            }
+                            let index = match history_index.get() {
+                                Some(i) => i.saturating_sub(1),
+                                None => history.len() - 1,
+                            };
+                            ev.prevent_default();
+                            set_history_index.set(Some(index));
+                            set_input.set(history[index].clone());
+                        } else if ev.key() == "ArrowDown"
+                            && history_index.get().is_some()
+                            && caret_on_edge_line(&ev, false)
+                        {
+                            // Move forward through history, clearing past the newest entry
+                            ev.prevent_default();
+                            let history = prompt_history.get();
+                            match history_index.get() {
+                                Some(i) if i + 1 < history.len() => {
+                                    set_history_index.set(Some(i + 1));
+                                    set_input.set(history[i + 1].clone());
+                                }
+                                _ => {
+                                    set_history_index.set(None);
+                                    set_input.set(String::new());
+                                }
+                            }
                         }
                     }
+                    on:paste=paste_image
                     disabled=move || is_streaming.get()
                 ></textarea>
                 <button id="send-button"
                         type="button"
+                        class:hidden=move || is_streaming.get()
                         on:click=move |_: web_sys::MouseEvent| do_send()
                         disabled=move || is_streaming.get()>
                     "➤"
                 </button>
+                {view! {
+                    <button id="stop-button"
+                            type="button"
+                            title="Stop generating"
+                            aria-label="Stop generating"
+                            class:hidden=move || !is_streaming.get()
+                            on:click=move |_: web_sys::MouseEvent| stop_reply()>
+                        "■"
+                    </button>
+                }.into_any()}
             </div>
         </div>
+
+        // Notifications for background events
+        <div class="toast-container" aria-live="polite">
+            <For
+                each=move || toasts.get()
+                key=|toast| toast.id
+                children=move |toast| {
+                    let id = toast.id;
+                    view! {
+                        <div class="toast"
+                             class:toast-error=toast.is_error
+                             role=if toast.is_error { "alert" } else { "status" }>
+                            <span class="toast-message">{toast.message}</span>
+                            {toast.undo_delete.map(|model| view! {
+                                <button class="toast-undo"
+                                        aria-label=format!("Undo deleting {}", model)
+                                        on:click=move |_| undo_delete(model.clone())>
+                                    "Undo"
+                                </button>
+                            }.into_any())}
+                            <button class="toast-close"
+                                    aria-label="Dismiss notification"
+                                    on:click=move |_| dismiss_toast(id)>
+                                "✕"
+                            </button>
+                        </div>
+                    }
+                }
+            />
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_multibyte_model_names_truncate_on_char_boundaries() {
+        let cjk = "通义千问模型大型语言模型测试版本号";
+        assert!(cjk.chars().count() > 15);
+        assert_eq!(truncate_model_name(cjk), format!("{}...", cjk.chars().take(12).collect::<String>()));
+
+        let emoji = "🦙🦙🦙🦙🦙🦙🦙🦙🦙🦙🦙🦙🦙🦙🦙🦙:latest";
+        let truncated = truncate_model_name(emoji);
+        assert_eq!(truncated, "🦙🦙🦙🦙🦙🦙🦙🦙🦙🦙🦙🦙...");
+        assert_eq!(truncated.chars().count(), 15);
+    }
+
+    #[test]
+    fn multibyte_character_split_across_chunks_is_decoded_once() {
+        let bytes = "héllo 🦙!".as_bytes();
+        // "é" is 2 bytes from offset 1, the llama 4 bytes from offset 7
+        let mut pending = Vec::new();
+        assert_eq!(decode_utf8_chunk(&mut pending, &bytes[..2]), "h");
+        assert_eq!(pending, &bytes[1..2]);
+        assert_eq!(decode_utf8_chunk(&mut pending, &bytes[2..9]), "éllo ");
+        assert_eq!(decode_utf8_chunk(&mut pending, &bytes[9..10]), "");
+        assert_eq!(decode_utf8_chunk(&mut pending, &bytes[10..]), "🦙!");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_not_held_back() {
+        let mut pending = Vec::new();
+        assert_eq!(decode_utf8_chunk(&mut pending, b"ok \xff then"), "ok \u{fffd} then");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn code_indentation_survives_markdown_rendering() {
+        let text = "Here is the loop:\n\n```python\nfor i in range(3):\n\tif i:\n        print(i)\n```\n\nAnd an indented block:\n\n    fn main() {\n        println!(\"hi\");\n    }\n";
+        let html = markdown_to_html(text, "");
+        assert!(html.contains("<p>Here is the loop:</p>"));
+        assert!(html.contains("for i in range(3):\n\tif i:\n        print(i)\n</code></pre>"));
+        assert!(html.contains("<pre><code>fn main() {\n    println!(\"hi\");\n}\n</code></pre>"));
+    }
+
+    #[test]
+    fn code_indentation_survives_highlighting_and_streaming() {
+        let text = "```\n\tindented\n    spaced\n```\n";
+        let html = markdown_to_html(text, "spaced");
+        assert!(html.contains("\tindented\n    <mark>spaced</mark>\n"));
+        let html = markdown_to_html_fresh(text, "", Some(text.find("spaced").unwrap()));
+        assert!(html.contains("\tindented\n    <span class=\"token-fresh\">spaced\n</span>"));
+    }
+
+    #[test]
+    fn short_model_names_are_kept() {
+        assert_eq!(truncate_model_name("llama3:latest"), "llama3:latest");
+        assert_eq!(truncate_model_name("模型:latest"), "模型:latest");
+        assert_eq!(truncate_model_name("fifteen-chars-x"), "fifteen-chars-x");
+    }
+
+    #[test]
+    fn a_view_error_renders_the_fallback() {
+        let owner = Owner::new();
+        let html = owner.with(|| {
+            view! {
+                <ErrorBoundary fallback=|errors| view! { <AppError errors/> }>
+                    {Err::<String, _>(std::io::Error::other("model list exploded"))}
+                </ErrorBoundary>
+            }
+            .to_html()
+        });
+        assert!(html.contains("Something went wrong"), "{}", html);
+        assert!(html.contains("role=\"alert\""), "{}", html);
+        assert!(html.contains("model list exploded"), "{}", html);
+    }
+
+    #[test]
+    fn crash_screen_shows_the_escaped_panic() {
+        let html = crash_screen_html("panicked at <src/app.rs>: index out of bounds");
+        assert!(html.contains("Something went wrong"));
+        assert!(html.contains("location.reload()"));
+        assert!(html.contains("<pre>panicked at &lt;src/app.rs&gt;: index out of bounds</pre>"), "{}", html);
+    }
+
+    #[test]
+    fn the_selected_model_is_kept_while_installed() {
+        let installed = vec!["llama3:latest".to_string(), "mistral:7b".to_string()];
+        assert_eq!(reconciled_model(Some("mistral:7b"), &installed).as_deref(), Some("mistral:7b"));
+        assert_eq!(reconciled_model(Some("llama3"), &installed).as_deref(), Some("llama3"));
+        assert_eq!(reconciled_model(Some("cloud:gpt"), &installed).as_deref(), Some("cloud:gpt"));
+    }
+
+    #[test]
+    fn a_removed_model_falls_back_to_the_first_installed() {
+        let installed = vec!["llama3:latest".to_string(), "mistral:7b".to_string()];
+        assert_eq!(reconciled_model(Some("mistral"), &installed).as_deref(), Some("llama3:latest"));
+        assert_eq!(reconciled_model(Some("phi3:mini"), &installed).as_deref(), Some("llama3:latest"));
+        assert_eq!(reconciled_model(None, &installed).as_deref(), Some("llama3:latest"));
+        assert_eq!(reconciled_model(Some("llama3"), &[]), None);
+    }
+
+    fn download(status: &str, done: bool) -> PullProgress {
+        PullProgress {
+            model: "llama3".to_string(),
+            status: status.to_string(),
+            percent: 0.0,
+            done,
+            error: None,
+            bytes_downloaded: 0,
+            total_bytes: 0,
+            speed: String::new(),
+            last_update: 0,
+            raw_status: String::new(),
+        }
+    }
+
+    #[test]
+    fn downloads_keep_being_checked_while_polling_is_paused() {
+        let downloads = [download("Downloading", false)];
+        assert_eq!(download_poll_delay_ms(&downloads, false), Some(2000));
+        let paused = download_poll_delay_ms(&downloads, true).unwrap();
+        assert!(paused > 2000);
+        // Well before the server gives up on a pull nobody asks about
+        assert!(paused * 2 < 180_000);
+    }
+
+    #[test]
+    fn a_hidden_tab_s_late_checks_still_add_up_to_a_rate() {
+        let downloads = [download("Downloading", false)];
+        let paused = download_poll_delay_ms(&downloads, true).unwrap();
+        // Throttled timers can fire a minute apart
+        assert!(pull_rate_max_gap_ms(paused) > 60_000.0);
+        assert!(pull_rate_max_gap_ms(download_poll_delay_ms(&downloads, false).unwrap()) < 60_000.0);
+    }
+
+    #[test]
+    fn time_left_counts_the_whole_download() {
+        let mb = 1024 * 1024;
+        // Only the first of the layers has been announced, so the pull's own total is 10 MB
+        assert_eq!(pull_speed_text(Some(mb as f64), 5 * mb, Some(125 * mb)), "1.0 MB/s · 2m 0s left");
+        assert_eq!(pull_speed_text(Some(mb as f64), 5 * mb, None), "1.0 MB/s");
+        assert_eq!(pull_speed_text(None, 5 * mb, Some(125 * mb)), "estimating…");
+    }
+
+    #[test]
+    fn nothing_to_check_without_a_running_download() {
+        assert_eq!(download_poll_delay_ms(&[], false), None);
+        let downloads = [download("Paused", false), download("Success", true)];
+        assert_eq!(download_poll_delay_ms(&downloads, false), None);
+        assert_eq!(download_poll_delay_ms(&downloads, true), None);
+    }
+
+    fn chat(conversation_id: &str, texts: &[&str]) -> ModelChat {
+        ModelChat {
+            conversation_id: Some(conversation_id.to_string()),
+            messages: texts
+                .iter()
+                .map(|text| serde_json::from_value(serde_json::json!({ "role": "user", "text": text })).unwrap())
+                .collect(),
+            locked: false,
+            set_aside_at: 0,
+        }
+    }
+
+    fn texts(chat: &ModelChat) -> Vec<&str> {
+        chat.messages.iter().map(|m| m.text.as_str()).collect()
+    }
+
+    #[test]
+    fn switching_models_swaps_their_chats() {
+        let mut chats = HashMap::new();
+        let on_mistral = swap_model_chat(&mut chats, "llama3", "mistral", chat("a", &["hi llama"]));
+        assert!(on_mistral.messages.is_empty() && on_mistral.conversation_id.is_none());
+
+        let mut mistral = on_mistral;
+        mistral.messages = chat("b", &["hi mistral"]).messages;
+        mistral.locked = true;
+        let back = swap_model_chat(&mut chats, "mistral", "llama3", mistral);
+        assert_eq!(back.conversation_id.as_deref(), Some("a"));
+        assert_eq!(texts(&back), ["hi llama"]);
+        assert!(!chats.contains_key("llama3"));
+
+        let again = swap_model_chat(&mut chats, "llama3", "mistral", back);
+        assert_eq!(texts(&again), ["hi mistral"]);
+        assert!(again.locked);
+        assert_eq!(texts(&chats["llama3"]), ["hi llama"]);
+    }
+
+    #[test]
+    fn empty_chats_are_not_kept() {
+        let mut chats = HashMap::from([("llama3".to_string(), chat("a", &["old"]))]);
+        let next = swap_model_chat(&mut chats, "llama3", "mistral", chat("a", &[]));
+        assert!(next.messages.is_empty());
+        assert!(chats.is_empty());
+    }
+
+    #[test]
+    fn the_chats_set_aside_longest_ago_go_over_the_cap() {
+        let aside = |at| ModelChat { set_aside_at: at, ..chat("a", &["hi"]) };
+        let mut chats = HashMap::from([
+            ("llama3".to_string(), aside(300)),
+            ("mistral".to_string(), aside(100)),
+            ("phi3".to_string(), aside(200)),
+        ]);
+        assert_eq!(trim_model_chats(&mut chats, 0), 0);
+        assert_eq!(chats.len(), 3);
+
+        // The current chat takes one of the three
+        assert_eq!(trim_model_chats(&mut chats, 3), 1);
+        let mut kept: Vec<&str> = chats.keys().map(String::as_str).collect();
+        kept.sort();
+        assert_eq!(kept, ["llama3", "phi3"]);
+
+        assert_eq!(trim_model_chats(&mut chats, 1), 2);
+        assert!(chats.is_empty());
+    }
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn a_model_on_the_gpu_is_not_counted_twice() {
+        // An 8 GB card holding a 7 GB model, with 4 GB of RAM free
+        let ps = serde_json::json!({ "models": [{ "name": "llama3:latest", "size": 7 * GB, "size_vram": 7 * GB }] });
+        let memory = MemoryInfo { total_ram: 16 * GB, available_ram: 4 * GB, vram: Some(8 * GB), loaded_ram_bytes: loaded_ram_bytes(&ps) };
+        assert_eq!(memory.budget(), 12 * GB);
+        assert!(!memory.may_not_fit(9 * GB));
+        assert!(memory.may_not_fit(11 * GB));
+    }
+
+    #[test]
+    fn the_ram_share_of_a_split_model_is_given_back() {
+        let ps = serde_json::json!({ "models": [
+            { "name": "big:70b", "size": 40 * GB, "size_vram": 8 * GB },
+            { "name": "old:1b", "size": GB },
+        ] });
+        assert_eq!(loaded_ram_bytes(&ps), 33 * GB);
+        assert_eq!(loaded_ram_bytes(&serde_json::json!({})), 0);
+    }
+
+    #[test]
+    fn models_need_room_for_their_cache_too() {
+        let memory = MemoryInfo { total_ram: 16 * GB, available_ram: 10 * GB, vram: None, loaded_ram_bytes: 0 };
+        // 9 GB on disk needs about 10.8 GB
+        assert!(memory.may_not_fit(9 * GB));
+        assert!(!memory.may_not_fit(8 * GB));
+    }
+
+    #[test]
+    fn a_rate_limited_stream_says_so_and_when_to_retry() {
+        assert_eq!(
+            stream_error_message(429, "Too many requests, try again later", Some("12")),
+            "Too many requests, try again later (retry in 12s)"
+        );
+        assert_eq!(stream_error_message(502, "", None), "Request failed with status 502");
+    }
+
+    #[test]
+    fn an_expired_sign_in_does_not_show_the_login_page() {
+        let message = stream_error_message(401, "<!DOCTYPE html><form>...</form>", None);
+        assert!(!message.contains('<'), "{}", message);
+        assert!(message.contains("sign in"), "{}", message);
+    }
+
+    fn curl_body(command: &str) -> serde_json::Value {
+        let json = command.split_once("-d '").unwrap().1.trim_end_matches('\'');
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn curl_for_a_chat_posts_the_messages_to_api_chat() {
+        let request = r#"{"model":"llama3","messages":[{"role":"user","content":"hi"}],"options":{"num_predict":8}}"#;
+        let command = curl_command("http://localhost:11434/", request, &HashMap::new()).unwrap();
+        assert!(command.starts_with("curl --no-buffer 'http://localhost:11434/api/chat'"), "{}", command);
+        let body = curl_body(&command);
+        assert_eq!(body["messages"], serde_json::json!([{ "role": "user", "content": "hi" }]));
+        assert_eq!(body["options"]["num_predict"], 8);
+        assert!(body.get("prompt").is_none());
+    }
+
+    #[test]
+    fn curl_for_the_generate_endpoint_sends_prompt_system_and_images() {
+        let request = r#"{"model":"llava","endpoint":"generate","messages":[
+            {"role":"system","content":"Be brief"},
+            {"role":"user","content":"first"},
+            {"role":"assistant","content":"ok"},
+            {"role":"user","content":"what is this?","images":["aGk="]}
+        ]}"#;
+        let command = curl_command("http://localhost:11434", request, &HashMap::new()).unwrap();
+        assert!(command.starts_with("curl --no-buffer 'http://localhost:11434/api/generate'"), "{}", command);
+        let body = curl_body(&command);
+        assert_eq!(body["prompt"], "what is this?");
+        assert_eq!(body["system"], "Be brief");
+        assert_eq!(body["images"], serde_json::json!(["aGk="]));
+        assert!(body.get("messages").is_none() && body.get("raw").is_none());
+    }
+
+    #[test]
+    fn curl_for_the_generate_endpoint_leaves_out_what_is_missing() {
+        let request = r#"{"model":"llama3","endpoint":"generate","messages":[{"role":"user","content":"hi"}]}"#;
+        let body = curl_body(&curl_command("http://localhost:11434", request, &HashMap::new()).unwrap());
+        assert_eq!(body, serde_json::json!({ "model": "llama3", "stream": true, "prompt": "hi" }));
+    }
+
+    #[test]
+    fn curl_for_a_tool_call_sends_the_tools_and_thinking() {
+        let request = r#"{"model":"qwen3","messages":[{"role":"user","content":"weather in Paris?"}],"tools":[
+            {"type":"function","function":{"name":"get_weather","parameters":{"type":"object","properties":{"city":{"type":"string"}}}}}
+        ]}"#;
+        let capabilities = HashMap::from([("qwen3".to_string(), vec!["completion".to_string(), "tools".to_string(), "thinking".to_string()])]);
+        let body = curl_body(&curl_command("http://localhost:11434", request, &capabilities).unwrap());
+        assert_eq!(body["tools"][0]["function"]["name"], "get_weather");
+        assert_eq!(body["tools"][0]["function"]["parameters"]["properties"]["city"]["type"], "string");
+        assert_eq!(body["think"], true);
+
+        // Without known capabilities there's nothing to say the server would ask for thinking
+        let body = curl_body(&curl_command("http://localhost:11434", request, &HashMap::new()).unwrap());
+        assert!(body["tools"].is_array() && body.get("think").is_none());
     }
 }
@@ -1,4 +1,6 @@
 pub mod app;
+pub mod models;
+pub mod routes;
 
 use crate::app::*;
 use leptos::prelude::*;
@@ -8,7 +10,11 @@ use wasm_bindgen::prelude::wasm_bindgen;
 pub fn hydrate() {
     #[cfg(feature = "hydrate")]
     {
-        console_error_panic_hook::set_once();
+        // Log the panic as usual, then replace the page with a reload prompt
+        std::panic::set_hook(Box::new(|info| {
+            console_error_panic_hook::hook(info);
+            show_crash_screen(&info.to_string());
+        }));
         _ = console_log::init_with_level(log::Level::Debug);
         leptos::mount::hydrate_body(App);
     }
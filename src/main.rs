@@ -2,7 +2,8 @@
 #[tokio::main]
 async fn main() {
     use ollama_rust::app::*;
-    use axum::routing::post;
+    use ollama_rust::routes;
+    use axum::routing::{get, post};
     use axum::Router;
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
@@ -13,35 +14,996 @@ async fn main() {
     let leptos_options = conf.leptos_options;
     let routes = generate_route_list(App);
 
-    let app = Router::new()
-        .route("/api/stream", post(stream_handler))
-        .nest_service("/pkg", ServeDir::new(format!("{}/pkg", &leptos_options.site_root)).append_index_html_on_directories(false))
+    if autostart_ollama_enabled() {
+        autostart_ollama().await;
+    }
+
+    let mut app = Router::new()
+        .route(routes::STREAM, post(stream_handler))
+        .route(routes::STREAM_CANCEL, post(cancel_stream_handler))
+        .route(routes::CHAT, post(chat_handler))
+        .route(routes::MODELS, get(models_handler))
+        .route(routes::HEALTH, get(|| std::future::ready("ok")))
+        .route(routes::LOGIN, post(login_handler));
+
+    // Prometheus metrics are opt-in
+    if metrics_enabled() {
+        match install_metrics_recorder() {
+            Ok(handle) => {
+                app = app.route(routes::METRICS, get(move || std::future::ready(handle.render())));
+            }
+            Err(e) => leptos::logging::error!("failed to set up metrics: {}", e),
+        }
+    }
+
+    let app = app
+        .nest_service(routes::PKG, ServeDir::new(format!("{}/pkg", &leptos_options.site_root)).append_index_html_on_directories(false))
         .leptos_routes(&leptos_options, routes, {
             let leptos_options = leptos_options.clone();
             move || shell(leptos_options.clone())
         })
-        .with_state(leptos_options);
+        .with_state(leptos_options)
+        .layer(axum::Extension(OllamaBaseUrl(ollama_rust::models::ollama_base_url())))
+        .layer(axum::middleware::from_fn(rate_limit))
+        .layer(axum::middleware::from_fn_with_state(auth_token(), require_auth));
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     leptos::logging::log!("listening on http://{}", &addr);
-    axum::serve(listener, app).await.unwrap();
+    // Client addresses are needed for per-IP rate limiting
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .await
+        .unwrap();
+}
+
+/// Ollama the chat and model routes talk to, handed to their handlers as a request
+/// extension: the configured `OLLAMA_HOST` when serving, a stand-in in tests
+#[cfg(feature = "ssr")]
+#[derive(Clone)]
+struct OllamaBaseUrl(String);
+
+/// Token every request must carry when `UI_AUTH_TOKEN` is set
+#[cfg(feature = "ssr")]
+fn auth_token() -> Option<String> {
+    std::env::var("UI_AUTH_TOKEN").ok().filter(|t| !t.is_empty())
+}
+
+/// Cookie set by the login form, holding the same token
+#[cfg(feature = "ssr")]
+const AUTH_COOKIE: &str = "ui_auth";
+
+/// Sign-in form shown instead of the app, posting to `routes::LOGIN`
+#[cfg(feature = "ssr")]
+fn login_page() -> axum::response::Html<String> {
+    axum::response::Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head><meta charset="utf-8"><meta name="viewport" content="width=device-width, initial-scale=1"><title>Ollama Rust - Sign in</title></head>
+<body style="font-family: system-ui, sans-serif; display: flex; justify-content: center; padding-top: 20vh;">
+<form method="post" action="{}" style="display: flex; flex-direction: column; gap: 0.5rem; width: 260px;">
+<label for="token">Access token</label>
+<input id="token" name="token" type="password" autofocus required>
+<button type="submit">Sign in</button>
+</form>
+</body>
+</html>"#,
+        ollama_rust::routes::LOGIN
+    ))
+}
+
+/// Compare without bailing out at the first differing byte
+#[cfg(feature = "ssr")]
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Token from `Authorization: Bearer ...` or the login cookie
+#[cfg(feature = "ssr")]
+fn request_token(headers: &axum::http::HeaderMap) -> Option<String> {
+    use axum::http::header::{AUTHORIZATION, COOKIE};
+
+    if let Some(bearer) = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        return Some(bearer.trim().to_string());
+    }
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == AUTH_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Reject requests without `token` (401 with a login form). Does nothing when
+/// it's `None`, i.e. `UI_AUTH_TOKEN` is unset.
+#[cfg(feature = "ssr")]
+async fn require_auth(
+    axum::extract::State(token): axum::extract::State<Option<String>>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    use ollama_rust::routes;
+
+    let Some(token) = token else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    if path == routes::HEALTH || path == routes::LOGIN {
+        return next.run(request).await;
+    }
+    if request_token(request.headers()).is_some_and(|given| tokens_match(&given, &token)) {
+        return next.run(request).await;
+    }
+
+    (
+        axum::http::StatusCode::UNAUTHORIZED,
+        [(axum::http::header::WWW_AUTHENTICATE, "Bearer")],
+        login_page(),
+    )
+        .into_response()
+}
+
+#[cfg(feature = "ssr")]
+#[derive(serde::Deserialize)]
+struct LoginForm {
+    token: String,
+}
+
+/// Check the submitted token and set the auth cookie
+#[cfg(feature = "ssr")]
+async fn login_handler(axum::Form(form): axum::Form<LoginForm>) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    match auth_token() {
+        Some(token) if tokens_match(&form.token, &token) => (
+            [(
+                axum::http::header::SET_COOKIE,
+                format!("{}={}; Path=/; HttpOnly; SameSite=Strict", AUTH_COOKIE, token),
+            )],
+            axum::response::Redirect::to("/"),
+        )
+            .into_response(),
+        Some(_) => (axum::http::StatusCode::UNAUTHORIZED, login_page()).into_response(),
+        None => axum::response::Redirect::to("/").into_response(),
+    }
+}
+
+/// Requests per minute allowed for one client, from `var` (`0` disables the limit)
+#[cfg(feature = "ssr")]
+fn rate_limit_per_minute(var: &str, default: usize) -> usize {
+    std::env::var(var)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Recent request times per (endpoint, client IP)
+#[cfg(feature = "ssr")]
+type RateLimitMap = std::collections::HashMap<(&'static str, std::net::IpAddr), std::collections::VecDeque<std::time::Instant>>;
+
+#[cfg(feature = "ssr")]
+static RATE_LIMITS: std::sync::OnceLock<std::sync::Mutex<RateLimitMap>> = std::sync::OnceLock::new();
+
+/// Record a request and return how long the client must wait if it's over `limit` per minute
+#[cfg(feature = "ssr")]
+fn check_rate_limit(endpoint: &'static str, ip: std::net::IpAddr, limit: usize) -> Option<std::time::Duration> {
+    let window = std::time::Duration::from_secs(60);
+    let now = std::time::Instant::now();
+    let mut map = RATE_LIMITS.get_or_init(Default::default).lock().unwrap();
+
+    // Forget clients that have been quiet for a full window
+    map.retain(|_, times| times.back().is_some_and(|t| now.duration_since(*t) < window));
+
+    let times = map.entry((endpoint, ip)).or_default();
+    while times.front().is_some_and(|t| now.duration_since(*t) >= window) {
+        times.pop_front();
+    }
+    if times.len() >= limit {
+        return times.front().map(|oldest| window.saturating_sub(now.duration_since(*oldest)));
+    }
+    times.push_back(now);
+    None
+}
+
+/// Proxies whose `X-Forwarded-For` / `Forwarded` headers are believed, from
+/// `UI_TRUSTED_PROXIES` (comma-separated IPs). Empty unless set.
+#[cfg(feature = "ssr")]
+fn trusted_proxies() -> Vec<std::net::IpAddr> {
+    std::env::var("UI_TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect()
+}
+
+/// An address from a forwarding header: a bare IP, or with a port or IPv6 brackets
+#[cfg(feature = "ssr")]
+fn forwarded_ip(value: &str) -> Option<std::net::IpAddr> {
+    let value = value.trim().trim_matches('"');
+    value
+        .parse()
+        .ok()
+        .or_else(|| value.parse::<std::net::SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| value.strip_prefix('[')?.split(']').next()?.parse().ok())
+}
+
+/// The client behind `peer`. Only when `peer` is a trusted proxy are the
+/// forwarding headers read, right to left, skipping the proxies' own hops;
+/// otherwise anyone could pick their own address by sending the header.
+#[cfg(feature = "ssr")]
+fn client_ip(peer: std::net::IpAddr, headers: &axum::http::HeaderMap, trusted: &[std::net::IpAddr]) -> std::net::IpAddr {
+    if !trusted.contains(&peer) {
+        return peer;
+    }
+    let values = |name: &str| -> Vec<String> {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::to_string)
+            .collect()
+    };
+    let mut hops: Vec<std::net::IpAddr> = values("x-forwarded-for").iter().filter_map(|v| forwarded_ip(v)).collect();
+    if hops.is_empty() {
+        hops = values("forwarded")
+            .iter()
+            .flat_map(|element| element.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .filter(|(name, _)| name.eq_ignore_ascii_case("for"))
+            .filter_map(|(_, value)| forwarded_ip(value))
+            .collect();
+    }
+    hops.iter()
+        .rev()
+        .find(|ip| !trusted.contains(ip))
+        .or(hops.first())
+        .copied()
+        .unwrap_or(peer)
+}
+
+/// Per-IP limits on chat (`UI_RATE_LIMIT_CHAT`, default 30/min) and on starting
+/// pulls (`UI_RATE_LIMIT_PULL`, default 10/min) and sign-in attempts
+/// (`UI_RATE_LIMIT_LOGIN`, default 10/min). Everything else is unlimited.
+/// Behind a proxy listed in `UI_TRUSTED_PROXIES` the forwarded client address counts.
+#[cfg(feature = "ssr")]
+async fn rate_limit(request: axum::extract::Request, next: axum::middleware::Next) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    use leptos::server_fn::ServerFn;
+    use ollama_rust::routes;
+
+    let path = request.uri().path();
+    let (endpoint, limit) = if path == routes::STREAM || path == routes::CHAT {
+        ("chat", rate_limit_per_minute("UI_RATE_LIMIT_CHAT", 30))
+    } else if path == <ollama_rust::models::StartModelPull as ServerFn>::PATH {
+        ("pull", rate_limit_per_minute("UI_RATE_LIMIT_PULL", 10))
+    } else if path == routes::LOGIN {
+        // Sign-in attempts skip auth, so this is all that slows down guessing the token
+        ("login", rate_limit_per_minute("UI_RATE_LIMIT_LOGIN", 10))
+    } else {
+        return next.run(request).await;
+    };
+    let ip = request
+        .extensions()
+        .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
+        .map(|info| client_ip(info.0.ip(), request.headers(), &trusted_proxies()));
+    let (Some(ip), true) = (ip, limit > 0) else {
+        return next.run(request).await;
+    };
+
+    match check_rate_limit(endpoint, ip, limit) {
+        Some(retry_after) => (
+            axum::http::StatusCode::TOO_MANY_REQUESTS,
+            [(axum::http::header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+            "Too many requests, try again later",
+        )
+            .into_response(),
+        None => next.run(request).await,
+    }
+}
+
+/// Ollama is started at boot when `AUTOSTART_OLLAMA` is set to `1` or `true`
+#[cfg(feature = "ssr")]
+fn autostart_ollama_enabled() -> bool {
+    std::env::var("AUTOSTART_OLLAMA")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Start Ollama before serving if it isn't already answering, and wait until it does.
+/// A failure is logged and the UI comes up anyway, with Ollama shown as stopped.
+/// An Ollama that was already running is left alone, here and when the server exits.
+#[cfg(feature = "ssr")]
+async fn autostart_ollama() {
+    if ollama_rust::models::get_ollama_status().await.is_ok_and(|status| status.running) {
+        leptos::logging::log!("AUTOSTART_OLLAMA: Ollama is already running at {}", ollama_rust::models::ollama_base_url());
+        return;
+    }
+    leptos::logging::log!("AUTOSTART_OLLAMA: starting Ollama...");
+    match ollama_rust::models::start_ollama_and_wait().await {
+        Ok(()) => leptos::logging::log!("AUTOSTART_OLLAMA: Ollama is up at {}", ollama_rust::models::ollama_base_url()),
+        Err(reason) => leptos::logging::error!("AUTOSTART_OLLAMA: {}", reason),
+    }
+}
+
+/// `/metrics` is served when `UI_METRICS` is set to `1` or `true`
+#[cfg(feature = "ssr")]
+fn metrics_enabled() -> bool {
+    std::env::var("UI_METRICS")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+#[cfg(feature = "ssr")]
+fn install_metrics_recorder() -> Result<metrics_exporter_prometheus::PrometheusHandle, metrics_exporter_prometheus::BuildError> {
+    use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full("ollama_ui_chat_request_duration_seconds".to_string()),
+            &[0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0],
+        )?
+        .install_recorder()
+}
+
+/// Tracks one chat request in the metrics and the queue depth shown in the UI:
+/// counts it as active while alive and records its duration when dropped,
+/// however the request ends. Started before Ollama is called, so time spent
+/// waiting behind other generations is included.
+#[cfg(feature = "ssr")]
+struct StreamMetrics {
+    started: std::time::Instant,
+    request_id: String,
+    _in_flight: ollama_rust::app::ChatInFlight,
+}
+
+#[cfg(feature = "ssr")]
+impl StreamMetrics {
+    fn start(request_id: &str) -> Self {
+        metrics::gauge!("ollama_ui_active_streams").increment(1.0);
+        Self {
+            started: std::time::Instant::now(),
+            request_id: request_id.to_string(),
+            _in_flight: ollama_rust::app::ChatInFlight::start(),
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl Drop for StreamMetrics {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        metrics::gauge!("ollama_ui_active_streams").decrement(1.0);
+        metrics::histogram!("ollama_ui_chat_request_duration_seconds").record(elapsed);
+        leptos::logging::log!("[{}] chat finished after {:.1}s", self.request_id, elapsed);
+    }
+}
+
+/// Cancellation tokens of the `/api/stream` replies still running, by request id
+#[cfg(feature = "ssr")]
+static STREAM_CANCELS: std::sync::OnceLock<std::sync::Mutex<std::collections::HashMap<String, tokio_util::sync::CancellationToken>>> =
+    std::sync::OnceLock::new();
+
+#[cfg(feature = "ssr")]
+fn stream_cancels() -> &'static std::sync::Mutex<std::collections::HashMap<String, tokio_util::sync::CancellationToken>> {
+    STREAM_CANCELS.get_or_init(Default::default)
+}
+
+/// A reply's entry in `STREAM_CANCELS`, removed when the reply's stream is dropped
+#[cfg(feature = "ssr")]
+struct StreamCancel {
+    request_id: String,
+    token: tokio_util::sync::CancellationToken,
+}
+
+#[cfg(feature = "ssr")]
+impl StreamCancel {
+    fn register(request_id: &str) -> Self {
+        let token = tokio_util::sync::CancellationToken::new();
+        stream_cancels().lock().unwrap().insert(request_id.to_string(), token.clone());
+        Self { request_id: request_id.to_string(), token }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl Drop for StreamCancel {
+    fn drop(&mut self) {
+        stream_cancels().lock().unwrap().remove(&self.request_id);
+    }
+}
+
+/// Pass `events` through until the request is cancelled. The Ollama request
+/// behind them is dropped at that point, which stops the generation; only
+/// this reply is affected, whatever else is streaming.
+#[cfg(feature = "ssr")]
+fn cancellable(events: EventStream, request_id: &str, protocol: StreamProtocol) -> EventStream {
+    use futures::StreamExt;
+
+    let cancel = StreamCancel::register(request_id);
+    let stream = async_stream::stream! {
+        let cancel = cancel;
+        let mut events = Box::pin(events.take_until(cancel.token.clone().cancelled_owned()));
+        while let Some(event) = events.next().await {
+            yield event;
+        }
+        if cancel.token.is_cancelled() {
+            drop(events);
+            leptos::logging::log!("[{}] chat cancelled", cancel.request_id);
+            yield Ok(protocol.done(&cancel.request_id, None));
+        }
+    };
+    Box::pin(stream)
+}
+
+/// Run `events` on its own task, at most `capacity` events ahead of the client.
+/// When a slow client falls that far behind, reading from Ollama pauses (and
+/// the generation with it) until it catches up, so the server never holds more
+/// than that of a reply. The task stops, dropping the Ollama request, as soon
+/// as the client goes away.
+#[cfg(feature = "ssr")]
+fn bounded(events: EventStream, capacity: usize) -> EventStream {
+    use futures::StreamExt;
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(capacity);
+    tokio::spawn(async move {
+        let mut events = events;
+        loop {
+            let event = tokio::select! {
+                event = events.next() => event,
+                _ = tx.closed() => break,
+            };
+            let Some(event) = event else { break };
+            // Waits while the buffer is full; fails once the client has disconnected
+            if tx.send(event).await.is_err() {
+                break;
+            }
+        }
+    });
+    Box::pin(futures::stream::poll_fn(move |cx| rx.poll_recv(cx)))
+}
+
+#[cfg(feature = "ssr")]
+#[derive(serde::Deserialize)]
+struct CancelStreamRequest {
+    request_id: String,
+}
+
+/// Cancel one `/api/stream` reply by its request id
+#[cfg(feature = "ssr")]
+async fn cancel_stream_handler(axum::Json(request): axum::Json<CancelStreamRequest>) -> axum::http::StatusCode {
+    let token = stream_cancels().lock().unwrap().get(&request.request_id).cloned();
+    match token {
+        Some(token) => {
+            token.cancel();
+            axum::http::StatusCode::NO_CONTENT
+        }
+        None => axum::http::StatusCode::NOT_FOUND,
+    }
+}
+
+/// Id for one chat request, logged by the server and handed back to the client
+/// (`x-request-id` header, and the `id` of the final SSE event) so the two can be matched up
+#[cfg(feature = "ssr")]
+fn new_request_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+#[cfg(feature = "ssr")]
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Response header naming the `/api/stream` event format used, so clients can
+/// tell whether the server knows the version they asked for
+#[cfg(feature = "ssr")]
+const STREAM_PROTOCOL_HEADER: &str = "x-stream-protocol";
+
+/// Ollama's timing and token counts, passed on when a reply is done
+#[cfg(feature = "ssr")]
+const REPLY_STATS: [&str; 7] = [
+    "done_reason",
+    "total_duration",
+    "load_duration",
+    "prompt_eval_count",
+    "prompt_eval_duration",
+    "eval_count",
+    "eval_duration",
+];
+
+/// Event format of `/api/stream`, picked with the request's `"protocol"`.
+/// Version 1 (the default) sends reply text as unnamed events, errors as
+/// `[Error: ...]` text and ends with `__END__`; alongside those it has the named
+/// `thinking`, `tool_calls`, `context` and `status` events, which clients that
+/// only read unnamed events skip. Version 2 names every event: `start`, `token`,
+/// `thinking`, `tool_calls`, `context`, `status`, `error` and `done`.
+#[cfg(feature = "ssr")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum StreamProtocol {
+    V1,
+    V2,
+}
+
+#[cfg(feature = "ssr")]
+impl StreamProtocol {
+    /// Unknown future versions get the newest one this server speaks
+    fn requested(version: Option<u32>) -> Self {
+        match version {
+            None | Some(0 | 1) => Self::V1,
+            Some(_) => Self::V2,
+        }
+    }
+
+    fn version(self) -> u32 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    /// `{"model", "request_id", "protocol"}` before anything else; version 2 only
+    fn start(self, model: &str, request_id: &str) -> Option<axum::response::sse::Event> {
+        (self == Self::V2).then(|| {
+            let data = serde_json::json!({ "model": model, "request_id": request_id, "protocol": self.version() });
+            axum::response::sse::Event::default().event("start").data(data.to_string())
+        })
+    }
+
+    /// A piece of the reply
+    fn token(self, text: &str) -> axum::response::sse::Event {
+        match self {
+            Self::V1 => axum::response::sse::Event::default().data(text),
+            Self::V2 => axum::response::sse::Event::default().event("token").data(text),
+        }
+    }
+
+    /// Reasoning from a thinking model; named in both versions
+    fn thinking(self, text: &str) -> axum::response::sse::Event {
+        match self {
+            Self::V1 | Self::V2 => axum::response::sse::Event::default().event("thinking").data(text),
+        }
+    }
+
+    /// Calls the model makes, as one line of JSON so it fits in a single `data:`
+    /// field; named in both versions
+    fn tool_calls(self, calls: &[serde_json::Value]) -> axum::response::sse::Event {
+        let data = serde_json::Value::Array(calls.to_vec()).to_string();
+        match self {
+            Self::V1 | Self::V2 => axum::response::sse::Event::default().event("tool_calls").data(data),
+        }
+    }
+
+    /// `{"estimated", "window"}` when the prompt overflowed the loaded context;
+    /// named in both versions
+    fn context(self, truncation: &serde_json::Value) -> axum::response::sse::Event {
+        match self {
+            Self::V1 | Self::V2 => axum::response::sse::Event::default().event("context").data(truncation.to_string()),
+        }
+    }
+
+    /// Progress before the reply starts, like waiting for Ollama; named in both versions
+    fn status(self, message: &str) -> axum::response::sse::Event {
+        match self {
+            Self::V1 | Self::V2 => axum::response::sse::Event::default().event("status").data(message),
+        }
+    }
+
+    /// Why the reply failed; nothing follows it
+    fn error(self, message: &str) -> axum::response::sse::Event {
+        match self {
+            Self::V1 => axum::response::sse::Event::default().data(format!("[Error: {}]", message)),
+            Self::V2 => axum::response::sse::Event::default().event("error").data(message),
+        }
+    }
+
+    /// End of the reply. Version 2 carries the `REPLY_STATS` Ollama reported, as JSON.
+    fn done(self, request_id: &str, reply: Option<&serde_json::Value>) -> axum::response::sse::Event {
+        match self {
+            Self::V1 => axum::response::sse::Event::default().id(request_id).data("__END__"),
+            Self::V2 => {
+                let mut stats = serde_json::Map::new();
+                for key in REPLY_STATS {
+                    if let Some(value) = reply.and_then(|reply| reply.get(key)) {
+                        stats.insert(key.to_string(), value.clone());
+                    }
+                }
+                axum::response::sse::Event::default()
+                    .event("done")
+                    .id(request_id)
+                    .data(serde_json::Value::Object(stats).to_string())
+            }
+        }
+    }
+}
+
+/// Which Ollama endpoint answers a chat request
+#[cfg(feature = "ssr")]
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Endpoint {
+    /// `/api/chat`: the whole conversation, through the model's chat template
+    #[default]
+    Chat,
+    /// `/api/generate`: only the latest user turn as a single prompt, plus the latest system message
+    Generate,
 }
 
 #[cfg(feature = "ssr")]
 #[derive(serde::Deserialize)]
 pub struct PromptRequest {
     pub model: String,
+    /// Single-turn prompt, used when `messages` is empty
+    #[serde(default)]
     pub prompt: String,
+    /// Conversation so far, forwarded to Ollama's `/api/chat`
+    #[serde(default)]
+    pub messages: Vec<ollama_rust::app::ApiMessage>,
+    /// How long Ollama keeps the model loaded afterwards (e.g. "5m", "-1", "0").
+    /// Left out of the upstream request when unset so Ollama's default applies.
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Structured output: `"json"` or a JSON schema object
+    #[serde(default)]
+    pub format: Option<serde_json::Value>,
+    /// Model options forwarded to Ollama as-is (e.g. `num_predict`)
+    #[serde(default)]
+    pub options: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Start Ollama and retry once if it isn't running (it has side effects, so it's opt-in)
+    #[serde(default)]
+    pub auto_start: bool,
+    /// Send the prompt verbatim to `/api/generate`, bypassing the model's template.
+    /// Only the latest user turn is used; earlier messages and system prompts are ignored.
+    #[serde(default)]
+    pub raw: bool,
+    /// `"chat"` (the default) or `"generate"`; `raw` always uses `/api/generate`
+    #[serde(default)]
+    pub endpoint: Endpoint,
+    /// Functions the model may call, in Ollama's `tools` format
+    #[serde(default)]
+    pub tools: Vec<serde_json::Value>,
+    /// `/api/stream` event format to answer with; see `StreamProtocol`
+    #[serde(default)]
+    pub protocol: Option<u32>,
+}
+
+#[cfg(feature = "ssr")]
+impl PromptRequest {
+    /// Rough size of what goes upstream, in tokens
+    fn estimated_prompt_tokens(&self) -> u64 {
+        if self.raw {
+            estimate_tokens(&self.raw_prompt())
+        } else if self.endpoint == Endpoint::Generate {
+            estimate_tokens(&self.raw_prompt()) + self.system_prompt().map_or(0, |system| estimate_tokens(&system))
+        } else {
+            self.chat_messages().iter().map(|m| estimate_tokens(&m.content)).sum()
+        }
+    }
+
+    /// Messages to send upstream, falling back to `prompt` as a single user turn
+    fn chat_messages(&self) -> Vec<ollama_rust::app::ApiMessage> {
+        if self.messages.is_empty() {
+            vec![ollama_rust::app::ApiMessage {
+                role: "user".to_string(),
+                content: self.prompt.clone(),
+                ..Default::default()
+            }]
+        } else {
+            self.messages.clone()
+        }
+    }
+
+    /// Latest system message, sent as `system` to `/api/generate`
+    fn system_prompt(&self) -> Option<String> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.role == "system")
+            .map(|m| m.content.clone())
+            .filter(|content| !content.is_empty())
+    }
+
+    /// Images of the latest user message, for `/api/generate`
+    fn latest_images(&self) -> Vec<String> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.role == "user")
+            .map(|m| m.images.clone())
+            .unwrap_or_default()
+    }
+
+    /// Prompt for raw mode and `/api/generate`: `prompt` if set, otherwise the latest user message
+    fn raw_prompt(&self) -> String {
+        if !self.prompt.is_empty() {
+            return self.prompt.clone();
+        }
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.role == "user")
+            .map(|m| m.content.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "ssr")]
+type EventStream = std::pin::Pin<Box<dyn futures::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>> + Send>>;
+
+/// Event stream carrying a single error
+#[cfg(feature = "ssr")]
+fn error_events(protocol: StreamProtocol, message: String) -> EventStream {
+    Box::pin(futures::stream::once(async move { Ok(protocol.error(&message)) }))
+}
+
+/// Plain JSON list of installed models for non-Leptos clients
+#[cfg(feature = "ssr")]
+async fn models_handler(
+    axum::Extension(OllamaBaseUrl(base_url)): axum::Extension<OllamaBaseUrl>,
+) -> Result<axum::Json<Vec<ollama_rust::models::ModelInfo>>, axum::http::StatusCode> {
+    ollama_rust::models::fetch_installed_models(&base_url)
+        .await
+        .map(axum::Json)
+        .ok_or(axum::http::StatusCode::SERVICE_UNAVAILABLE)
 }
 
 #[cfg(feature = "ssr")]
 async fn stream_handler(
     axum::extract::State(_state): axum::extract::State<leptos::prelude::LeptosOptions>,
+    axum::Extension(OllamaBaseUrl(base_url)): axum::Extension<OllamaBaseUrl>,
     axum::Json(payload): axum::Json<PromptRequest>,
-) -> axum::response::sse::Sse<std::pin::Pin<Box<dyn futures::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>> + Send>>> {
+) -> impl axum::response::IntoResponse {
+    metrics::counter!("ollama_ui_chat_requests_total").increment(1);
+    let request_id = new_request_id();
+    leptos::logging::log!("[{}] chat request for {}", request_id, payload.model);
+    let protocol = StreamProtocol::requested(payload.protocol);
+    let headers = [(REQUEST_ID_HEADER, request_id.clone()), (STREAM_PROTOCOL_HEADER, protocol.version().to_string())];
+    let events = bounded(stream_events(base_url, payload, request_id, protocol), sse_buffer_events());
+    (headers, sse_response(events, sse_keep_alive_interval()))
+}
+
+/// Events of one `/api/stream` reply from the Ollama at `base_url`, starting with
+/// `start`. Nothing is asked of Ollama until the stream is read, so the response goes out right away; Ollama
+/// sends nothing until the model has loaded, and keep-alives cover that wait.
+#[cfg(feature = "ssr")]
+fn stream_events(base_url: String, payload: PromptRequest, request_id: String, protocol: StreamProtocol) -> EventStream {
     use futures::StreamExt;
-    use tokio_util::codec::{FramedRead, LinesCodec};
-    use tokio_util::io::StreamReader;
+
+    let start = protocol.start(&payload.model, &request_id);
+    let reply = {
+        let request_id = request_id.clone();
+        futures::stream::once(async move { chat_stream(&base_url, payload, &request_id, protocol).await }).flatten()
+    };
+    let events = cancellable(Box::pin(reply), &request_id, protocol);
+    match start {
+        Some(start) => Box::pin(futures::stream::once(std::future::ready(Ok(start))).chain(events)),
+        None => events,
+    }
+}
+
+/// SSE response for `events`, with a comment line every `keep_alive` while nothing
+/// else is sent so proxies don't close a connection waiting on the model
+#[cfg(feature = "ssr")]
+fn sse_response(events: EventStream, keep_alive: Option<std::time::Duration>) -> axum::response::sse::Sse<EventStream> {
+    let sse = axum::response::sse::Sse::new(events);
+    match keep_alive {
+        Some(interval) => sse.keep_alive(axum::response::sse::KeepAlive::new().interval(interval).text("keep-alive")),
+        None => sse,
+    }
+}
+
+/// Refuse a model that isn't installed before asking Ollama, whose own error for
+/// it is unclear. Passes when Ollama can't be reached, so auto-start and the CLI
+/// fallback still get their chance.
+#[cfg(feature = "ssr")]
+async fn check_model_installed(base_url: &str, model: &str) -> Result<(), String> {
+    match ollama_rust::models::fetch_installed_models(base_url).await {
+        Some(installed) if !installed.iter().any(|m| ollama_rust::models::same_model(model, &m.name)) => {
+            Err(format!("Model \"{}\" is not installed", model))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Validate a chat request and build the Ollama endpoint and body for it.
+/// Shared by the streaming and the plain JSON chat routes.
+#[cfg(feature = "ssr")]
+async fn upstream_request(
+    base_url: &str,
+    payload: &PromptRequest,
+    stream: bool,
+) -> Result<(&'static str, serde_json::Value), String> {
+    let messages = payload.chat_messages();
+
+    let keep_alive = match payload.keep_alive.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+        Some(value) => match parse_keep_alive(value) {
+            Some(parsed) => Some(parsed),
+            None => return Err(format!("Invalid keep_alive \"{}\" (use e.g. 5m, 1h, -1 or 0)", value)),
+        },
+        None => None,
+    };
+
+    match &payload.format {
+        None | Some(serde_json::Value::Object(_)) => {}
+        Some(serde_json::Value::String(format)) if format == "json" => {}
+        Some(_) => return Err("format must be \"json\" or a JSON schema object".to_string()),
+    }
+
+    if let Some(num_predict) = payload.options.as_ref().and_then(|o| o.get("num_predict")) {
+        if num_predict.as_i64().is_none_or(|n| n < -1) {
+            return Err("num_predict must be a whole number, -1 for unlimited".to_string());
+        }
+    }
+    for key in ["num_gpu", "main_gpu"] {
+        if let Some(value) = payload.options.as_ref().and_then(|o| o.get(key)) {
+            if value.as_u64().is_none_or(|n| n > u32::MAX as u64) {
+                return Err(format!("{} must be a whole number, 0 or more", key));
+            }
+        }
+    }
+    if payload.options.as_ref().and_then(|o| o.get("low_vram")).is_some_and(|v| !v.is_boolean()) {
+        return Err("low_vram must be true or false".to_string());
+    }
+    if let Some(stop) = payload.options.as_ref().and_then(|o| o.get("stop")) {
+        let valid = stop
+            .as_array()
+            .is_some_and(|list| list.iter().all(|s| s.as_str().is_some_and(|s| !s.is_empty())));
+        if !valid {
+            return Err("stop must be a list of non-empty strings".to_string());
+        }
+    }
+
+    if !payload.tools.is_empty() {
+        if payload.raw {
+            return Err("tools can't be used in raw mode".to_string());
+        }
+        if payload.endpoint == Endpoint::Generate {
+            return Err("tools need the chat endpoint".to_string());
+        }
+        validate_tools(&payload.tools)?;
+    }
+
+    let details = ollama_rust::models::fetch_model_details(base_url, &payload.model).await;
+    if !payload.tools.is_empty()
+        && details.as_ref().is_some_and(|d| !d.capabilities.is_empty() && !d.capabilities.iter().any(|c| c == "tools"))
+    {
+        return Err(format!("{} doesn't support tools", payload.model));
+    }
+
+    // Refuse prompts that clearly won't fit in the model's context window
+    if prompt_guard_enabled() {
+        if let Some(num_ctx) = details.as_ref().and_then(|d| d.context_size) {
+            let estimated = payload.estimated_prompt_tokens();
+            if estimated > num_ctx {
+                return Err(format!(
+                    "Prompt is too long for {} (~{} tokens, context window is {})",
+                    payload.model, estimated, num_ctx
+                ));
+            }
+        }
+    }
+
+    let (path, mut body) = if payload.raw {
+        (ollama_rust::routes::ollama::GENERATE, serde_json::json!({
+            "model": payload.model,
+            "prompt": payload.raw_prompt(),
+            "raw": true,
+            "stream": stream
+        }))
+    } else if payload.endpoint == Endpoint::Generate {
+        let mut body = serde_json::json!({
+            "model": payload.model,
+            "prompt": payload.raw_prompt(),
+            "stream": stream
+        });
+        if let Some(system) = payload.system_prompt() {
+            body["system"] = serde_json::Value::String(system);
+        }
+        let images = payload.latest_images();
+        if !images.is_empty() {
+            body["images"] = serde_json::Value::from(images);
+        }
+        (ollama_rust::routes::ollama::GENERATE, body)
+    } else {
+        (ollama_rust::routes::ollama::CHAT, serde_json::json!({
+            "model": payload.model,
+            "messages": messages,
+            "stream": stream
+        }))
+    };
+    if let Some(keep_alive) = keep_alive {
+        body["keep_alive"] = keep_alive;
+    }
+    if let Some(format) = payload.format.clone() {
+        body["format"] = format;
+    }
+    if let Some(options) = payload.options.clone().filter(|o| !o.is_empty()) {
+        body["options"] = serde_json::Value::Object(options);
+    }
+    if !payload.tools.is_empty() {
+        body["tools"] = serde_json::Value::Array(payload.tools.clone());
+    }
+    // Reasoning models stream their thoughts separately when asked to
+    if !payload.raw && details.as_ref().is_some_and(|d| d.capabilities.iter().any(|c| c == "thinking")) {
+        body["think"] = serde_json::Value::Bool(true);
+    }
+    Ok((path, body))
+}
+
+/// Whole reply and Ollama's stats as one JSON object, for scripts that don't want SSE
+#[cfg(feature = "ssr")]
+async fn chat_handler(
+    axum::Extension(OllamaBaseUrl(base_url)): axum::Extension<OllamaBaseUrl>,
+    axum::Json(payload): axum::Json<PromptRequest>,
+) -> Result<axum::Json<serde_json::Value>, (axum::http::StatusCode, axum::Json<serde_json::Value>)> {
+    use axum::http::StatusCode;
+
+    metrics::counter!("ollama_ui_chat_requests_total").increment(1);
+    let request_id = new_request_id();
+    leptos::logging::log!("[{}] chat request for {}", request_id, payload.model);
+    let error = |status: StatusCode, message: String| {
+        leptos::logging::warn!("[{}] {}", request_id, message);
+        (status, axum::Json(serde_json::json!({ "error": message, "request_id": request_id })))
+    };
+
+    if payload.model.starts_with("cloud:") {
+        return Err(error(StatusCode::BAD_REQUEST, "Cloud models are not supported here".to_string()));
+    }
+    check_model_installed(&base_url, &payload.model)
+        .await
+        .map_err(|message| error(StatusCode::NOT_FOUND, message))?;
+    let (path, body) = upstream_request(&base_url, &payload, false)
+        .await
+        .map_err(|message| error(StatusCode::BAD_REQUEST, message))?;
+    // Counted from here, like `/api/stream`, so refused requests stay out of the metrics
+    let _stream_metrics = StreamMetrics::start(&request_id);
+
+    let client = ollama_rust::models::ollama_client();
+    let url = format!("{}{}", base_url, path);
+    let mut res = client.post(&url).json(&body).send().await;
+    if payload.auto_start && res.as_ref().is_err_and(|e| e.is_connect()) && ollama_rust::models::start_ollama_and_wait().await.is_ok() {
+        res = client.post(&url).json(&body).send().await;
+    }
+    let response = res.map_err(|_| error(StatusCode::BAD_GATEWAY, "Ollama not reachable".to_string()))?;
+
+    let status = response.status();
+    let reply: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| error(StatusCode::BAD_GATEWAY, format!("Unexpected reply from Ollama: {}", e)))?;
+    if !status.is_success() {
+        let message = reply["error"].as_str().unwrap_or("Ollama returned an error").to_string();
+        return Err(error(StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY), message));
+    }
+    if let Some(eval_count) = reply["eval_count"].as_u64() {
+        metrics::counter!("ollama_ui_tokens_generated_total").increment(eval_count);
+    }
+
+    // `/api/chat` nests the text under `message`, `/api/generate` uses `response`
+    let content = reply["message"]["content"].as_str().or(reply["response"].as_str()).unwrap_or_default();
+    let mut message = serde_json::json!({ "role": "assistant", "content": content });
+    if let Some(thinking) = reply["message"]["thinking"].as_str().filter(|t| !t.is_empty()) {
+        message["thinking"] = serde_json::Value::from(thinking);
+    }
+    if let Some(tool_calls) = reply["message"]["tool_calls"].as_array().filter(|calls| !calls.is_empty()) {
+        message["tool_calls"] = serde_json::Value::Array(tool_calls.clone());
+    }
+    let mut result = serde_json::json!({ "model": payload.model, "message": message, "request_id": request_id });
+    for key in REPLY_STATS {
+        if let Some(value) = reply.get(key) {
+            result[key] = value.clone();
+        }
+    }
+    Ok(axum::Json(result))
+}
+
+#[cfg(feature = "ssr")]
+async fn chat_stream(base_url: &str, payload: PromptRequest, request_id: &str, protocol: StreamProtocol) -> EventStream {
+    use futures::StreamExt;
+
+    let messages = payload.chat_messages();
 
     // Check if this is a cloud model request
     if payload.model.starts_with("cloud:") {
@@ -58,63 +1020,836 @@ async fn stream_handler(
             1. Sign up for Ollama Cloud at ollama.com\n\
             2. Get your API credentials\n\
             3. Configure the cloud endpoint in your settings",
-            payload.prompt.chars().take(100).collect::<String>(),
+            messages.last().map(|m| m.content.as_str()).unwrap_or("").chars().take(100).collect::<String>(),
             cloud_model
         );
 
+        let request_id = request_id.to_string();
         let stream = async_stream::stream! {
             // Stream the response word by word for a more realistic effect
-            for word in response_text.split_whitespace() {
-                yield Ok(axum::response::sse::Event::default().data(format!("{} ", word)));
+            for word in response_text.split_inclusive(' ') {
+                yield Ok(protocol.token(word));
                 tokio::time::sleep(tokio::time::Duration::from_millis(30)).await;
             }
-            yield Ok(axum::response::sse::Event::default().data("__END__"));
+            yield Ok(protocol.done(&request_id, None));
         };
-        return axum::response::sse::Sse::new(Box::pin(stream));
+        return Box::pin(stream);
     }
 
     // Local Ollama model request
-    let client = reqwest::Client::new();
-    let res = client
-        .post("http://localhost:11434/api/generate")
-        .json(&serde_json::json!({
-            "model": payload.model,
-            "prompt": payload.prompt,
-            "stream": true
-        }))
-        .send()
-        .await;
+    if let Err(message) = check_model_installed(base_url, &payload.model).await {
+        leptos::logging::warn!("[{}] {}", request_id, message);
+        return error_events(protocol, message);
+    }
+    let client = ollama_rust::models::ollama_client();
+    let context_check = ContextCheck {
+        base_url: base_url.to_string(),
+        model: payload.model.clone(),
+        estimated_tokens: payload.estimated_prompt_tokens(),
+    };
+    let (path, body) = match upstream_request(base_url, &payload, true).await {
+        Ok(request) => request,
+        Err(message) => {
+            leptos::logging::warn!("[{}] {}", request_id, message);
+            return error_events(protocol, message);
+        }
+    };
+    let stream_metrics = StreamMetrics::start(request_id);
 
-    match res {
-        Ok(response) => {
-            let body_with_io_error = response.bytes_stream().map(|res| {
-                res.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
-            });
-            let reader = StreamReader::new(body_with_io_error);
-            let mut lines = FramedRead::new(reader, LinesCodec::new());
+    let url = format!("{}{}", base_url, path);
+    let res = client.post(&url).json(&body).send().await;
 
+    match res {
+        Ok(response) => response_events(response, context_check, stream_metrics, protocol, sse_flush_interval()),
+        // Opt-in: start Ollama ourselves and retry once
+        Err(e) if payload.auto_start && e.is_connect() => {
             let stream = async_stream::stream! {
-                while let Some(Ok(line)) = lines.next().await {
-                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
-                        if let Some(text) = json["response"].as_str() {
-                            yield Ok(axum::response::sse::Event::default().data(text));
-                        }
-                        if json["done"].as_bool().unwrap_or(false) {
-                            yield Ok(axum::response::sse::Event::default().data("__END__"));
+                yield Ok(protocol.status("Starting Ollama..."));
+                if let Err(reason) = ollama_rust::models::start_ollama_and_wait().await {
+                    yield Ok(protocol.error(&reason));
+                    return;
+                }
+                match client.post(url).json(&body).send().await {
+                    Ok(response) => {
+                        let mut events = response_events(response, context_check, stream_metrics, protocol, sse_flush_interval());
+                        while let Some(event) = events.next().await {
+                            yield event;
                         }
                     }
+                    Err(_) => yield Ok(protocol.error("Ollama not reachable")),
                 }
             };
-            axum::response::sse::Sse::new(Box::pin(stream))
+            Box::pin(stream)
         }
-        Err(_) => {
-            let error_stream = futures::stream::once(async {
-                Ok(axum::response::sse::Event::default().data("[Error: Ollama not reachable]"))
-            });
-            axum::response::sse::Sse::new(Box::pin(error_stream))
+        // Last resort: the HTTP API is out of reach, but the CLI may still get through
+        Err(e) if e.is_connect() && cli_fallback_enabled() => {
+            cli_events(&payload.model, payload.raw_prompt(), stream_metrics, protocol)
+        }
+        Err(e) => {
+            leptos::logging::warn!("[{}] Ollama not reachable: {}", request_id, e);
+            error_events(protocol, "Ollama not reachable".to_string())
+        }
+    }
+}
+
+/// Answer through `ollama run <model>` instead of the HTTP API, streaming stdout
+/// line by line. Only the latest user message is sent: no history, system prompt,
+/// options, format, tools or thinking. The child is killed if the client goes away.
+#[cfg(feature = "ssr")]
+fn cli_events(model: &str, prompt: String, stream_metrics: StreamMetrics, protocol: StreamProtocol) -> EventStream {
+    use futures::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::codec::{FramedRead, LinesCodec};
+
+    let mut command = tokio::process::Command::new("ollama");
+    command
+        .args(["run", "--nowordwrap", model])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        // Dropping the stream on disconnect drops the child with it
+        .kill_on_drop(true);
+
+    let stream = async_stream::stream! {
+        let stream_metrics = stream_metrics;
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                yield Ok(protocol.error(&format!("Could not run ollama: {}", e)));
+                return;
+            }
+        };
+        let (Some(mut stdin), Some(stdout), Some(mut stderr)) = (child.stdin.take(), child.stdout.take(), child.stderr.take()) else {
+            yield Ok(protocol.error("Could not talk to ollama"));
+            return;
+        };
+        // Read stderr alongside stdout so a chatty spinner can't fill the pipe and stall the child
+        let stderr_text = tokio::spawn(async move {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text).await;
+            text
+        });
+        // Closing stdin tells `ollama run` the prompt is complete
+        let _ = stdin.write_all(prompt.as_bytes()).await;
+        drop(stdin);
+
+        let mut lines = FramedRead::new(stdout, LinesCodec::new());
+        let mut replied = false;
+        while let Some(Ok(line)) = lines.next().await {
+            replied = true;
+            yield Ok(protocol.token(&format!("{}\n", line)));
+        }
+
+        let status = child.wait().await;
+        if !status.as_ref().is_ok_and(|s| s.success()) && !replied {
+            let stderr_text = stderr_text.await.unwrap_or_default();
+            let reason = stderr_text
+                .lines()
+                .rev()
+                .find_map(|line| line.split_once("Error: ").map(|(_, reason)| reason.trim().to_string()))
+                .unwrap_or_else(|| "ollama run failed".to_string());
+            yield Ok(protocol.error(&reason));
+            return;
+        }
+        yield Ok(protocol.done(&stream_metrics.request_id, None));
+    };
+    Box::pin(stream)
+}
+
+/// What's needed to tell, once a reply is done, whether Ollama cut off the prompt
+#[cfg(feature = "ssr")]
+struct ContextCheck {
+    base_url: String,
+    model: String,
+    estimated_tokens: u64,
+}
+
+#[cfg(feature = "ssr")]
+impl ContextCheck {
+    /// `{"estimated", "window"}` if the prompt (by estimate, or by what Ollama
+    /// evaluated) filled the loaded context window, meaning earlier turns were dropped
+    async fn truncation(&self, prompt_eval_count: Option<u64>) -> Option<serde_json::Value> {
+        let window = ollama_rust::models::fetch_loaded_context_length(&self.base_url, &self.model).await?;
+        let truncated = self.estimated_tokens > window || prompt_eval_count.is_some_and(|n| n >= window);
+        truncated.then(|| serde_json::json!({ "estimated": self.estimated_tokens, "window": window }))
+    }
+}
+
+/// Turn Ollama's newline-delimited JSON reply into SSE events. Reply text is
+/// gathered for up to `flush_interval` into one event (zero sends each token).
+#[cfg(feature = "ssr")]
+fn response_events(
+    response: reqwest::Response,
+    context_check: ContextCheck,
+    stream_metrics: StreamMetrics,
+    protocol: StreamProtocol,
+    flush_interval: std::time::Duration,
+) -> EventStream {
+    use futures::StreamExt;
+
+    let mut lines = ollama_rust::models::ndjson_lines(response.bytes_stream());
+
+    let stream = async_stream::stream! {
+        let stream_metrics = stream_metrics;
+        // Text held back until the flush interval since its first token passes
+        let mut buffered = String::new();
+        let mut flush_at: Option<tokio::time::Instant> = None;
+        loop {
+            let next = match flush_at {
+                Some(deadline) => match tokio::time::timeout_at(deadline, lines.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        yield Ok(protocol.token(&std::mem::take(&mut buffered)));
+                        flush_at = None;
+                        continue;
+                    }
+                },
+                None => lines.next().await,
+            };
+            let Some(Ok(line)) = next else { break };
+            ollama_rust::app::record_raw_chunk(&stream_metrics.request_id, &line);
+            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
+                // e.g. an unknown model, or the model failing to load
+                if let Some(message) = json["error"].as_str() {
+                    if !buffered.is_empty() {
+                        yield Ok(protocol.token(&std::mem::take(&mut buffered)));
+                    }
+                    leptos::logging::warn!("[{}] Ollama error: {}", stream_metrics.request_id, message);
+                    yield Ok(protocol.error(message));
+                    return;
+                }
+                // `/api/chat` nests reasoning under `message` too, `/api/generate` has it at the top
+                let thinking = json["message"]["thinking"].as_str().or(json["thinking"].as_str());
+                if let Some(thinking) = thinking.filter(|t| !t.is_empty()) {
+                    // Keep text and thinking in the order Ollama sent them
+                    if !buffered.is_empty() {
+                        yield Ok(protocol.token(&std::mem::take(&mut buffered)));
+                        flush_at = None;
+                    }
+                    yield Ok(protocol.thinking(thinking));
+                }
+                if let Some(tool_calls) = json["message"]["tool_calls"].as_array().filter(|calls| !calls.is_empty()) {
+                    if !buffered.is_empty() {
+                        yield Ok(protocol.token(&std::mem::take(&mut buffered)));
+                        flush_at = None;
+                    }
+                    yield Ok(protocol.tool_calls(tool_calls));
+                }
+                // `/api/chat` nests the text under `message`, `/api/generate` uses `response`
+                let text = json["message"]["content"].as_str().or(json["response"].as_str());
+                if let Some(text) = text.filter(|t| !t.is_empty()) {
+                    if flush_interval.is_zero() {
+                        yield Ok(protocol.token(text));
+                    } else {
+                        buffered.push_str(text);
+                        flush_at.get_or_insert_with(|| tokio::time::Instant::now() + flush_interval);
+                    }
+                }
+                if json["done"].as_bool().unwrap_or(false) {
+                    if let Some(eval_count) = json["eval_count"].as_u64() {
+                        metrics::counter!("ollama_ui_tokens_generated_total").increment(eval_count);
+                    }
+                    if !buffered.is_empty() {
+                        yield Ok(protocol.token(&std::mem::take(&mut buffered)));
+                    }
+                    if let Some(truncation) = context_check.truncation(json["prompt_eval_count"].as_u64()).await {
+                        yield Ok(protocol.context(&truncation));
+                    }
+                    yield Ok(protocol.done(&stream_metrics.request_id, Some(&json)));
+                    break;
+                }
+            }
+        }
+        // Connection closed before `done`: don't lose what was held back
+        if !buffered.is_empty() {
+            yield Ok(protocol.token(&buffered));
+        }
+    };
+    Box::pin(stream)
+}
+
+/// Check tool definitions before Ollama sees them: each needs `"type": "function"`, a
+/// function name made of letters, digits, `_` or `-`, and object-typed `parameters` if any
+#[cfg(feature = "ssr")]
+fn validate_tools(tools: &[serde_json::Value]) -> Result<(), String> {
+    for (index, tool) in tools.iter().enumerate() {
+        let position = index + 1;
+        if tool["type"].as_str() != Some("function") {
+            return Err(format!("tool {} must have \"type\": \"function\"", position));
+        }
+        let function = &tool["function"];
+        let name = function["name"].as_str().unwrap_or_default();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("tool {} needs a function name of letters, digits, _ or -", position));
         }
+        match function.get("parameters") {
+            None => {}
+            Some(params) if params.is_object() && params["type"].as_str() == Some("object") => {}
+            Some(_) => return Err(format!("tool {} ({}): parameters must be a JSON schema with \"type\": \"object\"", position, name)),
+        }
+        if function.get("description").is_some_and(|d| !d.is_string()) {
+            return Err(format!("tool {} ({}): description must be a string", position, name));
+        }
+    }
+    Ok(())
+}
+
+/// Validate a `keep_alive` value: either a number of seconds (`-1` keeps the model
+/// loaded forever, `0` unloads it right away) or a Go-style duration like `5m` or `1h30m`
+#[cfg(feature = "ssr")]
+fn parse_keep_alive(value: &str) -> Option<serde_json::Value> {
+    if let Ok(seconds) = value.parse::<i64>() {
+        return Some(serde_json::Value::from(seconds));
+    }
+
+    let mut rest = value.strip_prefix('-').unwrap_or(value);
+    if rest.is_empty() {
+        return None;
     }
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        if number_len == 0 || rest[..number_len].parse::<f64>().is_err() {
+            return None;
+        }
+        rest = &rest[number_len..];
+        let unit = ["ns", "us", "µs", "ms", "s", "m", "h"]
+            .into_iter()
+            .filter(|unit| rest.starts_with(unit))
+            .max_by_key(|unit| unit.len())?;
+        rest = &rest[unit.len()..];
+    }
+
+    Some(serde_json::Value::String(value.to_string()))
+}
+
+/// Seconds between SSE keep-alive comments, from `UI_SSE_KEEPALIVE_SECS` (default 15, `0` disables)
+#[cfg(feature = "ssr")]
+fn sse_keep_alive_interval() -> Option<std::time::Duration> {
+    let secs = std::env::var("UI_SSE_KEEPALIVE_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(15);
+    (secs > 0).then(|| std::time::Duration::from_secs(secs))
+}
+
+/// How long to gather tokens into one SSE event, from `UI_SSE_FLUSH_MS` (default `0`, one event per token)
+#[cfg(feature = "ssr")]
+fn sse_flush_interval() -> std::time::Duration {
+    let ms = std::env::var("UI_SSE_FLUSH_MS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(0);
+    std::time::Duration::from_millis(ms)
+}
+
+/// Events `/api/stream` holds for a client that reads slower than the model
+/// writes, from `UI_SSE_BUFFER_EVENTS` (default 256, at least 1)
+#[cfg(feature = "ssr")]
+fn sse_buffer_events() -> usize {
+    std::env::var("UI_SSE_BUFFER_EVENTS")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(256)
+        .max(1)
+}
+
+/// Chat falls back to the `ollama run` CLI when `UI_CLI_FALLBACK` is set to `1` or `true`
+#[cfg(feature = "ssr")]
+fn cli_fallback_enabled() -> bool {
+    std::env::var("UI_CLI_FALLBACK")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// The prompt length guard is on unless `UI_PROMPT_GUARD` is set to `0` or `false`
+#[cfg(feature = "ssr")]
+fn prompt_guard_enabled() -> bool {
+    std::env::var("UI_PROMPT_GUARD")
+        .map(|v| v != "0" && !v.eq_ignore_ascii_case("false"))
+        .unwrap_or(true)
+}
+
+/// Rough token estimate (~4 characters per token), good enough for an advisory check
+#[cfg(feature = "ssr")]
+fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
 }
 
 #[cfg(not(feature = "ssr"))]
 pub fn main() {}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use tower::ServiceExt;
+
+    /// A small app behind `require_auth` with `token` configured
+    fn auth_app(token: Option<&str>) -> axum::Router {
+        use axum::routing::get;
+        use ollama_rust::routes;
+
+        axum::Router::new()
+            .route("/", get(|| std::future::ready("app")))
+            .route(routes::HEALTH, get(|| std::future::ready("ok")))
+            .layer(axum::middleware::from_fn_with_state(token.map(str::to_string), require_auth))
+    }
+
+    async fn status_of(app: axum::Router, request: Request<Body>) -> StatusCode {
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn missing_token_is_refused_with_the_login_form() {
+        let response = auth_app(Some("secret"))
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains(&format!(r#"action="{}""#, ollama_rust::routes::LOGIN)));
+    }
+
+    #[tokio::test]
+    async fn wrong_token_is_refused() {
+        let request = Request::get("/").header(header::AUTHORIZATION, "Bearer nope").body(Body::empty()).unwrap();
+        assert_eq!(status_of(auth_app(Some("secret")), request).await, StatusCode::UNAUTHORIZED);
+        let request = Request::get("/").header(header::COOKIE, "ui_auth=nope").body(Body::empty()).unwrap();
+        assert_eq!(status_of(auth_app(Some("secret")), request).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn bearer_token_is_accepted() {
+        let request = Request::get("/").header(header::AUTHORIZATION, "Bearer secret").body(Body::empty()).unwrap();
+        assert_eq!(status_of(auth_app(Some("secret")), request).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn login_cookie_is_accepted() {
+        let request = Request::get("/")
+            .header(header::COOKIE, format!("theme=dark; {}=secret", AUTH_COOKIE))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(status_of(auth_app(Some("secret")), request).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn health_is_served_without_a_token() {
+        let request = Request::get(ollama_rust::routes::HEALTH).body(Body::empty()).unwrap();
+        assert_eq!(status_of(auth_app(Some("secret")), request).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn everything_is_open_without_a_configured_token() {
+        let request = Request::get("/").body(Body::empty()).unwrap();
+        assert_eq!(status_of(auth_app(None), request).await, StatusCode::OK);
+    }
+
+    #[test]
+    fn requests_past_the_limit_are_told_when_to_retry() {
+        let ip: std::net::IpAddr = [10, 0, 0, 1].into();
+        for _ in 0..3 {
+            assert_eq!(check_rate_limit("test", ip, 3), None);
+        }
+        let retry_after = check_rate_limit("test", ip, 3).expect("fourth request should be limited");
+        assert!(retry_after <= std::time::Duration::from_secs(60));
+        // Other clients and endpoints have their own budget
+        assert_eq!(check_rate_limit("test", [10, 0, 0, 2].into(), 3), None);
+        assert_eq!(check_rate_limit("other", ip, 3), None);
+    }
+
+    #[test]
+    fn forwarded_clients_are_only_believed_from_trusted_proxies() {
+        let proxy: std::net::IpAddr = [10, 0, 2, 1].into();
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.9, 198.51.100.7".parse().unwrap());
+
+        assert_eq!(client_ip(proxy, &headers, &[]), proxy);
+        assert_eq!(client_ip(proxy, &headers, &[proxy]), "198.51.100.7".parse::<std::net::IpAddr>().unwrap());
+        // A chain of trusted proxies is skipped down to the client
+        let second: std::net::IpAddr = "198.51.100.7".parse().unwrap();
+        assert_eq!(client_ip(proxy, &headers, &[proxy, second]), "203.0.113.9".parse::<std::net::IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn the_forwarded_header_is_read_when_x_forwarded_for_is_missing() {
+        let proxy: std::net::IpAddr = [10, 0, 2, 1].into();
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("forwarded", r#"for="[2001:db8::1]:4711";proto=https"#.parse().unwrap());
+        assert_eq!(client_ip(proxy, &headers, &[proxy]), "2001:db8::1".parse::<std::net::IpAddr>().unwrap());
+
+        headers.insert("forwarded", "for=192.0.2.60:8080".parse().unwrap());
+        assert_eq!(client_ip(proxy, &headers, &[proxy]), "192.0.2.60".parse::<std::net::IpAddr>().unwrap());
+
+        // Nothing usable forwarded: the proxy itself is the client
+        headers.insert("forwarded", "for=unknown".parse().unwrap());
+        assert_eq!(client_ip(proxy, &headers, &[proxy]), proxy);
+    }
+
+    #[tokio::test]
+    async fn chat_burst_gets_429_with_retry_after() {
+        use axum::routing::post;
+        use ollama_rust::routes;
+
+        let app = axum::Router::new()
+            .route(routes::CHAT, post(|| std::future::ready("ok")))
+            .layer(axum::middleware::from_fn(rate_limit));
+        let client = std::net::SocketAddr::from(([10, 0, 1, 1], 40000));
+        let chat = || {
+            let mut request = Request::post(routes::CHAT).body(Body::empty()).unwrap();
+            request.extensions_mut().insert(axum::extract::ConnectInfo(client));
+            request
+        };
+
+        // `UI_RATE_LIMIT_CHAT` isn't set in tests, so the default of 30 a minute applies
+        for _ in 0..30 {
+            assert_eq!(status_of(app.clone(), chat()).await, StatusCode::OK);
+        }
+        let response = app.oneshot(chat()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+        assert!((1..=60).contains(&retry_after));
+    }
+
+    #[tokio::test]
+    async fn a_burst_of_bad_logins_gets_429() {
+        use axum::routing::post;
+        use ollama_rust::routes;
+
+        let app = axum::Router::new()
+            .route(routes::LOGIN, post(|| std::future::ready(StatusCode::UNAUTHORIZED)))
+            .layer(axum::middleware::from_fn(rate_limit));
+        let client = std::net::SocketAddr::from(([10, 0, 1, 3], 40000));
+        let login = || {
+            let mut request = Request::post(routes::LOGIN).body(Body::from("token=guess")).unwrap();
+            request.extensions_mut().insert(axum::extract::ConnectInfo(client));
+            request
+        };
+
+        // `UI_RATE_LIMIT_LOGIN` isn't set in tests, so the default of 10 a minute applies
+        for _ in 0..10 {
+            assert_eq!(status_of(app.clone(), login()).await, StatusCode::UNAUTHORIZED);
+        }
+        let response = app.oneshot(login()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    #[tokio::test]
+    async fn updating_installed_models_is_not_held_to_the_pull_limit() {
+        use axum::routing::post;
+        use leptos::server_fn::ServerFn;
+        use ollama_rust::models::{StartModelPull, StartModelUpdate};
+
+        let app = axum::Router::new()
+            .route(StartModelPull::PATH, post(|| std::future::ready("ok")))
+            .route(StartModelUpdate::PATH, post(|| std::future::ready("ok")))
+            .layer(axum::middleware::from_fn(rate_limit));
+        let client = std::net::SocketAddr::from(([10, 0, 1, 2], 40000));
+        let call = |path: &str| {
+            let mut request = Request::post(path).body(Body::empty()).unwrap();
+            request.extensions_mut().insert(axum::extract::ConnectInfo(client));
+            request
+        };
+
+        // More updates than the default of 10 pulls a minute, as "update all" makes
+        for _ in 0..25 {
+            assert_eq!(status_of(app.clone(), call(StartModelUpdate::PATH)).await, StatusCode::OK);
+        }
+        for _ in 0..10 {
+            assert_eq!(status_of(app.clone(), call(StartModelPull::PATH)).await, StatusCode::OK);
+        }
+        assert_eq!(status_of(app.clone(), call(StartModelPull::PATH)).await, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(status_of(app, call(StartModelUpdate::PATH)).await, StatusCode::OK);
+    }
+
+    /// What `events` sends to the client, as SSE text
+    async fn sse_body(events: EventStream) -> String {
+        use axum::response::IntoResponse;
+
+        let response = axum::response::sse::Sse::new(events).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    /// `response_events` over an Ollama reply with the given NDJSON lines
+    async fn reply_events(lines: &[serde_json::Value], flush_interval: std::time::Duration) -> String {
+        let body: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        let response = reqwest::Response::from(axum::http::Response::new(body));
+        let context_check = ContextCheck { base_url: mock_ollama().to_string(), model: "test".to_string(), estimated_tokens: 0 };
+        let events = response_events(response, context_check, StreamMetrics::start("req-1"), StreamProtocol::V2, flush_interval);
+        sse_body(events).await
+    }
+
+    fn token_line(text: &str) -> serde_json::Value {
+        serde_json::json!({ "message": { "role": "assistant", "content": text }, "done": false })
+    }
+
+    #[tokio::test]
+    async fn tokens_within_the_flush_window_become_one_event() {
+        let lines = [token_line("Hel"), token_line("lo"), token_line(" world"), serde_json::json!({ "done": true, "eval_count": 3 })];
+        let body = reply_events(&lines, std::time::Duration::from_secs(60)).await;
+        // `done` flushes what was held back, ahead of itself
+        assert_eq!(body.matches("event: token").count(), 1, "{}", body);
+        let token_at = body.find("event: token\ndata: Hello world\n").expect(&body);
+        let done_at = body.find("event: done").expect(&body);
+        assert!(token_at < done_at);
+        assert!(body.contains(r#"data: {"eval_count":3}"#));
+    }
+
+    #[tokio::test]
+    async fn zero_flush_interval_sends_every_token() {
+        let lines = [token_line("Hel"), token_line("lo"), serde_json::json!({ "done": true })];
+        let body = reply_events(&lines, std::time::Duration::ZERO).await;
+        assert_eq!(body.matches("event: token").count(), 2, "{}", body);
+        assert!(body.contains("data: Hel\n") && body.contains("data: lo\n"));
+    }
+
+    #[tokio::test]
+    async fn held_back_text_is_sent_when_the_reply_ends_early() {
+        let lines = [token_line("cut "), token_line("off")];
+        let body = reply_events(&lines, std::time::Duration::from_secs(60)).await;
+        assert!(body.contains("event: token\ndata: cut off\n"), "{}", body);
+        assert!(!body.contains("event: done"));
+    }
+
+    /// How long the stand-in Ollama holds back a streamed reply's headers, as Ollama
+    /// does while it loads the model
+    const MOCK_LOAD_TIME: std::time::Duration = std::time::Duration::from_millis(300);
+
+    /// Base URL of a stand-in Ollama for this test process, answering `/api/tags`
+    /// with `llama3:latest` installed and `/api/chat` with an echo, streamed after
+    /// [`MOCK_LOAD_TIME`] when asked to stream
+    fn mock_ollama() -> &'static str {
+        static MOCK: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+        MOCK.get_or_init(|| {
+            use axum::response::IntoResponse;
+            use axum::routing::{get, post};
+
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.set_nonblocking(true).unwrap();
+            let base_url = format!("http://{}", listener.local_addr().unwrap());
+            let app = axum::Router::new()
+                .route(
+                    "/api/tags",
+                    get(|| async { axum::Json(serde_json::json!({ "models": [{ "name": "llama3:latest", "size": 1 }] })) }),
+                )
+                .route(
+                    "/api/chat",
+                    post(|axum::Json(body): axum::Json<serde_json::Value>| async move {
+                        let asked = body["messages"][0]["content"].as_str().unwrap_or_default().to_string();
+                        if body["stream"] != false {
+                            tokio::time::sleep(MOCK_LOAD_TIME).await;
+                            let lines = [
+                                serde_json::json!({ "message": { "role": "assistant", "content": format!("You said: {}", asked) }, "done": false }),
+                                serde_json::json!({ "done": true, "done_reason": "stop", "eval_count": 3 }),
+                            ];
+                            return lines.iter().map(|line| format!("{}\n", line)).collect::<String>().into_response();
+                        }
+                        axum::Json(serde_json::json!({
+                            "model": body["model"],
+                            "message": { "role": "assistant", "content": format!("You said: {}", asked), "thinking": "" },
+                            "done": true,
+                            "done_reason": "stop",
+                            "eval_count": 4,
+                            "prompt_eval_count": 2,
+                            "total_duration": 1000,
+                        }))
+                        .into_response()
+                    }),
+                );
+            // Its own runtime, since each test's runtime ends with the test
+            std::thread::spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+                runtime.block_on(async move {
+                    let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                    axum::serve(listener, app).await.unwrap();
+                });
+            });
+            base_url
+        })
+    }
+
+    /// The stand-in Ollama, as the routes are handed it; the environment is left
+    /// alone, since other tests read it from other threads
+    fn on_mock_ollama() -> axum::Extension<OllamaBaseUrl> {
+        axum::Extension(OllamaBaseUrl(mock_ollama().to_string()))
+    }
+
+    fn prompt(json: serde_json::Value) -> PromptRequest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn chat_returns_the_whole_reply_with_stats() {
+        let payload = prompt(serde_json::json!({ "model": "llama3", "messages": [{ "role": "user", "content": "hi" }] }));
+        let axum::Json(reply) = chat_handler(on_mock_ollama(), axum::Json(payload)).await.unwrap();
+        assert_eq!(reply["model"], "llama3");
+        assert_eq!(reply["message"], serde_json::json!({ "role": "assistant", "content": "You said: hi" }));
+        assert_eq!(reply["done_reason"], "stop");
+        assert_eq!(reply["eval_count"], 4);
+        assert_eq!(reply["prompt_eval_count"], 2);
+        assert_eq!(reply["total_duration"], 1000);
+        assert!(reply["request_id"].as_str().is_some_and(|id| !id.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn a_router_given_the_stand_in_lists_its_models() {
+        use axum::routing::get;
+        use ollama_rust::routes;
+
+        let app = axum::Router::new().route(routes::MODELS, get(models_handler)).layer(on_mock_ollama());
+        let response = app.oneshot(Request::get(routes::MODELS).body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let models: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(models.as_array().unwrap().iter().any(|m| m["name"] == "llama3:latest"), "{}", models);
+    }
+
+    #[tokio::test]
+    async fn chat_refuses_cloud_models() {
+        let payload = prompt(serde_json::json!({ "model": "cloud:gpt", "prompt": "hi" }));
+        let (status, axum::Json(error)) = chat_handler(on_mock_ollama(), axum::Json(payload)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error["request_id"].is_string());
+    }
+
+    #[tokio::test]
+    async fn unknown_models_are_refused_and_untagged_names_mean_latest() {
+        let ollama = mock_ollama();
+        assert_eq!(check_model_installed(ollama, "llama3").await, Ok(()));
+        assert_eq!(check_model_installed(ollama, "llama3:latest").await, Ok(()));
+        assert_eq!(check_model_installed(ollama, "mistral").await, Err("Model \"mistral\" is not installed".to_string()));
+        assert!(check_model_installed(ollama, "llama3:70b").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn chat_with_an_unknown_model_is_not_found() {
+        let payload = prompt(serde_json::json!({ "model": "mistral", "prompt": "hi" }));
+        let (status, axum::Json(error)) = chat_handler(on_mock_ollama(), axum::Json(payload)).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error["error"], "Model \"mistral\" is not installed");
+    }
+
+    #[tokio::test]
+    async fn keep_alives_are_sent_while_the_model_loads() {
+        use axum::response::IntoResponse;
+
+        let payload = prompt(serde_json::json!({ "model": "llama3", "prompt": "hi", "protocol": 2 }));
+        let events = stream_events(mock_ollama().to_string(), payload, "slow-load-test".to_string(), StreamProtocol::V2);
+        let response = sse_response(events, Some(MOCK_LOAD_TIME / 6)).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(body.starts_with("event: start\n"), "{}", body);
+        let keep_alive_at = body.find(": keep-alive\n").expect(&body);
+        let token_at = body.find("event: token\ndata: You said: hi\n").expect(&body);
+        assert!(keep_alive_at < token_at, "{}", body);
+        assert!(body.contains("event: done\nid: slow-load-test\n"), "{}", body);
+    }
+
+    /// A reply that never ends on its own
+    fn endless_tokens() -> EventStream {
+        Box::pin(futures::stream::repeat_with(|| Ok(axum::response::sse::Event::default().event("token").data("x"))))
+    }
+
+    async fn cancel(request_id: &str) -> StatusCode {
+        let request = CancelStreamRequest { request_id: request_id.to_string() };
+        cancel_stream_handler(axum::Json(request)).await
+    }
+
+    #[tokio::test]
+    async fn cancelling_one_stream_leaves_the_other_running() {
+        use futures::StreamExt;
+
+        let mut first = cancellable(endless_tokens(), "cancel-test-1", StreamProtocol::V2);
+        let mut second = cancellable(endless_tokens(), "cancel-test-2", StreamProtocol::V2);
+        assert!(first.next().await.is_some());
+        assert!(second.next().await.is_some());
+
+        assert_eq!(cancel("cancel-test-1").await, StatusCode::NO_CONTENT);
+        let rest = sse_body(first).await;
+        assert!(rest.starts_with("event: done\nid: cancel-test-1\n"), "{}", rest);
+        assert_eq!(rest.matches("event:").count(), 1, "{}", rest);
+        // Gone once its stream has ended
+        assert_eq!(cancel("cancel-test-1").await, StatusCode::NOT_FOUND);
+
+        assert_eq!(second.by_ref().take(100).count().await, 100);
+        assert_eq!(cancel("cancel-test-2").await, StatusCode::NO_CONTENT);
+        assert!(sse_body(second).await.contains("id: cancel-test-2"));
+    }
+
+    #[tokio::test]
+    async fn cancelling_an_unknown_stream_is_not_found() {
+        assert_eq!(cancel("cancel-test-unknown").await, StatusCode::NOT_FOUND);
+    }
+
+    /// Flags `dropped` when the stream holding it goes away
+    struct DropFlag(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    /// An endless reply counting the events taken from it, flagging when it's dropped
+    fn counted_tokens(
+        produced: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+        dropped: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    ) -> EventStream {
+        Box::pin(async_stream::stream! {
+            let _flag = DropFlag(dropped);
+            loop {
+                produced.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                yield Ok(axum::response::sse::Event::default().event("token").data("x"));
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn a_slow_client_stalls_the_reply_at_capacity() {
+        use futures::StreamExt;
+        use std::sync::atomic::Ordering;
+
+        let produced = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let dropped = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut events = bounded(counted_tokens(produced.clone(), dropped.clone()), 4);
+        assert!(events.next().await.is_some());
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let ahead = produced.load(Ordering::SeqCst);
+        // The one read, a full buffer, and one waiting for room
+        assert!((4..=6).contains(&ahead), "{}", ahead);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(produced.load(Ordering::SeqCst), ahead);
+
+        assert!(events.next().await.is_some());
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(produced.load(Ordering::SeqCst), ahead + 1);
+        assert!(!dropped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn the_reply_is_dropped_when_the_client_goes_away() {
+        use futures::StreamExt;
+        use std::sync::atomic::Ordering;
+
+        let produced = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let dropped = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut events = bounded(counted_tokens(produced, dropped.clone()), 4);
+        assert!(events.next().await.is_some());
+        drop(events);
+
+        for _ in 0..100 {
+            if dropped.load(Ordering::SeqCst) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("the reply outlived its client");
+    }
+}
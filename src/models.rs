@@ -0,0 +1,1940 @@
+//! Model operations: listing installed models, pulling, pausing, cancelling
+//! and deleting them, building models from a Modelfile, loading and
+//! benchmarking them, and starting Ollama itself, plus the helpers every
+//! request to Ollama goes through (its address, the shared HTTP client and the
+//! models directory). The UI in `app` calls the server functions here; `main`
+//! uses the helpers directly.
+
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "ssr")]
+use std::sync::{Mutex, OnceLock};
+
+/// Base URL of the Ollama API, taken from `OLLAMA_HOST` like the Ollama CLI does
+/// (default `http://localhost:11434`)
+#[cfg(feature = "ssr")]
+pub fn ollama_base_url() -> String {
+    base_url_from_host(&std::env::var("OLLAMA_HOST").unwrap_or_default())
+}
+
+/// Base URL for an `OLLAMA_HOST` value, read the way the Ollama CLI reads it: a bare
+/// `host[:port]` is plain HTTP on port 11434, while with a scheme the port defaults
+/// to the scheme's own (80 or 443). A path is kept, for Ollama behind a proxy
+/// prefix. Values that don't parse fall back to the default.
+#[cfg(feature = "ssr")]
+fn base_url_from_host(host: &str) -> String {
+    const DEFAULT: &str = "http://localhost:11434";
+    let host = host.trim();
+    if host.is_empty() {
+        return DEFAULT.to_string();
+    }
+
+    let (url, bare) = match host.split_once("://") {
+        Some(_) => (reqwest::Url::parse(host), None),
+        None => (reqwest::Url::parse(&format!("http://{}", host)), Some(host)),
+    };
+    let Ok(mut url) = url else {
+        return DEFAULT.to_string();
+    };
+    if let Some(bare) = bare {
+        // Only the part after an IPv6 address's brackets can hold the port
+        let authority = bare.split('/').next().unwrap_or_default();
+        if !authority.rsplit(']').next().unwrap_or_default().contains(':') {
+            let _ = url.set_port(Some(11434));
+        }
+    }
+    url.as_str().trim_end_matches('/').to_string()
+}
+
+/// Full URL for an Ollama API path such as `/api/tags`
+#[cfg(feature = "ssr")]
+pub fn ollama_url(path: &str) -> String {
+    format!("{}{}", ollama_base_url(), path)
+}
+
+// Shared HTTP client for all requests to Ollama
+#[cfg(feature = "ssr")]
+static OLLAMA_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// HTTP client for talking to Ollama. When `OLLAMA_AUTH_HEADER` (a full
+/// `Authorization` value) or `OLLAMA_API_KEY` (sent as a bearer token) is set,
+/// it is attached to every request for use behind an auth proxy.
+#[cfg(feature = "ssr")]
+pub fn ollama_client() -> reqwest::Client {
+    OLLAMA_CLIENT
+        .get_or_init(|| {
+            let headers = ollama_auth_headers(
+                std::env::var("OLLAMA_AUTH_HEADER").ok(),
+                std::env::var("OLLAMA_API_KEY").ok(),
+            );
+            reqwest::Client::builder()
+                .default_headers(headers)
+                .build()
+                .unwrap_or_default()
+        })
+        .clone()
+}
+
+/// Headers for every Ollama request: `auth_header` (`OLLAMA_AUTH_HEADER`) as the
+/// `Authorization` value if set, else `api_key` (`OLLAMA_API_KEY`) as a bearer token
+#[cfg(feature = "ssr")]
+fn ollama_auth_headers(auth_header: Option<String>, api_key: Option<String>) -> reqwest::header::HeaderMap {
+    let auth = auth_header
+        .filter(|v| !v.trim().is_empty())
+        .map(|v| v.trim().to_string())
+        .or_else(|| {
+            api_key
+                .filter(|v| !v.trim().is_empty())
+                .map(|key| format!("Bearer {}", key.trim()))
+        });
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(auth) = auth {
+        match reqwest::header::HeaderValue::from_str(&auth) {
+            Ok(mut value) => {
+                // Keeps the credential out of Debug output
+                value.set_sensitive(true);
+                headers.insert(reqwest::header::AUTHORIZATION, value);
+            }
+            Err(_) => leptos::logging::warn!("Ignoring Ollama auth header: value contains invalid characters"),
+        }
+    }
+    headers
+}
+
+/// Lines of a newline-delimited JSON body from Ollama. Framed by line so an
+/// object split across network chunks still arrives as one line.
+#[cfg(feature = "ssr")]
+pub fn ndjson_lines<S, B, E>(body: S) -> impl futures::Stream<Item = Result<String, tokio_util::codec::LinesCodecError>>
+where
+    S: futures::Stream<Item = Result<B, E>>,
+    B: tokio_util::bytes::Buf,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    use futures::StreamExt;
+    use tokio_util::codec::{FramedRead, LinesCodec};
+    use tokio_util::io::StreamReader;
+
+    let body = body.map(|res| res.map_err(std::io::Error::other));
+    FramedRead::new(StreamReader::new(body), LinesCodec::new())
+}
+
+// Models directory picked in the settings, passed as `OLLAMA_MODELS` to the
+// `ollama serve` this server starts; none means whatever it was started with
+#[cfg(feature = "ssr")]
+static MODELS_DIR_OVERRIDE: Mutex<Option<std::path::PathBuf>> = Mutex::new(None);
+
+#[cfg(feature = "ssr")]
+pub(crate) fn set_models_dir_override(dir: Option<std::path::PathBuf>) {
+    *MODELS_DIR_OVERRIDE.lock().unwrap() = dir;
+}
+
+#[cfg(feature = "ssr")]
+pub(crate) fn models_dir_override() -> Option<std::path::PathBuf> {
+    MODELS_DIR_OVERRIDE.lock().unwrap().clone()
+}
+
+/// Directory Ollama stores models in: the one picked in the settings, else
+/// `OLLAMA_MODELS`, else `~/.ollama/models`, else the system service's
+/// `/usr/share/ollama/.ollama/models`
+#[cfg(feature = "ssr")]
+pub fn ollama_models_dir() -> std::path::PathBuf {
+    if let Some(dir) = models_dir_override() {
+        return dir;
+    }
+    if let Ok(dir) = std::env::var("OLLAMA_MODELS") {
+        if !dir.trim().is_empty() {
+            return std::path::PathBuf::from(dir.trim());
+        }
+    }
+    let user_dir = std::env::var("HOME")
+        .map(|home| std::path::Path::new(&home).join(".ollama/models"))
+        .unwrap_or_default();
+    let service_dir = std::path::PathBuf::from("/usr/share/ollama/.ollama/models");
+    if !user_dir.exists() && service_dir.exists() {
+        service_dir
+    } else {
+        user_dir
+    }
+}
+
+/// `(digest, size)` of each blob a model manifest references
+#[cfg(feature = "ssr")]
+pub(crate) type ManifestLayers = Vec<(String, u64)>;
+
+/// Model name and layers of every manifest under
+/// `<models>/manifests/<host>/<namespace>/<model>/<tag>`, plus how many entries
+/// were skipped because they couldn't be read or parsed. Only failing to read
+/// the top-level manifests directory is an error.
+#[cfg(feature = "ssr")]
+pub(crate) fn read_model_manifests(models_dir: &std::path::Path) -> std::io::Result<(Vec<(String, ManifestLayers)>, usize)> {
+    fn walk(dir: &std::path::Path, files: &mut Vec<std::path::PathBuf>, skipped: &mut usize) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            *skipped += 1;
+            return;
+        };
+        for entry in entries {
+            let Ok(entry) = entry else {
+                *skipped += 1;
+                continue;
+            };
+            let path = entry.path();
+            if path.is_dir() {
+                walk(&path, files, skipped);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    let manifests_dir = models_dir.join("manifests");
+    std::fs::read_dir(&manifests_dir)?;
+    let mut files = Vec::new();
+    let mut skipped = 0;
+    walk(&manifests_dir, &mut files, &mut skipped);
+
+    let mut manifests = Vec::new();
+    for file in files {
+        let Ok(relative) = file.strip_prefix(&manifests_dir) else { continue };
+        let parts: Vec<String> = relative.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+        let [host, namespace, model, tag] = parts.as_slice() else { continue };
+        // Mirror the names `ollama list` shows
+        let name = match (host.as_str(), namespace.as_str()) {
+            ("registry.ollama.ai", "library") => format!("{}:{}", model, tag),
+            ("registry.ollama.ai", _) => format!("{}/{}:{}", namespace, model, tag),
+            _ => format!("{}/{}/{}:{}", host, namespace, model, tag),
+        };
+        let Ok(manifest) = std::fs::read_to_string(&file)
+            .map_err(|_| ())
+            .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).map_err(|_| ()))
+        else {
+            skipped += 1;
+            continue;
+        };
+        let mut layers: ManifestLayers = manifest["layers"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .chain(std::iter::once(&manifest["config"]))
+            .filter_map(|layer| Some((layer["digest"].as_str()?.to_string(), layer["size"].as_u64()?)))
+            .collect();
+        layers.sort();
+        layers.dedup();
+        if layers.is_empty() {
+            skipped += 1;
+            continue;
+        }
+        manifests.push((name, layers));
+    }
+    Ok((manifests, skipped))
+}
+
+/// A model name passed to a model operation, trimmed; `None` if it's blank
+pub fn model_name_arg(name: &str) -> Option<&str> {
+    let name = name.trim();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Whether `requested` names the installed model `installed`; like Ollama, a
+/// name without a tag means `:latest`
+pub fn same_model(requested: &str, installed: &str) -> bool {
+    requested == installed || (!requested.contains(':') && installed.strip_suffix(":latest") == Some(requested))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StatusResponse {
+    pub running: bool,
+    pub models: Vec<String>,
+    /// Size on disk in bytes, by model name
+    #[serde(default)]
+    pub sizes: HashMap<String, u64>,
+}
+
+/// Installed model details as reported by Ollama's `/api/tags`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelInfo {
+    pub name: String,
+    pub size: u64,
+    pub digest: String,
+    pub modified_at: String,
+    pub family: Option<String>,
+    pub parameter_size: Option<String>,
+    pub quantization_level: Option<String>,
+}
+
+/// List installed models via `/api/tags` of the Ollama at `base_url`.
+/// Returns `None` when Ollama isn't reachable.
+#[cfg(feature = "ssr")]
+pub async fn fetch_installed_models(base_url: &str) -> Option<Vec<ModelInfo>> {
+    let client = ollama_client();
+    let response = client.get(format!("{}{}", base_url, crate::routes::ollama::TAGS)).send().await.ok()?;
+
+    let models = match response.json::<serde_json::Value>().await {
+        Ok(json) => installed_models_from_tags(&json),
+        Err(_) => vec![],
+    };
+
+    Some(models)
+}
+
+/// Models listed in an `/api/tags` response; entries without a usable name are skipped
+#[cfg(feature = "ssr")]
+fn installed_models_from_tags(json: &serde_json::Value) -> Vec<ModelInfo> {
+    json["models"]
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|m| {
+                    let details = &m["details"];
+                    // Newer Ollama versions report the tag under `model` as well as
+                    // (or instead of) `name`, so accept either, whichever isn't blank
+                    let name = [&m["name"], &m["model"]]
+                        .into_iter()
+                        .filter_map(|v| v.as_str())
+                        .map(str::trim)
+                        .find(|s| !s.is_empty())?;
+                    Some(ModelInfo {
+                        name: name.to_string(),
+                        size: m["size"].as_u64().unwrap_or(0),
+                        digest: m["digest"].as_str().unwrap_or("").to_string(),
+                        modified_at: m["modified_at"].as_str().unwrap_or("").to_string(),
+                        family: details["family"].as_str().map(|s| s.to_string()),
+                        parameter_size: details["parameter_size"].as_str().map(|s| s.to_string()),
+                        quantization_level: details["quantization_level"].as_str().map(|s| s.to_string()),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[server]
+pub async fn get_ollama_status() -> Result<StatusResponse, ServerFnError> {
+    // Check if Ollama is running by hitting the tags endpoint
+    match fetch_installed_models(&ollama_base_url()).await {
+        Some(models) => Ok(StatusResponse {
+            running: true,
+            sizes: models.iter().map(|m| (m.name.clone(), m.size)).collect(),
+            models: models.into_iter().map(|m| m.name).collect(),
+        }),
+        None => Ok(StatusResponse { running: false, models: vec![], sizes: HashMap::new() }),
+    }
+}
+
+/// Remove a model with `/api/delete`. Failures carry Ollama's own explanation.
+#[server]
+pub async fn delete_model(model_name: String) -> Result<bool, ServerFnError> {
+    remove_model(&ollama_base_url(), &model_name).await
+}
+
+/// [`delete_model`] on the Ollama at `base_url`
+#[cfg(feature = "ssr")]
+async fn remove_model(base_url: &str, model_name: &str) -> Result<bool, ServerFnError> {
+    let Some(model) = model_name_arg(model_name) else {
+        return Ok(false);
+    };
+
+    let response = ollama_client()
+        .delete(format!("{}{}", base_url, crate::routes::ollama::DELETE))
+        .json(&serde_json::json!({ "model": model }))
+        .send()
+        .await
+        .map_err(|e| ServerFnError::new(format!("Could not reach Ollama: {}", e)))?;
+
+    if response.status().is_success() {
+        forget_model_details(model);
+        return Ok(true);
+    }
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    let reason = serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|json| json["error"].as_str().map(str::to_string))
+        .unwrap_or_else(|| text.trim().to_string());
+    Err(ServerFnError::new(if reason.is_empty() { format!("Ollama answered the delete with HTTP {}", status) } else { reason }))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PullProgress {
+    pub model: String,
+    pub status: String,
+    pub percent: f32,
+    pub done: bool,
+    pub error: Option<String>,
+    pub bytes_downloaded: u64,
+    /// Size of everything the pull has announced so far, across all layers
+    #[serde(default)]
+    pub total_bytes: u64,
+    pub speed: String,
+    pub last_update: i64, // timestamp for speed calculation
+    /// Ollama's own status line behind `status`, e.g. "pulling sha256:…", for debugging
+    #[serde(default)]
+    pub raw_status: String,
+}
+
+/// Ollama pull status prefixes and the phase shown for them, checked in order.
+/// Layer downloads are reported as "pulling <digest>" (older versions: "downloading <digest>").
+#[cfg(feature = "ssr")]
+const PULL_PHASES: [(&str, &str); 7] = [
+    ("pulling manifest", "Fetching manifest"),
+    ("pulling ", "Downloading"),
+    ("downloading", "Downloading"),
+    ("verifying", "Verifying"),
+    ("writing manifest", "Finalizing"),
+    ("removing", "Finalizing"),
+    ("success", "Complete"),
+];
+
+/// Friendly phase for an Ollama pull status; statuses not in [`PULL_PHASES`] are shown as sent
+#[cfg(feature = "ssr")]
+fn pull_phase(status: &str) -> String {
+    PULL_PHASES
+        .iter()
+        .find(|(prefix, _)| status.starts_with(prefix))
+        .map_or_else(|| status.to_string(), |(_, phase)| phase.to_string())
+}
+
+// Progress of every pull (and model creation) by model name, for polling
+#[cfg(feature = "ssr")]
+static PULL_PROGRESS: OnceLock<Mutex<HashMap<String, PullProgress>>> = OnceLock::new();
+
+#[cfg(feature = "ssr")]
+pub(crate) fn get_progress_store() -> &'static Mutex<HashMap<String, PullProgress>> {
+    PULL_PROGRESS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Running pull tasks by model, so a pull can be paused or cancelled mid-download
+#[cfg(feature = "ssr")]
+static PULL_TASKS: OnceLock<Mutex<HashMap<String, tokio::task::AbortHandle>>> = OnceLock::new();
+
+#[cfg(feature = "ssr")]
+fn pull_tasks() -> &'static Mutex<HashMap<String, tokio::task::AbortHandle>> {
+    PULL_TASKS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Stop the pull task for `model`, if there is one. Dropping the request makes Ollama
+/// stop downloading; layers it already has stay on disk for the next pull.
+#[cfg(feature = "ssr")]
+pub(crate) fn abort_pull_task(model: &str) {
+    if let Some(task) = pull_tasks().lock().unwrap().remove(model) {
+        task.abort();
+    }
+    pull_watched().lock().unwrap().remove(model);
+}
+
+/// Run `pull` on its own task as `model`'s pull, so it can be paused or cancelled.
+/// The task forgets itself when it finishes, unless a newer pull has taken its place.
+#[cfg(feature = "ssr")]
+pub(crate) fn spawn_pull_task<F>(model: String, pull: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    // Held until the handle is stored, so a pull that finishes at once still finds it
+    let mut tasks = pull_tasks().lock().unwrap();
+    let key = model.clone();
+    let task = tokio::spawn(async move {
+        pull.await;
+        let mut tasks = pull_tasks().lock().unwrap();
+        if tasks.get(&key).is_some_and(|task| task.id() == tokio::task::id()) {
+            tasks.remove(&key);
+            pull_watched().lock().unwrap().remove(&key);
+        }
+    });
+    tasks.insert(model, task.abort_handle());
+}
+
+/// Layers a pull has announced, for cleaning up after it's cancelled
+#[cfg(feature = "ssr")]
+struct PullLayers {
+    /// The model was installed when the pull started (an update); nothing is cleaned up then
+    previously_installed: bool,
+    digests: std::collections::HashSet<String>,
+}
+
+// Announced layers of each pull by model, kept until it succeeds or is cleaned up
+#[cfg(feature = "ssr")]
+static PULL_LAYERS: OnceLock<Mutex<HashMap<String, PullLayers>>> = OnceLock::new();
+
+#[cfg(feature = "ssr")]
+fn pull_layers() -> &'static Mutex<HashMap<String, PullLayers>> {
+    PULL_LAYERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// When the page last asked about each running pull; a pull nobody is following is stopped
+#[cfg(feature = "ssr")]
+static PULL_WATCHED: OnceLock<Mutex<HashMap<String, std::time::Instant>>> = OnceLock::new();
+
+#[cfg(feature = "ssr")]
+fn pull_watched() -> &'static Mutex<HashMap<String, std::time::Instant>> {
+    PULL_WATCHED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// When a pull gives up on the page that started it: once it has gone
+/// `unwatched_timeout` without polling for progress, checked at least every
+/// `check_every` even while Ollama sends nothing
+#[cfg(feature = "ssr")]
+#[derive(Clone, Copy, Debug)]
+struct PullWatch {
+    unwatched_timeout: std::time::Duration,
+    check_every: std::time::Duration,
+}
+
+/// Generous because browsers throttle timers in background tabs to about once a minute
+#[cfg(feature = "ssr")]
+const PULL_WATCH: PullWatch = PullWatch {
+    unwatched_timeout: std::time::Duration::from_secs(180),
+    check_every: std::time::Duration::from_secs(10),
+};
+
+/// Start following `model`'s pull; it's forgotten again when the pull task ends or is stopped
+#[cfg(feature = "ssr")]
+pub(crate) fn mark_pull_watched(model: &str) {
+    pull_watched().lock().unwrap().insert(model.to_string(), std::time::Instant::now());
+}
+
+/// Note that the page still follows `model`'s pull, if one is running
+#[cfg(feature = "ssr")]
+fn refresh_pull_watched(model: &str) {
+    if let Some(seen) = pull_watched().lock().unwrap().get_mut(model) {
+        *seen = std::time::Instant::now();
+    }
+}
+
+/// Whether the page that started `model`'s pull has gone away (closed, navigated
+/// off, or lost its connection), having not asked about it for `timeout`
+#[cfg(feature = "ssr")]
+fn pull_abandoned(model: &str, timeout: std::time::Duration) -> bool {
+    pull_watched()
+        .lock()
+        .unwrap()
+        .get(model)
+        .is_some_and(|seen| seen.elapsed() > timeout)
+}
+
+#[server]
+pub async fn start_model_pull(model_name: String) -> Result<PullProgress, ServerFnError> {
+    pull_model(ollama_base_url(), model_name, PULL_WATCH).await
+}
+
+/// [`start_model_pull`] from the Ollama at `base_url`, giving up on an unwatched
+/// pull as `watch` says
+#[cfg(feature = "ssr")]
+async fn pull_model(base_url: String, model_name: String, watch: PullWatch) -> Result<PullProgress, ServerFnError> {
+    let Some(model) = model_name_arg(&model_name) else {
+        return Ok(PullProgress {
+            model: model_name,
+            status: "Error".to_string(),
+            percent: 0.0,
+            done: true,
+            error: Some("Model name cannot be empty".to_string()),
+            bytes_downloaded: 0,
+            total_bytes: 0,
+            speed: "".to_string(),
+            last_update: 0,
+            raw_status: String::new(),
+        });
+    };
+
+    let model = model.to_string();
+    let model_clone = model.clone();
+
+    // First ensure Ollama is running
+    if fetch_installed_models(&base_url).await.is_none() {
+        if let Err(reason) = start_ollama_and_wait().await {
+            // Kept in the store so progress polling reports it too
+            let failed = PullProgress {
+                model: model.clone(),
+                status: "Error".to_string(),
+                percent: 0.0,
+                done: true,
+                error: Some(reason),
+                bytes_downloaded: 0,
+                total_bytes: 0,
+                speed: "".to_string(),
+                last_update: 0,
+                raw_status: String::new(),
+            };
+            get_progress_store().lock().unwrap().insert(model, failed.clone());
+            return Ok(failed);
+        }
+    }
+
+    // Resuming a paused pull (or pulling again) replaces any earlier task
+    abort_pull_task(&model);
+    mark_pull_watched(&model);
+
+    // Unknown counts as installed, so a failed lookup never leads to deleting anything
+    let previously_installed = fetch_installed_models(&base_url)
+        .await
+        .is_none_or(|installed| installed.iter().any(|m| same_model(&model, &m.name)));
+    pull_layers().lock().unwrap().entry(model.clone()).or_insert_with(|| PullLayers {
+        previously_installed,
+        digests: Default::default(),
+    });
+
+    // Initialize progress
+    {
+        let store = get_progress_store();
+        let mut map = store.lock().unwrap();
+        map.insert(model.clone(), PullProgress {
+            model: model.clone(),
+            status: "Starting...".to_string(),
+            percent: 0.0,
+            done: false,
+            error: None,
+            bytes_downloaded: 0,
+            total_bytes: 0,
+            speed: "".to_string(),
+            last_update: 0,
+            raw_status: String::new(),
+        });
+    }
+
+    // Start the pull using Ollama API (streams JSON progress)
+    let pull_url = format!("{}{}", base_url, crate::routes::ollama::PULL);
+    spawn_pull_task(model, async move {
+        let client = ollama_client();
+        let res = client.post(pull_url)
+            .json(&serde_json::json!({ "name": model_clone }))
+            .send()
+            .await;
+
+        match res {
+            Ok(response) => {
+                use futures::StreamExt;
+
+                // Completed and total bytes of each layer, as Ollama reports them one layer at a time
+                let mut layers: HashMap<String, (u64, u64)> = HashMap::new();
+                let mut lines = ndjson_lines(response.bytes_stream());
+                // Why the pull failed if the reply ends before `success`
+                let mut cut_off = "Ollama stopped answering before the download finished".to_string();
+
+                loop {
+                    // Wake up now and then even when Ollama is quiet, to notice an abandoned pull
+                    let line = match tokio::time::timeout(watch.check_every, lines.next()).await {
+                        Ok(Some(Ok(line))) => Some(line),
+                        Ok(Some(Err(e))) => {
+                            cut_off = format!("Lost the connection to Ollama: {}", e);
+                            break;
+                        }
+                        Ok(None) => break,
+                        Err(_) => None,
+                    };
+                    if pull_abandoned(&model_clone, watch.unwatched_timeout) {
+                        // Returning drops the request, which makes Ollama stop downloading
+                        leptos::logging::log!("Stopping pull of {}: no page is following it", model_clone);
+                        let store = get_progress_store();
+                        let mut map = store.lock().unwrap();
+                        if let Some(progress) = map.get_mut(&model_clone) {
+                            progress.status = "Cancelled".to_string();
+                            progress.done = true;
+                            progress.error = Some("Stopped because the page that started it was closed".to_string());
+                            progress.speed = String::new();
+                        }
+                        break;
+                    }
+                    let Some(line) = line else { continue };
+                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
+                        let store = get_progress_store();
+                        let mut map = store.lock().unwrap();
+
+                        let status_text = json["status"].as_str().unwrap_or("").to_string();
+                        let total = json["total"].as_u64().unwrap_or(0);
+                        let completed = json["completed"].as_u64().unwrap_or(0);
+
+                        // Get previous values to preserve if needed
+                        let prev = map.get(&model_clone).cloned();
+                        // Paused or cancelled while this line was on its way
+                        if prev.as_ref().is_some_and(|p| p.status == "Paused" || p.status == "Cancelled") {
+                            break;
+                        }
+                        let prev_speed = prev.as_ref().map(|p| p.speed.clone()).unwrap_or_default();
+                        let prev_percent = prev.as_ref().map(|p| p.percent).unwrap_or(0.0);
+
+                        if let Some(digest) = json["digest"].as_str() {
+                            if let Some(pull) = pull_layers().lock().unwrap().get_mut(&model_clone) {
+                                pull.digests.insert(digest.to_string());
+                            }
+                        }
+                        if total > 0 {
+                            let layer = json["digest"].as_str().unwrap_or(&status_text).to_string();
+                            layers.insert(layer, (completed.min(total), total));
+                        }
+                        let (all_completed, all_total) = layers
+                            .values()
+                            .fold((0, 0), |(done, size), (layer_done, layer_size)| (done + layer_done, size + layer_size));
+
+                        let percent = if all_total > 0 {
+                            (all_completed as f32 / all_total as f32) * 100.0
+                        } else {
+                            prev_percent // Keep previous percent if no new data
+                        };
+
+                        // Calculate speed from completed bytes, keep previous if no new data
+                        let speed = if all_total > 0 && all_completed > 0 {
+                            format_bytes(all_completed) + " / " + &format_bytes(all_total)
+                        } else if !prev_speed.is_empty() {
+                            prev_speed // Keep previous speed
+                        } else {
+                            "".to_string()
+                        };
+
+                        let is_done = status_text == "success" || json.get("error").is_some();
+                        let error = json["error"].as_str().map(|s| s.to_string());
+                        if is_done {
+                            let result = if error.is_none() { "success" } else { "error" };
+                            metrics::counter!("ollama_ui_pulls_total", "result" => result).increment(1);
+                            pull_layers().lock().unwrap().remove(&model_clone);
+                            if error.is_none() {
+                                forget_model_details(&model_clone);
+                            }
+                        }
+
+                        map.insert(model_clone.clone(), PullProgress {
+                            model: model_clone.clone(),
+                            status: if is_done && error.is_none() { "Complete".to_string() } else { pull_phase(&status_text) },
+                            percent: if is_done && error.is_none() { 100.0 } else { percent },
+                            done: is_done,
+                            error,
+                            bytes_downloaded: all_completed,
+                            total_bytes: all_total,
+                            speed,
+                            last_update: std::time::SystemTime::now()
+                                .duration_since(std::time::UNIX_EPOCH)
+                                .unwrap_or_default()
+                                .as_secs() as i64,
+                            raw_status: status_text,
+                        });
+                    }
+                }
+
+                // Not finished, paused or cancelled: the pull can't go on, so say so
+                // rather than leave it looking mid-download
+                let store = get_progress_store();
+                let mut map = store.lock().unwrap();
+                if let Some(progress) = map.get_mut(&model_clone).filter(|p| !p.done && p.status != "Paused") {
+                    progress.status = "Error".to_string();
+                    progress.done = true;
+                    progress.error = Some(cut_off);
+                    progress.speed = String::new();
+                    metrics::counter!("ollama_ui_pulls_total", "result" => "error").increment(1);
+                    pull_layers().lock().unwrap().remove(&model_clone);
+                }
+            }
+            Err(e) => {
+                pull_layers().lock().unwrap().remove(&model_clone);
+                let store = get_progress_store();
+                let mut map = store.lock().unwrap();
+                map.insert(model_clone.clone(), PullProgress {
+                    model: model_clone,
+                    status: "Error".to_string(),
+                    percent: 0.0,
+                    done: true,
+                    error: Some(e.to_string()),
+                    bytes_downloaded: 0,
+                    total_bytes: 0,
+                    speed: "".to_string(),
+                    last_update: 0,
+                    raw_status: String::new(),
+                });
+            }
+        }
+    });
+
+    Ok(PullProgress {
+        model: model_name.trim().to_string(),
+        status: "Starting...".to_string(),
+        percent: 0.0,
+        done: false,
+        error: None,
+        bytes_downloaded: 0,
+        total_bytes: 0,
+        speed: "".to_string(),
+        last_update: 0,
+        raw_status: String::new(),
+    })
+}
+
+/// Re-pull `model_name` to pick up layers that changed upstream, like
+/// [`start_model_pull`] but only for a model that's already installed. Not held
+/// to the pull rate limit, so "update all" can refresh every installed model.
+#[server]
+pub async fn start_model_update(model_name: String) -> Result<PullProgress, ServerFnError> {
+    let model = model_name.trim();
+    let installed = fetch_installed_models(&ollama_base_url())
+        .await
+        .ok_or_else(|| ServerFnError::new("Ollama not reachable"))?;
+    if !installed.iter().any(|m| same_model(model, &m.name)) {
+        return Err(ServerFnError::new(format!("{} is not installed", model)));
+    }
+    start_model_pull(model_name).await
+}
+
+#[server]
+pub async fn cancel_model_pull(model_name: String) -> Result<bool, ServerFnError> {
+    use std::process::Command;
+
+    let model = model_name.trim().to_string();
+
+    // Mark as cancelled in progress store
+    {
+        let store = get_progress_store();
+        let mut map = store.lock().unwrap();
+        if let Some(progress) = map.get_mut(&model) {
+            progress.done = true;
+            progress.status = "Cancelled".to_string();
+            progress.error = Some("Download cancelled by user".to_string());
+        }
+    }
+    abort_pull_task(&model);
+
+    // Kill any running ollama pull process for this model
+    let _ = Command::new("pkill")
+        .args(["-f", &format!("ollama pull {}", model)])
+        .output();
+
+    Ok(true)
+}
+
+/// Delete what a cancelled pull of a model that wasn't installed before left in the
+/// blobs directory: partial downloads of its layers, and finished layers that no
+/// installed model or other pull uses. Returns the bytes freed. Ollama resumes from
+/// these files, so this is only done when asked for.
+#[server]
+pub async fn remove_cancelled_pull(model_name: String) -> Result<u64, ServerFnError> {
+    let model = model_name.trim().to_string();
+    let cancelled = get_progress_store()
+        .lock()
+        .unwrap()
+        .get(&model)
+        .is_some_and(|p| p.status == "Cancelled");
+    if !cancelled {
+        return Err(ServerFnError::new("Only a cancelled download can be cleaned up"));
+    }
+
+    let dir = ollama_models_dir();
+    // Everything installed models use stays, so give up if that can't be known
+    let installed: std::collections::HashSet<String> = match read_model_manifests(&dir) {
+        Ok((manifests, 0)) => manifests.into_iter().flat_map(|(_, layers)| layers).map(|(digest, _)| digest).collect(),
+        Ok(_) => return Err(ServerFnError::new("Some model manifests couldn't be read, so nothing was removed")),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Default::default(),
+        Err(e) => return Err(ServerFnError::new(format!("Could not read the models directory: {}", e))),
+    };
+
+    let (pull, in_other_pulls) = {
+        let mut pulls = pull_layers().lock().unwrap();
+        let Some(pull) = pulls.remove(&model) else { return Ok(0) };
+        let others: std::collections::HashSet<String> = pulls.values().flat_map(|p| p.digests.iter().cloned()).collect();
+        (pull, others)
+    };
+    if pull.previously_installed {
+        return Ok(0);
+    }
+
+    let Ok(entries) = std::fs::read_dir(dir.join("blobs")) else { return Ok(0) };
+    let names: Vec<String> = pull
+        .digests
+        .iter()
+        .filter(|digest| !installed.contains(*digest) && !in_other_pulls.contains(*digest))
+        .map(|digest| digest.replace(':', "-"))
+        .collect();
+    let mut freed = 0;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        // `<blob>` once finished, `<blob>-partial` and `<blob>-partial-N` while downloading
+        let ours = names.iter().any(|name| {
+            file_name == *name || file_name.strip_prefix(name.as_str()).is_some_and(|rest| rest.starts_with("-partial"))
+        });
+        if !ours {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if std::fs::remove_file(entry.path()).is_ok() {
+            freed += size;
+        }
+    }
+    Ok(freed)
+}
+
+/// Stop a pull but keep its progress, marked "Paused". Resuming is pulling again
+/// with `start_model_pull`; Ollama picks up from the layers it already has.
+#[server]
+pub async fn pause_model_pull(model_name: String) -> Result<bool, ServerFnError> {
+    let model = model_name.trim().to_string();
+
+    let store = get_progress_store();
+    let mut map = store.lock().unwrap();
+    let Some(progress) = map.get_mut(&model).filter(|p| !p.done) else {
+        return Ok(false);
+    };
+    progress.status = "Paused".to_string();
+    progress.speed = String::new();
+    abort_pull_task(&model);
+    Ok(true)
+}
+
+#[server]
+pub async fn check_pull_progress(model_name: String) -> Result<PullProgress, ServerFnError> {
+    let model = model_name.trim().to_string();
+    refresh_pull_watched(&model);
+
+    // Check progress store first
+    {
+        let store = get_progress_store();
+        let map = store.lock().unwrap();
+        if let Some(progress) = map.get(&model) {
+            return Ok(progress.clone());
+        }
+    }
+
+    // Fallback: check if model exists (might have been pulled before tracking)
+    let status = get_ollama_status().await?;
+    let model_exists = status.models.iter().any(|m| same_model(&model, m));
+
+    if model_exists {
+        Ok(PullProgress {
+            model,
+            status: "Complete".to_string(),
+            percent: 100.0,
+            done: true,
+            error: None,
+            bytes_downloaded: 0,
+            total_bytes: 0,
+            speed: "".to_string(),
+            last_update: 0,
+            raw_status: String::new(),
+        })
+    } else {
+        Ok(PullProgress {
+            model,
+            status: "Waiting...".to_string(),
+            percent: 0.0,
+            done: false,
+            error: None,
+            bytes_downloaded: 0,
+            total_bytes: 0,
+            speed: "".to_string(),
+            last_update: 0,
+            raw_status: String::new(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BenchmarkRun {
+    pub tokens_per_sec: f64,
+    /// Model load plus prompt evaluation, i.e. time until the first token
+    pub first_token_ms: f64,
+    pub eval_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BenchmarkStat {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+#[cfg(feature = "ssr")]
+impl BenchmarkStat {
+    fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        Some(BenchmarkStat {
+            min: values.iter().cloned().fold(f64::INFINITY, f64::min),
+            avg: values.iter().sum::<f64>() / values.len() as f64,
+            max: values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BenchmarkResult {
+    pub model: String,
+    pub runs: Vec<BenchmarkRun>,
+    pub tokens_per_sec: Option<BenchmarkStat>,
+    pub first_token_ms: Option<BenchmarkStat>,
+    /// Set when a run failed; `runs` still holds the ones that completed
+    pub error: Option<String>,
+}
+
+/// Per-model details from Ollama's `/api/show`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelDetails {
+    /// Context window: an explicit `num_ctx` parameter, else the trained context length
+    pub context_size: Option<u64>,
+    /// e.g. "completion", "vision", "tools", "thinking", "embedding"
+    pub capabilities: Vec<String>,
+}
+
+/// Strip one pair of `"""` or `"` quotes from a Modelfile argument
+fn modelfile_unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix("\"\"\"")
+        .and_then(|v| v.strip_suffix("\"\"\""))
+        .or_else(|| value.strip_prefix('"').and_then(|v| v.strip_suffix('"')))
+        .unwrap_or(value)
+}
+
+/// `/api/create` fields for a Modelfile: `from`, `system`, `template`, `license`,
+/// `parameters` and `messages`. Fails without a `FROM` line, or on an instruction
+/// that can't be sent this way (`ADAPTER` needs its file uploaded to Ollama first).
+pub fn modelfile_create_fields(modelfile: &str) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let mut fields = serde_json::Map::new();
+    let mut parameters = serde_json::Map::new();
+    let mut messages = Vec::new();
+    let mut lines = modelfile.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (instruction, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let mut rest = rest.trim().to_string();
+        // A """ block runs on until the line that closes it
+        if rest.matches("\"\"\"").count() == 1 {
+            loop {
+                let Some((_, next)) = lines.next() else {
+                    return Err(format!("Line {}: \"\"\" is never closed", index + 1));
+                };
+                rest.push('\n');
+                rest.push_str(next);
+                if next.contains("\"\"\"") {
+                    break;
+                }
+            }
+        }
+        match instruction.to_ascii_uppercase().as_str() {
+            "FROM" | "SYSTEM" | "TEMPLATE" | "LICENSE" => {
+                fields.insert(instruction.to_ascii_lowercase(), modelfile_unquote(&rest).into());
+            }
+            "PARAMETER" => {
+                let Some((key, value)) = rest.split_once(char::is_whitespace) else {
+                    return Err(format!("Line {}: PARAMETER needs a name and a value", index + 1));
+                };
+                let value = modelfile_unquote(value);
+                if key == "stop" {
+                    let stops = parameters.entry("stop").or_insert_with(|| serde_json::Value::Array(Vec::new()));
+                    if let Some(stops) = stops.as_array_mut() {
+                        stops.push(value.into());
+                    }
+                } else {
+                    let value = value
+                        .parse::<i64>()
+                        .map(serde_json::Value::from)
+                        .or_else(|_| value.parse::<f64>().map(serde_json::Value::from))
+                        .or_else(|_| value.parse::<bool>().map(serde_json::Value::from))
+                        .unwrap_or_else(|_| value.into());
+                    parameters.insert(key.to_string(), value);
+                }
+            }
+            "MESSAGE" => {
+                let Some((role, content)) = rest.split_once(char::is_whitespace) else {
+                    return Err(format!("Line {}: MESSAGE needs a role and a message", index + 1));
+                };
+                messages.push(serde_json::json!({ "role": role, "content": modelfile_unquote(content) }));
+            }
+            "ADAPTER" => return Err(format!("Line {}: ADAPTER isn't supported here; use `ollama create`", index + 1)),
+            other => return Err(format!("Line {}: unknown instruction {}", index + 1, other)),
+        }
+    }
+    if fields.get("from").and_then(|from| from.as_str()).is_none_or(str::is_empty) {
+        return Err("The Modelfile needs a FROM line naming the model to start from".to_string());
+    }
+    if !parameters.is_empty() {
+        fields.insert("parameters".to_string(), serde_json::Value::Object(parameters));
+    }
+    if !messages.is_empty() {
+        fields.insert("messages".to_string(), serde_json::Value::Array(messages));
+    }
+    Ok(fields)
+}
+
+/// A model's Modelfile from `/api/show`, ready to edit and build a new model from:
+/// a `FROM` pointing at a blob on disk is replaced by the model's own name
+#[server]
+pub async fn get_modelfile(model_name: String) -> Result<String, ServerFnError> {
+    let model = model_name.trim().to_string();
+    let response = ollama_client()
+        .post(ollama_url(crate::routes::ollama::SHOW))
+        .json(&serde_json::json!({ "model": model }))
+        .send()
+        .await
+        .map_err(|_| ServerFnError::new("Ollama not reachable"))?;
+    if !response.status().is_success() {
+        return Err(ServerFnError::new(format!("Could not read the Modelfile of {}", model)));
+    }
+    let json = response.json::<serde_json::Value>().await?;
+    Ok(editable_modelfile(json["modelfile"].as_str().unwrap_or_default(), &model))
+}
+
+/// `modelfile` as shown by `/api/show` for `model`, with a `FROM` naming a blob
+/// path swapped for the model's name so it can be built from again
+#[cfg(feature = "ssr")]
+fn editable_modelfile(modelfile: &str, model: &str) -> String {
+    modelfile
+        .lines()
+        .map(|line| match line.split_once(' ') {
+            Some((instruction, from)) if instruction.eq_ignore_ascii_case("FROM") && from.trim().starts_with('/') => {
+                format!("FROM {}", model)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Build `model_name` from a Modelfile with `/api/create`. Progress goes to the
+/// pull progress store, so it's followed (and can be cancelled) like a download.
+#[server]
+pub async fn create_model(model_name: String, modelfile: String) -> Result<PullProgress, ServerFnError> {
+    let model = model_name.trim().to_string();
+    if model.is_empty() || model.contains(char::is_whitespace) {
+        return Err(ServerFnError::new("Give the new model a name without spaces"));
+    }
+    let mut body = modelfile_create_fields(&modelfile).map_err(ServerFnError::new)?;
+    body.insert("model".to_string(), model.clone().into());
+    body.insert("stream".to_string(), true.into());
+    // Ollama versions from before the structured create API read this instead, and newer ones ignore it
+    body.insert("modelfile".to_string(), modelfile.into());
+    if !get_ollama_status().await?.running {
+        return Err(ServerFnError::new("Ollama is not running"));
+    }
+
+    abort_pull_task(&model);
+    mark_pull_watched(&model);
+    let starting = PullProgress {
+        model: model.clone(),
+        status: "Creating...".to_string(),
+        percent: 0.0,
+        done: false,
+        error: None,
+        bytes_downloaded: 0,
+        total_bytes: 0,
+        speed: "".to_string(),
+        last_update: 0,
+        raw_status: String::new(),
+    };
+    get_progress_store().lock().unwrap().insert(model.clone(), starting.clone());
+
+    let model_clone = model.clone();
+    spawn_pull_task(model, async move {
+        use futures::StreamExt;
+
+        let update = |status: String, done: bool, error: Option<String>| {
+            let store = get_progress_store();
+            let mut map = store.lock().unwrap();
+            if let Some(progress) = map.get_mut(&model_clone).filter(|p| p.status != "Cancelled") {
+                progress.percent = if done && error.is_none() { 100.0 } else { progress.percent };
+                progress.status = status;
+                progress.done = done;
+                progress.error = error;
+            }
+        };
+        let response = match ollama_client().post(ollama_url(crate::routes::ollama::CREATE)).json(&body).send().await {
+            Ok(response) => response,
+            Err(e) => return update("Error".to_string(), true, Some(e.to_string())),
+        };
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&text)
+                .ok()
+                .and_then(|json| json["error"].as_str().map(str::to_string))
+                .unwrap_or_else(|| format!("HTTP {}", status));
+            return update("Error".to_string(), true, Some(message));
+        }
+        let mut lines = ndjson_lines(response.bytes_stream());
+        while let Some(Ok(line)) = lines.next().await {
+            let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) else { continue };
+            if let Some(error) = json["error"].as_str() {
+                return update("Error".to_string(), true, Some(error.to_string()));
+            }
+            let status = json["status"].as_str().unwrap_or_default();
+            if status == "success" {
+                forget_model_details(&model_clone);
+                return update("Complete".to_string(), true, None);
+            }
+            update(status.to_string(), false, None);
+        }
+        update("Error".to_string(), true, Some("Ollama stopped answering before the model was created".to_string()));
+    });
+
+    Ok(starting)
+}
+
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+
+    if bytes >= GB {
+        format!("{:.1} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.1} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+// Per-model `/api/show` results, so it is only hit once per model until the model changes
+#[cfg(feature = "ssr")]
+static MODEL_DETAILS: OnceLock<Mutex<HashMap<String, ModelDetails>>> = OnceLock::new();
+
+#[cfg(feature = "ssr")]
+fn model_details() -> &'static Mutex<HashMap<String, ModelDetails>> {
+    MODEL_DETAILS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Drop what's cached about `model` (under any name for it), after it has been
+/// created, pulled again or removed, so the next lookup asks Ollama
+#[cfg(feature = "ssr")]
+pub(crate) fn forget_model_details(model: &str) {
+    model_details()
+        .lock()
+        .unwrap()
+        .retain(|cached, _| !same_model(cached, model) && !same_model(model, cached));
+}
+
+/// Look up (and cache) a model's context window and capabilities via `/api/show`
+/// of the Ollama at `base_url`
+#[cfg(feature = "ssr")]
+pub async fn fetch_model_details(base_url: &str, model: &str) -> Option<ModelDetails> {
+    let store = model_details();
+    if let Some(details) = store.lock().unwrap().get(model) {
+        return Some(details.clone());
+    }
+
+    let response = ollama_client()
+        .post(format!("{}{}", base_url, crate::routes::ollama::SHOW))
+        .json(&serde_json::json!({ "model": model }))
+        .send()
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let json = response.json::<serde_json::Value>().await.ok()?;
+
+    let from_params = json["parameters"].as_str().and_then(|params| {
+        params.lines().find_map(|line| {
+            let mut parts = line.split_whitespace();
+            if parts.next()? == "num_ctx" {
+                parts.next()?.parse::<u64>().ok()
+            } else {
+                None
+            }
+        })
+    });
+    let from_info = json["model_info"].as_object().and_then(|info| {
+        info.iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+    });
+    let capabilities = json["capabilities"]
+        .as_array()
+        .map(|arr| arr.iter().filter_map(|c| c.as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default();
+
+    let details = ModelDetails {
+        context_size: from_params.or(from_info),
+        capabilities,
+    };
+    store.lock().unwrap().insert(model.to_string(), details.clone());
+    Some(details)
+}
+
+/// Context window `model` is actually running with, from `/api/ps` of the Ollama
+/// at `base_url` (newer Ollama versions only). This is often smaller than the
+/// trained context length.
+#[cfg(feature = "ssr")]
+pub async fn fetch_loaded_context_length(base_url: &str, model: &str) -> Option<u64> {
+    let json = ollama_client()
+        .get(format!("{}{}", base_url, crate::routes::ollama::PS))
+        .send()
+        .await
+        .ok()?
+        .json::<serde_json::Value>()
+        .await
+        .ok()?;
+    json["models"]
+        .as_array()?
+        .iter()
+        .find(|m| {
+            let loaded = |key: &str| m[key].as_str().is_some_and(|name| same_model(model, name));
+            loaded("name") || loaded("model")
+        })?["context_length"]
+        .as_u64()
+}
+
+/// Names of the models currently loaded in memory, via `/api/ps`.
+/// Returns `None` when Ollama isn't reachable.
+#[cfg(feature = "ssr")]
+pub async fn fetch_running_models() -> Option<Vec<String>> {
+    let json = ollama_client()
+        .get(ollama_url(crate::routes::ollama::PS))
+        .send()
+        .await
+        .ok()?
+        .json::<serde_json::Value>()
+        .await
+        .ok()?;
+
+    Some(
+        json["models"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|m| m["name"].as_str().or_else(|| m["model"].as_str()).map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    )
+}
+
+/// Load a model into memory ahead of time so the first chat doesn't pay the load
+/// latency. Resolves once the model shows up in `/api/ps`, or `false` if it doesn't
+/// within about 30 seconds. Fails with Ollama's error (or the HTTP status) if it
+/// won't load the model.
+#[server]
+pub async fn warm_up_model(model_name: String, keep_alive_secs: i64) -> Result<bool, ServerFnError> {
+    let model = model_name.trim().to_string();
+    if model.is_empty() {
+        return Ok(false);
+    }
+
+    // An empty prompt loads the model without generating anything
+    let res = ollama_client()
+        .post(ollama_url(crate::routes::ollama::GENERATE))
+        .json(&serde_json::json!({
+            "model": model,
+            "prompt": "",
+            "keep_alive": keep_alive_secs,
+            "stream": false
+        }))
+        .send()
+        .await;
+
+    let response = res.map_err(|_| ServerFnError::new("Ollama not reachable"))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&text)
+            .ok()
+            .and_then(|json| json["error"].as_str().map(str::to_string))
+            .unwrap_or_else(|| format!("HTTP {}", status));
+        return Err(ServerFnError::new(message));
+    }
+
+    // Wait (up to ~30s) for Ollama to report it as loaded
+    for _ in 0..30 {
+        if let Some(running) = fetch_running_models().await {
+            if running.iter().any(|m| same_model(&model, m)) {
+                return Ok(true);
+            }
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+    }
+
+    Ok(false)
+}
+
+/// Run a fixed prompt `runs` times (sequentially, non-streaming) and report
+/// tokens/sec and first-token latency from Ollama's timing stats
+#[server]
+pub async fn benchmark_model(model_name: String, prompt: String, runs: u32) -> Result<BenchmarkResult, ServerFnError> {
+    let model = model_name.trim().to_string();
+    let mut result = BenchmarkResult {
+        model: model.clone(),
+        runs: vec![],
+        tokens_per_sec: None,
+        first_token_ms: None,
+        error: None,
+    };
+    if model.is_empty() || prompt.trim().is_empty() {
+        result.error = Some("Model and prompt are required".to_string());
+        return Ok(result);
+    }
+
+    // One at a time so runs don't compete for the GPU
+    for run in 1..=runs.clamp(1, 10) {
+        let res = ollama_client()
+            .post(ollama_url(crate::routes::ollama::GENERATE))
+            .json(&serde_json::json!({
+                "model": model,
+                "prompt": prompt,
+                "stream": false
+            }))
+            .send()
+            .await;
+
+        let json = match res {
+            Ok(response) if response.status().is_success() => response.json::<serde_json::Value>().await.ok(),
+            Ok(response) => {
+                result.error = Some(format!("Run {} failed: HTTP {}", run, response.status()));
+                break;
+            }
+            Err(e) => {
+                result.error = Some(format!("Run {} failed: {}", run, e));
+                break;
+            }
+        };
+        let Some(json) = json else {
+            result.error = Some(format!("Run {} returned an unreadable response", run));
+            break;
+        };
+
+        let eval_count = json["eval_count"].as_u64().unwrap_or(0);
+        let eval_ns = json["eval_duration"].as_u64().unwrap_or(0);
+        let load_ns = json["load_duration"].as_u64().unwrap_or(0);
+        let prompt_eval_ns = json["prompt_eval_duration"].as_u64().unwrap_or(0);
+
+        result.runs.push(BenchmarkRun {
+            tokens_per_sec: if eval_ns > 0 { eval_count as f64 / (eval_ns as f64 / 1e9) } else { 0.0 },
+            first_token_ms: (load_ns + prompt_eval_ns) as f64 / 1e6,
+            eval_count,
+        });
+    }
+
+    let tps: Vec<f64> = result.runs.iter().map(|r| r.tokens_per_sec).collect();
+    let ftl: Vec<f64> = result.runs.iter().map(|r| r.first_token_ms).collect();
+    result.tokens_per_sec = BenchmarkStat::from_values(&tps);
+    result.first_token_ms = BenchmarkStat::from_values(&ftl);
+    Ok(result)
+}
+
+/// Capabilities for each of `models`, from the cached `/api/show` details.
+/// Models whose details couldn't be fetched are left out.
+#[server]
+pub async fn model_capabilities(models: Vec<String>) -> Result<HashMap<String, Vec<String>>, ServerFnError> {
+    let base_url = ollama_base_url();
+    let details = futures::future::join_all(models.iter().map(|model| fetch_model_details(&base_url, model))).await;
+    Ok(models
+        .into_iter()
+        .zip(details)
+        .filter_map(|(model, details)| details.map(|d| (model, d.capabilities)))
+        .collect())
+}
+
+/// Run `ollama serve` in the background and wait until it answers, up to 15 seconds.
+/// Fails with the reason when it exits early (its port taken by another program,
+/// say) or never answers on the configured address.
+#[cfg(feature = "ssr")]
+pub async fn start_ollama_and_wait() -> Result<(), String> {
+    use tokio::io::AsyncBufReadExt;
+
+    let mut command = tokio::process::Command::new("ollama");
+    command.arg("serve").stderr(std::process::Stdio::piped());
+    if let Some(dir) = models_dir_override() {
+        command.env("OLLAMA_MODELS", dir);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Could not run ollama serve: {}", e))?;
+
+    // Ollama logs to stderr for as long as it runs: pass it on to ours, and keep
+    // the last lines to explain an early exit
+    let recent_log = std::sync::Arc::new(Mutex::new(std::collections::VecDeque::new()));
+    if let Some(stderr) = child.stderr.take() {
+        let recent_log = recent_log.clone();
+        tokio::spawn(async move {
+            let mut lines = tokio::io::BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                eprintln!("{}", line);
+                let mut recent = recent_log.lock().unwrap();
+                recent.push_back(line);
+                if recent.len() > 20 {
+                    recent.pop_front();
+                }
+            }
+        });
+    }
+
+    for _ in 0..30 {
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        // Answering is what counts, even if it's an Ollama someone else started
+        if let Ok(response) = ollama_client().get(ollama_url(crate::routes::ollama::VERSION)).send().await {
+            if response.status().is_success() {
+                return Ok(());
+            }
+        }
+        if let Ok(Some(status)) = child.try_wait() {
+            // Give the log reader a moment to catch the last lines
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            let recent = recent_log.lock().unwrap();
+            if recent.iter().any(|line| line.contains("address already in use")) {
+                return Err(format!(
+                    "Ollama could not start: the port of {} is already in use by another program",
+                    ollama_base_url()
+                ));
+            }
+            let reason = recent
+                .iter()
+                .rev()
+                .find_map(|line| line.split_once("Error: ").map(|(_, reason)| reason.trim().to_string()))
+                .unwrap_or_else(|| format!("ollama serve exited ({})", status));
+            return Err(format!("Ollama could not start: {}", reason));
+        }
+    }
+    Err(format!("Ollama started but did not answer at {} within 15 seconds", ollama_base_url()))
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_header_is_sent_as_given() {
+        let headers = ollama_auth_headers(Some(" Basic dXNlcjpwYXNz ".to_string()), None);
+        let value = &headers[reqwest::header::AUTHORIZATION];
+        assert_eq!(value, "Basic dXNlcjpwYXNz");
+        assert!(value.is_sensitive());
+    }
+
+    #[test]
+    fn api_key_is_sent_as_a_bearer_token() {
+        let headers = ollama_auth_headers(None, Some("sk-123".to_string()));
+        let value = &headers[reqwest::header::AUTHORIZATION];
+        assert_eq!(value, "Bearer sk-123");
+        assert!(value.is_sensitive());
+    }
+
+    #[test]
+    fn auth_header_wins_over_api_key() {
+        let headers = ollama_auth_headers(Some("Token abc".to_string()), Some("sk-123".to_string()));
+        assert_eq!(headers[reqwest::header::AUTHORIZATION], "Token abc");
+        // A blank header doesn't hide the key
+        let headers = ollama_auth_headers(Some("  ".to_string()), Some("sk-123".to_string()));
+        assert_eq!(headers[reqwest::header::AUTHORIZATION], "Bearer sk-123");
+    }
+
+    #[test]
+    fn no_auth_header_without_credentials() {
+        assert!(ollama_auth_headers(None, None).is_empty());
+        assert!(ollama_auth_headers(Some(String::new()), Some(" ".to_string())).is_empty());
+        // Not a valid header value, so it's dropped rather than failing every request
+        assert!(ollama_auth_headers(Some("Bearer a\nb".to_string()), None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn pull_progress_split_across_chunks_is_one_line() {
+        use futures::StreamExt;
+
+        let chunks = [
+            &br#"{"status":"pulling sha256:a","digest":"sha256:a","#[..],
+            &br#""total":4000,"completed":1400}
+{"status":"verifying sha256 digest"}
+"#[..],
+        ];
+        let body = futures::stream::iter(chunks.map(|chunk| Ok::<_, std::io::Error>(tokio_util::bytes::Bytes::from_static(chunk))));
+        let lines: Vec<String> = ndjson_lines(body).map(|line| line.unwrap()).collect().await;
+        assert_eq!(lines.len(), 2);
+
+        let update: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(update["status"], "pulling sha256:a");
+        assert_eq!(update["digest"], "sha256:a");
+        assert_eq!(update["total"], 4000);
+        assert_eq!(update["completed"], 1400);
+        assert_eq!(pull_phase(update["status"].as_str().unwrap()), "Downloading");
+    }
+
+    #[test]
+    fn installed_models_from_a_tags_response() {
+        let json = serde_json::json!({
+            "models": [{
+                "name": "llama3:latest",
+                "model": "llama3:latest",
+                "modified_at": "2024-05-01T10:00:00Z",
+                "size": 4661224676u64,
+                "digest": "365c0bd3c000",
+                "details": {
+                    "format": "gguf",
+                    "family": "llama",
+                    "parameter_size": "8.0B",
+                    "quantization_level": "Q4_0"
+                }
+            }]
+        });
+        let models = installed_models_from_tags(&json);
+        assert_eq!(models.len(), 1);
+        let model = &models[0];
+        assert_eq!(model.name, "llama3:latest");
+        assert_eq!(model.size, 4661224676);
+        assert_eq!(model.digest, "365c0bd3c000");
+        assert_eq!(model.modified_at, "2024-05-01T10:00:00Z");
+        assert_eq!(model.family.as_deref(), Some("llama"));
+        assert_eq!(model.parameter_size.as_deref(), Some("8.0B"));
+        assert_eq!(model.quantization_level.as_deref(), Some("Q4_0"));
+    }
+
+    #[test]
+    fn tags_entries_without_a_name_use_model() {
+        let json = serde_json::json!({
+            "models": [
+                { "model": "mistral:7b", "size": 1 },
+                { "name": "  ", "model": "qwen2:0.5b", "size": 2 },
+                { "name": " phi3:mini ", "size": 3 },
+            ]
+        });
+        let names: Vec<String> = installed_models_from_tags(&json).into_iter().map(|m| m.name).collect();
+        assert_eq!(names, ["mistral:7b", "qwen2:0.5b", "phi3:mini"]);
+    }
+
+    #[test]
+    fn tags_entries_with_no_usable_name_are_skipped() {
+        let json = serde_json::json!({
+            "models": [
+                { "size": 1 },
+                { "name": "", "model": " ", "size": 2 },
+                { "name": 7, "size": 3 },
+                { "name": "gemma:2b", "size": 4 },
+            ]
+        });
+        let models = installed_models_from_tags(&json);
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].name, "gemma:2b");
+    }
+
+    #[test]
+    fn tags_entries_without_details_still_list() {
+        let json = serde_json::json!({ "models": [{ "name": "tinyllama:latest" }] });
+        let models = installed_models_from_tags(&json);
+        assert_eq!(models.len(), 1);
+        let model = &models[0];
+        assert_eq!(model.size, 0);
+        assert_eq!(model.digest, "");
+        assert!(model.family.is_none() && model.parameter_size.is_none() && model.quantization_level.is_none());
+    }
+
+    #[test]
+    fn no_models_in_an_empty_or_odd_tags_response() {
+        assert!(installed_models_from_tags(&serde_json::json!({ "models": [] })).is_empty());
+        assert!(installed_models_from_tags(&serde_json::json!({})).is_empty());
+        assert!(installed_models_from_tags(&serde_json::json!({ "models": "none" })).is_empty());
+    }
+
+    #[test]
+    fn a_pull_nobody_started_watching_is_not_abandoned() {
+        assert!(!pull_abandoned("never-watched-test:1b", std::time::Duration::ZERO));
+    }
+
+    /// One line of pull progress for the layer `sha256:a`
+    fn pull_line(completed: u64) -> String {
+        let line = serde_json::json!({ "status": "pulling sha256:a", "digest": "sha256:a", "total": 4000, "completed": completed });
+        format!("{}\n", line)
+    }
+
+    /// Model whose pull the stand-in Ollama never finishes
+    const ENDLESS_PULL: &str = "abandoned-test:1b";
+
+    /// Model whose pull the stand-in Ollama finishes with `success`
+    const COMPLETE_PULL: &str = "refreshed-test:latest";
+
+    /// Set once the stand-in Ollama's reply to the [`ENDLESS_PULL`] is dropped
+    static ENDLESS_PULL_DROPPED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+    struct EndlessPull;
+
+    impl Drop for EndlessPull {
+        fn drop(&mut self) {
+            ENDLESS_PULL_DROPPED.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    /// Base URL of a stand-in Ollama for this test process with nothing installed
+    /// and `loaded-test:latest` loaded with an 8192-token context. Only
+    /// `deletable-test:1b` can be deleted.
+    /// Its `/api/pull` sends two lines of progress and then ends the reply, except
+    /// for [`COMPLETE_PULL`], which then succeeds, and [`ENDLESS_PULL`], which gets
+    /// a line every 20 ms for as long as it's read.
+    fn mock_ollama() -> &'static str {
+        static MOCK: OnceLock<String> = OnceLock::new();
+        MOCK.get_or_init(|| {
+            use axum::routing::{delete, get, post};
+
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.set_nonblocking(true).unwrap();
+            let base_url = format!("http://{}", listener.local_addr().unwrap());
+            let app = axum::Router::new()
+                .route(crate::routes::ollama::TAGS, get(|| async { axum::Json(serde_json::json!({ "models": [] })) }))
+                .route(
+                    crate::routes::ollama::DELETE,
+                    delete(|axum::Json(body): axum::Json<serde_json::Value>| async move {
+                        if body["model"] == "deletable-test:1b" {
+                            return (axum::http::StatusCode::OK, String::new());
+                        }
+                        let error = serde_json::json!({ "error": format!("model '{}' not found", body["model"].as_str().unwrap_or_default()) });
+                        (axum::http::StatusCode::NOT_FOUND, error.to_string())
+                    }),
+                )
+                .route(
+                    crate::routes::ollama::PS,
+                    get(|| async {
+                        let loaded = serde_json::json!({ "name": "loaded-test:latest", "model": "loaded-test:latest", "context_length": 8192 });
+                        axum::Json(serde_json::json!({ "models": [loaded] }))
+                    }),
+                )
+                .route(
+                    crate::routes::ollama::PULL,
+                    post(|axum::Json(body): axum::Json<serde_json::Value>| async move {
+                        if body["name"] == COMPLETE_PULL {
+                            let success = serde_json::json!({ "status": "success" });
+                            return axum::body::Body::from(pull_line(4000) + &format!("{}\n", success));
+                        }
+                        if body["name"] != ENDLESS_PULL {
+                            return axum::body::Body::from(pull_line(1000) + &pull_line(2000));
+                        }
+                        let lines = futures::stream::unfold((EndlessPull, 0), |(guard, completed)| async move {
+                            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                            Some((Ok::<_, std::convert::Infallible>(pull_line(completed)), (guard, completed + 100)))
+                        });
+                        axum::body::Body::from_stream(lines)
+                    }),
+                );
+            // Its own runtime, since each test's runtime ends with the test
+            std::thread::spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+                runtime.block_on(async move {
+                    let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                    axum::serve(listener, app).await.unwrap();
+                });
+            });
+            base_url
+        })
+    }
+
+    /// `model`'s progress once `done` holds for it, polling for up to two seconds
+    async fn progress_when(model: &str, done: impl Fn(&PullProgress) -> bool) -> PullProgress {
+        for _ in 0..200 {
+            if let Some(progress) = get_progress_store().lock().unwrap().get(model).filter(|p| done(p)) {
+                return progress.clone();
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("{:?}", get_progress_store().lock().unwrap().get(model));
+    }
+
+    #[tokio::test]
+    async fn a_pull_whose_reply_ends_early_fails() {
+        let model = "cut-off-test:1b";
+        pull_model(mock_ollama().to_string(), model.to_string(), PULL_WATCH).await.unwrap();
+
+        let progress = progress_when(model, |p| p.done).await;
+        assert_eq!(progress.status, "Error");
+        assert_eq!(progress.error.as_deref(), Some("Ollama stopped answering before the download finished"));
+        assert_eq!(progress.bytes_downloaded, 2000);
+        assert!(!pull_layers().lock().unwrap().contains_key(model));
+        for _ in 0..100 {
+            if !pull_tasks().lock().unwrap().contains_key(model) && !pull_watched().lock().unwrap().contains_key(model) {
+                // Asking about a finished pull doesn't start following it again
+                check_pull_progress(model.to_string()).await.unwrap();
+                assert!(!pull_watched().lock().unwrap().contains_key(model));
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("the finished pull is still tracked");
+    }
+
+    #[tokio::test]
+    async fn deleting_goes_through_the_api_and_reports_ollama_s_reason() {
+        let stale = ModelDetails { context_size: Some(2048), capabilities: Vec::new() };
+        model_details().lock().unwrap().insert("deletable-test:1b".to_string(), stale);
+        let deleted = remove_model(mock_ollama(), "deletable-test:1b").await;
+        assert!(deleted.unwrap());
+        assert!(!model_details().lock().unwrap().contains_key("deletable-test:1b"));
+
+        let missing = remove_model(mock_ollama(), "missing-test:1b").await;
+        assert!(missing.unwrap_err().to_string().contains("model 'missing-test:1b' not found"));
+    }
+
+    #[tokio::test]
+    async fn a_loaded_model_s_context_is_found_without_its_latest_tag() {
+        for (model, expected) in [("loaded-test", Some(8192)), ("loaded-test:latest", Some(8192)), ("loaded-test:8b", None)] {
+            let context = fetch_loaded_context_length(mock_ollama(), model).await;
+            assert_eq!(context, expected, "{}", model);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_finished_pull_forgets_the_model_s_cached_details() {
+        let stale = ModelDetails { context_size: Some(2048), capabilities: vec!["completion".to_string()] };
+        for name in ["refreshed-test", COMPLETE_PULL, "unrelated-test:1b"] {
+            model_details().lock().unwrap().insert(name.to_string(), stale.clone());
+        }
+        pull_model(mock_ollama().to_string(), COMPLETE_PULL.to_string(), PULL_WATCH).await.unwrap();
+
+        assert_eq!(progress_when(COMPLETE_PULL, |p| p.done).await.status, "Complete");
+        let cached = model_details().lock().unwrap();
+        assert!(!cached.contains_key("refreshed-test") && !cached.contains_key(COMPLETE_PULL));
+        assert!(cached.contains_key("unrelated-test:1b"));
+    }
+
+    #[tokio::test]
+    async fn a_pull_is_abandoned_once_its_page_stops_polling() {
+        let watch = PullWatch {
+            unwatched_timeout: std::time::Duration::from_millis(150),
+            check_every: std::time::Duration::from_millis(10),
+        };
+        pull_model(mock_ollama().to_string(), ENDLESS_PULL.to_string(), watch).await.unwrap();
+
+        // The page keeps asking, so the pull carries on past the timeout
+        for _ in 0..4 {
+            tokio::time::sleep(std::time::Duration::from_millis(75)).await;
+            let progress = check_pull_progress(ENDLESS_PULL.to_string()).await.unwrap();
+            assert!(!progress.done, "{:?}", progress);
+        }
+        assert_eq!(progress_when(ENDLESS_PULL, |p| p.bytes_downloaded > 0).await.status, "Downloading");
+        assert!(!ENDLESS_PULL_DROPPED.load(std::sync::atomic::Ordering::SeqCst));
+
+        // Then it goes away
+        let progress = progress_when(ENDLESS_PULL, |p| p.done).await;
+        assert_eq!(progress.status, "Cancelled");
+        assert_eq!(progress.error.as_deref(), Some("Stopped because the page that started it was closed"));
+        for _ in 0..200 {
+            if ENDLESS_PULL_DROPPED.load(std::sync::atomic::Ordering::SeqCst) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("the pull request to Ollama is still open");
+    }
+
+    #[test]
+    fn the_models_dir_picked_in_settings_wins_until_cleared() {
+        let before = ollama_models_dir();
+        set_models_dir_override(Some(std::path::PathBuf::from("/srv/ollama-models")));
+        assert_eq!(ollama_models_dir(), std::path::PathBuf::from("/srv/ollama-models"));
+        set_models_dir_override(None);
+        assert_eq!(ollama_models_dir(), before);
+    }
+
+    #[test]
+    fn pull_statuses_map_to_phases() {
+        assert_eq!(pull_phase("pulling manifest"), "Fetching manifest");
+        assert_eq!(pull_phase("pulling 6a0746a1ec1a"), "Downloading");
+        assert_eq!(pull_phase("downloading sha256:6a0746a1ec1a"), "Downloading");
+        assert_eq!(pull_phase("verifying sha256 digest"), "Verifying");
+        assert_eq!(pull_phase("writing manifest"), "Finalizing");
+        assert_eq!(pull_phase("removing any unused layers"), "Finalizing");
+        assert_eq!(pull_phase("success"), "Complete");
+    }
+
+    #[test]
+    fn unknown_pull_statuses_are_shown_as_sent() {
+        assert_eq!(pull_phase("retrying in 5s"), "retrying in 5s");
+        assert_eq!(pull_phase(""), "");
+    }
+
+    #[test]
+    fn the_manifest_is_matched_before_layer_downloads() {
+        // "pulling " also prefixes "pulling manifest", so it has to come later
+        let position = |prefix| PULL_PHASES.iter().position(|(p, _)| *p == prefix).unwrap();
+        assert!(position("pulling manifest") < position("pulling "));
+    }
+
+    #[test]
+    fn ollama_host_gets_a_default_scheme_and_port() {
+        assert_eq!(base_url_from_host(""), "http://localhost:11434");
+        assert_eq!(base_url_from_host("  "), "http://localhost:11434");
+        assert_eq!(base_url_from_host("gpu-box"), "http://gpu-box:11434");
+        assert_eq!(base_url_from_host("0.0.0.0:8080"), "http://0.0.0.0:8080");
+        assert_eq!(base_url_from_host("https://ollama.example.com"), "https://ollama.example.com");
+        assert_eq!(base_url_from_host("https://ollama.example.com:8443/"), "https://ollama.example.com:8443");
+        assert_eq!(base_url_from_host("http://ollama.example.com"), "http://ollama.example.com");
+    }
+
+    #[test]
+    fn ollama_host_paths_are_kept() {
+        assert_eq!(base_url_from_host("http://proxy.example.com/ollama"), "http://proxy.example.com/ollama");
+        assert_eq!(base_url_from_host("https://proxy.example.com:8443/ollama/"), "https://proxy.example.com:8443/ollama");
+        assert_eq!(base_url_from_host("gpu-box/ollama"), "http://gpu-box:11434/ollama");
+    }
+
+    #[test]
+    fn ollama_host_can_be_an_ipv6_address() {
+        assert_eq!(base_url_from_host("[::1]"), "http://[::1]:11434");
+        assert_eq!(base_url_from_host("[::1]:8080"), "http://[::1]:8080");
+        assert_eq!(base_url_from_host("https://[fd00::2]"), "https://[fd00::2]");
+    }
+
+    #[test]
+    fn unusable_ollama_hosts_fall_back_to_the_default() {
+        assert_eq!(base_url_from_host("gpu-box:port"), "http://localhost:11434");
+        assert_eq!(base_url_from_host("http://"), "http://localhost:11434");
+    }
+
+    #[test]
+    fn blank_model_names_are_refused() {
+        assert_eq!(model_name_arg("  llama3:8b \n"), Some("llama3:8b"));
+        assert_eq!(model_name_arg(""), None);
+        assert_eq!(model_name_arg(" \t "), None);
+    }
+
+    #[test]
+    fn a_template_block_runs_over_several_lines() {
+        let fields = modelfile_create_fields("FROM llama3\nTEMPLATE \"\"\"{{ .System }}\n\n{{ .Prompt }}\"\"\"\nSYSTEM \"Be brief\"").unwrap();
+        assert_eq!(fields["from"], "llama3");
+        assert_eq!(fields["template"], "{{ .System }}\n\n{{ .Prompt }}");
+        assert_eq!(fields["system"], "Be brief");
+    }
+
+    #[test]
+    fn repeated_stop_parameters_are_all_kept() {
+        let fields = modelfile_create_fields("FROM llama3\nPARAMETER stop \"<|eot|>\"\nPARAMETER stop ###").unwrap();
+        assert_eq!(fields["parameters"]["stop"], serde_json::json!(["<|eot|>", "###"]));
+    }
+
+    #[test]
+    fn parameters_get_number_and_bool_types() {
+        let modelfile = "FROM llama3\nPARAMETER num_ctx 8192\nPARAMETER temperature 0.7\nPARAMETER low_vram true\nPARAMETER seed abc";
+        let parameters = &modelfile_create_fields(modelfile).unwrap()["parameters"];
+        assert_eq!(parameters["num_ctx"], serde_json::json!(8192));
+        assert_eq!(parameters["temperature"], serde_json::json!(0.7));
+        assert_eq!(parameters["low_vram"], serde_json::json!(true));
+        assert_eq!(parameters["seed"], serde_json::json!("abc"));
+    }
+
+    #[test]
+    fn a_modelfile_without_from_is_refused() {
+        assert!(modelfile_create_fields("# just a comment\nSYSTEM hi").unwrap_err().contains("FROM"));
+        assert!(modelfile_create_fields("FROM \"\"").is_err());
+    }
+
+    #[test]
+    fn an_unclosed_block_names_its_line() {
+        let error = modelfile_create_fields("FROM llama3\nSYSTEM \"\"\"Be brief\nand kind").unwrap_err();
+        assert_eq!(error, "Line 2: \"\"\" is never closed");
+    }
+
+    #[test]
+    fn adapters_are_refused() {
+        let error = modelfile_create_fields("FROM llama3\nADAPTER ./lora.gguf").unwrap_err();
+        assert!(error.starts_with("Line 2: ADAPTER"), "{}", error);
+    }
+
+    #[test]
+    fn a_blob_path_from_becomes_the_model_name() {
+        let shown = "# Modelfile generated by \"ollama show\"\nFROM /root/.ollama/models/blobs/sha256-6a0746a1ec1a\nPARAMETER stop <|eot|>";
+        assert_eq!(
+            editable_modelfile(shown, "llama3:latest"),
+            "# Modelfile generated by \"ollama show\"\nFROM llama3:latest\nPARAMETER stop <|eot|>"
+        );
+        assert_eq!(editable_modelfile("FROM llama3:8b", "mine:latest"), "FROM llama3:8b");
+    }
+
+    #[test]
+    fn a_name_without_a_tag_means_latest() {
+        assert!(same_model("llama3", "llama3:latest"));
+        assert!(same_model("llama3:latest", "llama3:latest"));
+        assert!(same_model("llama3:8b", "llama3:8b"));
+        assert!(!same_model("llama3", "llama3:8b"));
+        assert!(!same_model("llama3:latest", "llama3"));
+        assert!(!same_model("llama", "llama3:latest"));
+    }
+}
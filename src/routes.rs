@@ -0,0 +1,40 @@
+//! HTTP paths used by both the axum router and the frontend, plus the
+//! upstream Ollama endpoints the server calls. Server functions are
+//! registered by Leptos and don't appear here.
+
+/// `POST`: chat with a model, answered as Server-Sent Events
+pub const STREAM: &str = "/api/stream";
+
+/// `POST`: stop a `/api/stream` reply, given `{"request_id"}`; 404 if it isn't running
+pub const STREAM_CANCEL: &str = "/api/stream/cancel";
+
+/// `POST`: chat with a model, answered with the whole reply as JSON
+pub const CHAT: &str = "/chat";
+
+/// `GET`: installed models as JSON
+pub const MODELS: &str = "/models";
+
+/// `GET`: Prometheus metrics, only served when `UI_METRICS` is enabled
+pub const METRICS: &str = "/metrics";
+
+/// `GET`: liveness check, always served without auth
+pub const HEALTH: &str = "/health";
+
+/// `POST`: exchange `UI_AUTH_TOKEN` for a session cookie (form field `token`)
+pub const LOGIN: &str = "/login";
+
+/// Compiled frontend assets (wasm, JS, CSS)
+pub const PKG: &str = "/pkg";
+
+/// Ollama API endpoints, relative to the configured Ollama host
+pub mod ollama {
+    pub const CHAT: &str = "/api/chat";
+    pub const CREATE: &str = "/api/create";
+    pub const DELETE: &str = "/api/delete";
+    pub const GENERATE: &str = "/api/generate";
+    pub const PULL: &str = "/api/pull";
+    pub const PS: &str = "/api/ps";
+    pub const SHOW: &str = "/api/show";
+    pub const TAGS: &str = "/api/tags";
+    pub const VERSION: &str = "/api/version";
+}